@@ -0,0 +1,49 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use sivasbus::benchmark::{extract_line_id, extract_lines, extract_stations, extract_token};
+use std::hint::black_box;
+
+/// A line page about the size of the real ones, around 50 KB, with the
+/// line menu, the verification token, the stations and the bus request.
+fn line_page() -> String {
+    let menu: String = (1..=60)
+        .map(|n| format!(r#"<li><a href="/hat/{n}A">{n}A - Otogar Merkez Kampüs</a></li>"#))
+        .collect();
+    let stations: Vec<String> = (0..300)
+        .map(|n| {
+            format!(
+                r#"{{"linko": "/Akilli-Durak/{id}", "durakAd": "Cumhuriyet Meydanı {n}", "durakLat": "39.{lat:04}", "durakLng": "37.{long:04}"}}"#,
+                id = 1000 + n,
+                lat = 7400 + n,
+                long = 100 + n,
+            )
+        })
+        .collect();
+    let filler = "<div class=\"row\"><p>Sivas Belediyesi Ulaşım Hizmetleri</p></div>".repeat(120);
+    format!(
+        r#"<!DOCTYPE html><html><head><title>Hat 7A</title></head><body>
+<nav><ul>{menu}</ul></nav>{filler}
+<form><input name="__RequestVerificationToken" type="hidden" value="CfDJ8NrAkS0YZ9ZJpA1q7YbLq"></form>
+<script>var duraks = [{stations}];
+$.post('/aractekrar', {{ hgID: 42 }});</script></body></html>"#,
+        stations = stations.join(", "),
+    )
+}
+
+fn parsing(c: &mut Criterion) {
+    let page = line_page();
+    c.bench_function("extract_stations", |b| {
+        b.iter(|| extract_stations(black_box(&page)))
+    });
+    c.bench_function("extract_lines", |b| {
+        b.iter(|| extract_lines(black_box(&page)))
+    });
+    c.bench_function("extract_token", |b| {
+        b.iter(|| extract_token(black_box(&page)))
+    });
+    c.bench_function("extract_line_id", |b| {
+        b.iter(|| extract_line_id(black_box(&page)))
+    });
+}
+
+criterion_group!(benches, parsing);
+criterion_main!(benches);
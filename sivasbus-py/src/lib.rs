@@ -0,0 +1,131 @@
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use serde::Serialize;
+use tokio::runtime::Runtime;
+
+use sivasbus::{Client, Error, ErrorKind, StationId};
+
+create_exception!(sivasbus, SivasbusError, PyException);
+create_exception!(sivasbus, NetworkError, SivasbusError);
+create_exception!(sivasbus, ParseError, SivasbusError);
+create_exception!(sivasbus, TokenMissingError, SivasbusError);
+create_exception!(sivasbus, NotFoundError, SivasbusError);
+create_exception!(sivasbus, UnavailableError, SivasbusError);
+
+/// The Python exception for `error`'s kind, with its causes in the message.
+fn to_py_err(error: Error) -> PyErr {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(&error);
+    while let Some(cause) = source {
+        message.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+
+    match error.kind() {
+        ErrorKind::Network => NetworkError::new_err(message),
+        ErrorKind::Parse => ParseError::new_err(message),
+        ErrorKind::TokenMissing => TokenMissingError::new_err(message),
+        ErrorKind::NotFound => NotFoundError::new_err(message),
+        ErrorKind::Unavailable => UnavailableError::new_err(message),
+    }
+}
+
+fn to_python<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).map_err(|error| to_py_err(error.into()))?;
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+#[pyclass(name = "Client")]
+struct PyClient {
+    client: Client,
+    runtime: Runtime,
+}
+
+impl PyClient {
+    /// Runs `future` to completion without holding the GIL.
+    fn block_on<T: Send>(
+        &self,
+        py: Python<'_>,
+        future: impl Future<Output = Result<T, Error>> + Send,
+    ) -> PyResult<T> {
+        py.detach(|| self.runtime.block_on(future))
+            .map_err(to_py_err)
+    }
+}
+
+#[pymethods]
+impl PyClient {
+    /// A client for the live site, or for the one at `base_url`.
+    #[new]
+    #[pyo3(signature = (base_url=None))]
+    fn new(base_url: Option<&str>) -> PyResult<PyClient> {
+        let mut builder = Client::builder();
+        if let Some(base_url) = base_url {
+            builder = builder.base_url(base_url);
+        }
+        Ok(PyClient {
+            client: builder.build().map_err(to_py_err)?,
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?,
+        })
+    }
+
+    fn get_lines<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let lines = self.block_on(py, self.client.get_lines())?;
+        to_python(py, &lines)
+    }
+
+    fn get_all_stations<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let stations = self.block_on(py, self.client.get_all_stations())?;
+        to_python(py, &stations)
+    }
+
+    fn get_stations<'py>(&self, py: Python<'py>, line: &str) -> PyResult<Bound<'py, PyAny>> {
+        let stations = self.block_on(py, self.client.get_stations(line))?;
+        to_python(py, &stations)
+    }
+
+    fn get_line_buses<'py>(&self, py: Python<'py>, line: &str) -> PyResult<Bound<'py, PyAny>> {
+        let buses = self.block_on(py, self.client.get_line_buses(line))?;
+        to_python(py, &buses)
+    }
+
+    fn get_station_buses<'py>(&self, py: Python<'py>, station: i32) -> PyResult<Bound<'py, PyAny>> {
+        let buses = self.block_on(py, self.client.get_station_buses(StationId(station)))?;
+        to_python(py, &buses)
+    }
+}
+
+/// Blocking Python bindings for the Sivas live bus data scraper.
+///
+/// Results are plain dicts and lists with the same shape as the crate's
+/// serde JSON output.
+///
+/// ```python
+/// import sivasbus
+///
+/// client = sivasbus.Client()
+/// for line in client.get_lines():
+///     print(line["id"], line["human_name"])
+///
+/// try:
+///     arrivals = client.get_station_buses(10)
+/// except sivasbus.NetworkError as e:
+///     print("site unreachable:", e)
+/// ```
+#[pymodule]
+#[pyo3(name = "sivasbus")]
+fn sivasbus_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_class::<PyClient>()?;
+    m.add("SivasbusError", py.get_type::<SivasbusError>())?;
+    m.add("NetworkError", py.get_type::<NetworkError>())?;
+    m.add("ParseError", py.get_type::<ParseError>())?;
+    m.add("TokenMissingError", py.get_type::<TokenMissingError>())?;
+    m.add("NotFoundError", py.get_type::<NotFoundError>())?;
+    m.add("UnavailableError", py.get_type::<UnavailableError>())?;
+
+    Ok(())
+}
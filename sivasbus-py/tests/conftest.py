@@ -0,0 +1,69 @@
+"""A local stand-in for the municipality's site, with one line and its stops."""
+
+import http.server
+import json
+import threading
+
+import pytest
+
+TOKEN = '<input name="__RequestVerificationToken" type="hidden" value="tok123">'
+DURAKS = [
+    {"linko": "/Akilli-Durak/10", "durakAd": "Cumhuriyet Meydanı", "durakLat": "39.7505", "durakLng": "37.0150"},
+    {"linko": "/Akilli-Durak/11", "durakAd": "Otogar", "durakLat": "39.7700", "durakLng": "37.0400"},
+]
+HOME = '<html><body><a href="/hat/1">1 - Otogar Merkez</a></body></html>'
+LINE_PAGE = (
+    "<html><body>" + TOKEN
+    + "<script>var duraks = " + json.dumps(DURAKS) + ";\n"
+    + "$.post('/aractekrar', { hgID: 42 });</script></body></html>"
+)
+LINE_BUSES = [{"aracPlaka": "58 AC 123", "mevcutlat": "39.7510", "mevcutlng": "37.0160"}]
+STATION_BUSES = [{"plaka": "58 AC 123", "sure": 4}]
+
+
+class Handler(http.server.BaseHTTPRequestHandler):
+    def send(self, body, content_type="text/html; charset=utf-8", status=200):
+        body = body.encode()
+        self.send_response(status)
+        self.send_header("Content-Type", content_type)
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def do_GET(self):
+        if self.path == "/":
+            self.send(HOME)
+        elif self.path == "/hat/999":
+            self.send_response(302)
+            self.send_header("Location", "/Hata/404")
+            self.send_header("Content-Length", "0")
+            self.end_headers()
+        elif self.path.startswith("/hat/"):
+            self.send(LINE_PAGE)
+        elif self.path == "/Akilli-Duraklar-Harita":
+            self.send("<script>var duraks = " + json.dumps(DURAKS) + ";</script>")
+        elif self.path.startswith("/Akilli-Durak/"):
+            self.send(TOKEN)
+        else:
+            self.send("not found", status=404)
+
+    def do_POST(self):
+        self.rfile.read(int(self.headers.get("Content-Length", 0)))
+        if self.path == "/aractekrar":
+            self.send(json.dumps(LINE_BUSES), "application/json")
+        elif self.path == "/durakTekrar":
+            self.send(json.dumps(STATION_BUSES), "application/json")
+        else:
+            self.send("not found", status=404)
+
+    def log_message(self, *args):
+        pass
+
+
+@pytest.fixture(scope="session")
+def site():
+    server = http.server.ThreadingHTTPServer(("127.0.0.1", 0), Handler)
+    thread = threading.Thread(target=server.serve_forever, daemon=True)
+    thread.start()
+    yield "http://127.0.0.1:%d" % server.server_address[1]
+    server.shutdown()
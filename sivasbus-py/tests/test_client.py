@@ -0,0 +1,57 @@
+"""Smoke tests for the bindings, run with `maturin develop && pytest`."""
+
+import socket
+
+import pytest
+
+import sivasbus
+
+
+def unused_url():
+    with socket.socket() as sock:
+        sock.bind(("127.0.0.1", 0))
+        return "http://127.0.0.1:%d" % sock.getsockname()[1]
+
+
+def test_get_lines(site):
+    lines = sivasbus.Client(site).get_lines()
+    assert lines == [{"id": "1", "human_name": "1 - Otogar Merkez"}]
+
+
+def test_get_stations(site):
+    stations = sivasbus.Client(site).get_stations("1")
+    assert [station["id"] for station in stations] == [10, 11]
+    assert stations[0]["human_name"] == "Cumhuriyet Meydanı"
+    assert stations[0]["coords"] == {"lat": 39.7505, "long": 37.015}
+
+
+def test_get_all_stations(site):
+    stations = sivasbus.Client(site).get_all_stations()
+    assert [station["id"] for station in stations] == [10, 11]
+
+
+def test_get_line_buses(site):
+    buses = sivasbus.Client(site).get_line_buses("1")
+    assert [bus["license_plate"] for bus in buses] == ["58 AC 123"]
+    assert buses[0]["coords"] == {"lat": 39.751, "long": 37.016}
+
+
+def test_get_station_buses(site):
+    buses = sivasbus.Client(site).get_station_buses(10)
+    assert [bus["license_plate"] for bus in buses] == ["58 AC 123"]
+
+
+def test_unknown_line_raises_not_found(site):
+    with pytest.raises(sivasbus.NotFoundError):
+        sivasbus.Client(site).get_line_buses("999")
+
+
+def test_unreachable_site_raises_network_error():
+    with pytest.raises(sivasbus.NetworkError) as raised:
+        sivasbus.Client(unused_url()).get_lines()
+    assert isinstance(raised.value, sivasbus.SivasbusError)
+
+
+def test_exceptions_share_a_base():
+    for name in ["NetworkError", "ParseError", "TokenMissingError", "NotFoundError", "UnavailableError"]:
+        assert issubclass(getattr(sivasbus, name), sivasbus.SivasbusError)
@@ -0,0 +1,101 @@
+//! Service announcements ("duyurular") from the homepage.
+//!
+//! The site has no structured feed for these, so parsing is best effort:
+//!
+//! - Announcements are elements with the `duyuru`, `duyurular` item or
+//!   `announcement` classes. Anything else on the page is ignored.
+//! - The title is the first heading, `strong` or link text. Announcements
+//!   without one are skipped.
+//! - The body is the text of the paragraphs, or of the whole element minus
+//!   the title if there are none.
+//! - The date comes from a `time` element's `datetime` attribute, or else
+//!   the first `dd.mm.yyyy` date in the text. It's `None` when neither is
+//!   present or parseable.
+
+use chrono::NaiveDate;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Result};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub title: String,
+    pub body: String,
+    pub date: Option<NaiveDate>,
+}
+
+fn squash<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn extract_date(elem: ElementRef, date: &Regex) -> Option<NaiveDate> {
+    let time_selector = Selector::parse("time[datetime]").unwrap();
+    if let Some(time) = elem.select(&time_selector).next() {
+        let datetime = time.attr("datetime")?;
+        if let Ok(parsed) = NaiveDate::parse_from_str(datetime.get(..10)?, "%Y-%m-%d") {
+            return Some(parsed);
+        }
+    }
+
+    let text = squash(elem.text());
+    let captures = date.captures(&text)?;
+    NaiveDate::from_ymd_opt(
+        captures[3].parse().ok()?,
+        captures[2].parse().ok()?,
+        captures[1].parse().ok()?,
+    )
+}
+
+fn extract_alert(elem: ElementRef, date: &Regex) -> Option<Alert> {
+    let title_selector = Selector::parse("h1, h2, h3, h4, h5, h6, strong, a").unwrap();
+    let body_selector = Selector::parse("p").unwrap();
+
+    let title = squash(elem.select(&title_selector).next()?.text());
+    if title.is_empty() {
+        return None;
+    }
+
+    let paragraphs: Vec<String> = elem
+        .select(&body_selector)
+        .map(|p| squash(p.text()))
+        .filter(|text| !text.is_empty())
+        .collect();
+    let body = if paragraphs.is_empty() {
+        let text = squash(elem.text());
+        text.strip_prefix(&title)
+            .unwrap_or(&text)
+            .trim()
+            .to_string()
+    } else {
+        paragraphs.join("\n")
+    };
+
+    Some(Alert {
+        title,
+        body,
+        date: extract_date(elem, date),
+    })
+}
+
+pub fn extract_alerts(doc: &str) -> Vec<Alert> {
+    let selector = Selector::parse(".duyuru, .duyurular li, .announcement").unwrap();
+    let date = Regex::new(r"\b(\d{1,2})\.(\d{1,2})\.(\d{4})\b").unwrap();
+
+    Html::parse_document(doc)
+        .select(&selector)
+        .filter_map(|elem| extract_alert(elem, &date))
+        .collect()
+}
+
+impl Client {
+    /// Fetches the announcements on the homepage, see the [module
+    /// docs](self) for what's picked up.
+    pub async fn get_alerts(&self) -> Result<Vec<Alert>> {
+        let doc = self.get_document("/".to_string()).await?;
+        Ok(extract_alerts(&doc))
+    }
+}
@@ -0,0 +1,675 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+use crate::geo::snap_to_station;
+use crate::timetable::Schedule;
+use crate::{
+    Client, Coords, LineBus, NetworkSnapshot, Result, Station, StationBus, StationId,
+    normalize_plate,
+};
+
+/// How early a bus can be and still count as on time.
+const ON_TIME_EARLY: TimeDelta = TimeDelta::minutes(1);
+/// How late a bus can be and still count as on time.
+const ON_TIME_LATE: TimeDelta = TimeDelta::minutes(5);
+
+/// Hours with the number of distinct buses seen in them, busiest first.
+/// Buses are told apart by [`normalize_plate`], so "58 AB 123" and
+/// "58AB123" count once.
+pub fn estimate_peak_hours(snapshots: &[(NetworkSnapshot, u8)]) -> Vec<(u8, usize)> {
+    let mut plates_by_hour: HashMap<u8, HashSet<String>> = HashMap::new();
+    for (snapshot, hour) in snapshots {
+        let plates = plates_by_hour.entry(*hour).or_default();
+        for bus in snapshot.buses.values().flatten() {
+            plates.insert(normalize_plate(&bus.license_plate));
+        }
+    }
+
+    let mut hours: Vec<(u8, usize)> = plates_by_hour
+        .into_iter()
+        .map(|(hour, plates)| (hour, plates.len()))
+        .collect();
+    hours.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    hours
+}
+
+/// Mean gap between successive arrivals, `None` for fewer than two buses.
+pub fn average_headway(buses: &[StationBus]) -> Option<Duration> {
+    let first = buses.iter().map(|bus| bus.arrive_time).min()?;
+    let last = buses.iter().map(|bus| bus.arrive_time).max()?;
+    let gaps = u32::try_from(buses.len()).ok()?.checked_sub(1)?;
+    if gaps == 0 {
+        return None;
+    }
+    Some((last - first) / gaps)
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Adherence {
+    Early,
+    OnTime,
+    Late,
+    /// There are no scheduled times to compare with.
+    Unscheduled,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AdherenceReport {
+    pub license_plate: String,
+    /// When the bus is expected, from its live arrival time.
+    pub expected: NaiveTime,
+    /// The scheduled time closest to `expected`.
+    pub scheduled: Option<NaiveTime>,
+    /// How late the bus is against `scheduled`, negative when early.
+    pub delta: Option<TimeDelta>,
+    pub adherence: Adherence,
+}
+
+/// `a - b` on a 24 hour clock, so a bus due at 00:02 is 4 minutes late for
+/// 23:58 rather than almost a day early.
+fn clock_delta(a: NaiveTime, b: NaiveTime) -> TimeDelta {
+    let day = TimeDelta::days(1);
+    let delta = (a - b + day / 2)
+        .num_seconds()
+        .rem_euclid(day.num_seconds());
+    TimeDelta::seconds(delta) - day / 2
+}
+
+/// Compares the buses due at a stop, as fetched at `now`, with the stop's
+/// schedule, matching each bus to the nearest scheduled time.
+///
+/// Buses are on time from [`ON_TIME_EARLY`] early to [`ON_TIME_LATE`] late.
+/// With an empty schedule every bus is [`Adherence::Unscheduled`].
+pub fn schedule_adherence(
+    buses: &[StationBus],
+    schedule: &Schedule,
+    now: NaiveTime,
+) -> Vec<AdherenceReport> {
+    buses
+        .iter()
+        .map(|bus| {
+            let expected = now + TimeDelta::from_std(bus.arrive_time).unwrap_or(TimeDelta::zero());
+            let nearest = schedule
+                .times
+                .iter()
+                .map(|&scheduled| (scheduled, clock_delta(expected, scheduled)))
+                .min_by_key(|(_, delta)| delta.abs());
+            let adherence = match nearest {
+                None => Adherence::Unscheduled,
+                Some((_, delta)) if delta < -ON_TIME_EARLY => Adherence::Early,
+                Some((_, delta)) if delta > ON_TIME_LATE => Adherence::Late,
+                Some(_) => Adherence::OnTime,
+            };
+            AdherenceReport {
+                license_plate: bus.license_plate.clone(),
+                expected,
+                scheduled: nearest.map(|(scheduled, _)| scheduled),
+                delta: nearest.map(|(_, delta)| delta),
+                adherence,
+            }
+        })
+        .collect()
+}
+
+/// Buses moving faster than this between two records are assumed to have
+/// a GPS glitch.
+const MAX_SPEED_KMH: f64 = 120.0;
+/// Records further apart than this count as a gap in transmission.
+const MAX_RECORD_GAP: TimeDelta = TimeDelta::minutes(5);
+
+/// Where a bus was at a point in time, as recorded from polls.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PositionRecord {
+    pub timestamp: DateTime<Utc>,
+    /// The line the bus was fetched from, if known.
+    #[serde(default)]
+    pub line: Option<String>,
+    pub license_plate: String,
+    pub coords: Coords,
+}
+
+impl PositionRecord {
+    pub fn from_bus(timestamp: DateTime<Utc>, line: &str, bus: &LineBus) -> PositionRecord {
+        PositionRecord {
+            timestamp,
+            line: Some(line.to_string()),
+            license_plate: bus.license_plate.clone(),
+            coords: bus.coords.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DwellSummary {
+    pub samples: usize,
+    pub mean: Duration,
+    pub median: Duration,
+    pub p90: Duration,
+}
+
+impl DwellSummary {
+    fn new(mut dwells: Vec<Duration>) -> Option<DwellSummary> {
+        if dwells.is_empty() {
+            return None;
+        }
+        dwells.sort();
+        let samples = dwells.len();
+        let median = match samples % 2 {
+            0 => (dwells[samples / 2 - 1] + dwells[samples / 2]) / 2,
+            _ => dwells[samples / 2],
+        };
+        // Nearest rank.
+        let p90 = dwells[(samples * 9).div_ceil(10) - 1];
+        Some(DwellSummary {
+            samples,
+            mean: dwells.iter().sum::<Duration>() / samples as u32,
+            median,
+            p90,
+        })
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DwellStat {
+    pub station: i32,
+    /// Stops up to the layover cutoff.
+    pub dwell: Option<DwellSummary>,
+    /// Stops longer than the layover cutoff, usually at a terminus.
+    pub layover: Option<DwellSummary>,
+}
+
+/// How long buses stay at each station, from their recorded positions.
+///
+/// A stop lasts from the first to the last record of a bus within
+/// `radius_m` of a station, so a bus seen there only once counts as no
+/// dwell at all and stops shorter than the polling interval can't be told
+/// apart. Stops longer than `layover_cutoff` are summarised separately.
+///
+/// Stations without any stops are left out; the rest keep the order of
+/// `stations`.
+pub fn analyze_dwell(
+    records: impl Iterator<Item = PositionRecord>,
+    stations: &[Station],
+    radius_m: f64,
+    layover_cutoff: Duration,
+) -> Vec<DwellStat> {
+    let mut tracks: HashMap<String, Vec<PositionRecord>> = HashMap::new();
+    for record in records {
+        tracks
+            .entry(normalize_plate(&record.license_plate))
+            .or_default()
+            .push(record);
+    }
+
+    let mut dwells: BTreeMap<i32, (Vec<Duration>, Vec<Duration>)> = BTreeMap::new();
+    let mut add = |station: i32, from: DateTime<Utc>, to: DateTime<Utc>| {
+        let dwell = (to - from).to_std().unwrap_or_default();
+        let (stops, layovers) = dwells.entry(station).or_default();
+        match dwell > layover_cutoff {
+            true => layovers.push(dwell),
+            false => stops.push(dwell),
+        }
+    };
+    for track in tracks.values_mut() {
+        track.sort_by_key(|record| record.timestamp);
+        let mut visit: Option<(i32, DateTime<Utc>, DateTime<Utc>)> = None;
+        for record in track.iter() {
+            let station = snap_to_station(stations, &record.coords, radius_m).map(|s| s.id);
+            match (&mut visit, station) {
+                (Some((current, _, last)), Some(station)) if *current == station => {
+                    *last = record.timestamp;
+                    continue;
+                }
+                (Some((current, first, last)), _) => add(*current, *first, *last),
+                (None, _) => {}
+            }
+            visit = station.map(|station| (station, record.timestamp, record.timestamp));
+        }
+        if let Some((station, first, last)) = visit {
+            add(station, first, last);
+        }
+    }
+
+    stations
+        .iter()
+        .filter_map(|station| {
+            let (stops, layovers) = dwells.remove(&station.id)?;
+            Some(DwellStat {
+                station: station.id,
+                dwell: DwellSummary::new(stops),
+                layover: DwellSummary::new(layovers),
+            })
+        })
+        .collect()
+}
+
+/// How much one bus was used on one day.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct VehicleDay {
+    pub license_plate: String,
+    /// Straight-line distance between records, leaving out GPS glitches.
+    pub distance_km: f64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Seconds from the first to the last valid record.
+    pub active_secs: u64,
+    /// `active_secs` as a fraction of the day.
+    pub utilization: f64,
+    /// Times the bus went quiet for longer than five minutes.
+    pub gaps: usize,
+    /// Lines the bus was seen on, sorted.
+    pub lines: Vec<String>,
+}
+
+/// A [`VehicleDay`] with its lines joined by spaces, as one CSV row.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct VehicleDayRow {
+    pub license_plate: String,
+    pub distance_km: f64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub active_secs: u64,
+    pub utilization: f64,
+    pub gaps: usize,
+    pub lines: String,
+}
+
+impl From<&VehicleDay> for VehicleDayRow {
+    fn from(day: &VehicleDay) -> VehicleDayRow {
+        VehicleDayRow {
+            license_plate: day.license_plate.clone(),
+            distance_km: day.distance_km,
+            first_seen: day.first_seen,
+            last_seen: day.last_seen,
+            active_secs: day.active_secs,
+            utilization: day.utilization,
+            gaps: day.gaps,
+            lines: day.lines.join(" "),
+        }
+    }
+}
+
+/// Writes `days` to `writer` as CSV with a header, one [`VehicleDayRow`]
+/// per bus.
+#[cfg(feature = "csv")]
+pub fn write_vehicle_days_csv(days: &[VehicleDay], writer: impl std::io::Write) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for day in days {
+        writer.serialize(VehicleDayRow::from(day))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The date in Turkey, which has kept UTC+3 all year since 2016.
+pub(crate) fn turkish_date(time: DateTime<Utc>) -> NaiveDate {
+    (time + TimeDelta::hours(3)).date_naive()
+}
+
+struct VehicleState {
+    plate: String,
+    first: DateTime<Utc>,
+    last: PositionRecord,
+    distance_m: f64,
+    gaps: usize,
+    lines: BTreeSet<String>,
+}
+
+/// Distance, active time and gaps of every bus on `day`, a date in Turkish
+/// time, going through `records` once and keeping only one record per bus.
+///
+/// Records of a bus must come in time order, as they were recorded;
+/// records with invalid coordinates or out of order are skipped. A leg
+/// faster than 120 km/h is a GPS jump and adds no distance, in either
+/// direction when the position jumps back. Sorted by plate.
+pub fn vehicle_utilization(
+    records: impl IntoIterator<Item = PositionRecord>,
+    day: NaiveDate,
+) -> Vec<VehicleDay> {
+    let mut vehicles: HashMap<String, VehicleState> = HashMap::new();
+    for record in records {
+        if turkish_date(record.timestamp) != day || !record.coords.is_valid() {
+            continue;
+        }
+        let line = record.line.clone();
+        let state = match vehicles.entry(normalize_plate(&record.license_plate)) {
+            Entry::Vacant(entry) => entry.insert(VehicleState {
+                plate: record.license_plate.clone(),
+                first: record.timestamp,
+                last: record,
+                distance_m: 0.0,
+                gaps: 0,
+                lines: BTreeSet::new(),
+            }),
+            Entry::Occupied(entry) => {
+                let state = entry.into_mut();
+                let elapsed = record.timestamp - state.last.timestamp;
+                if elapsed <= TimeDelta::zero() {
+                    continue;
+                }
+                if elapsed > MAX_RECORD_GAP {
+                    state.gaps += 1;
+                }
+                let distance = state.last.coords.distance_m(&record.coords);
+                let hours = elapsed.as_seconds_f64() / 3600.0;
+                if distance / 1000.0 <= MAX_SPEED_KMH * hours {
+                    state.distance_m += distance;
+                }
+                state.last = record;
+                state
+            }
+        };
+        state.lines.extend(line);
+    }
+
+    let day_length = TimeDelta::days(1).as_seconds_f64();
+    let mut days: Vec<VehicleDay> = vehicles
+        .into_values()
+        .map(|state| {
+            let active = state.last.timestamp - state.first;
+            VehicleDay {
+                license_plate: state.plate,
+                distance_km: state.distance_m / 1000.0,
+                first_seen: state.first,
+                last_seen: state.last.timestamp,
+                active_secs: active.num_seconds().max(0) as u64,
+                utilization: active.as_seconds_f64() / day_length,
+                gaps: state.gaps,
+                lines: state.lines.into_iter().collect(),
+            }
+        })
+        .collect();
+    days.sort_by(|a, b| a.license_plate.cmp(&b.license_plate));
+    days
+}
+
+impl Client {
+    pub async fn station_headway(&self, station: StationId) -> Result<Option<Duration>> {
+        Ok(average_headway(&self.get_station_buses(station).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stations() -> Vec<Station> {
+        [
+            (10, 39.7505, 37.0150),
+            (11, 39.7520, 37.0180),
+            (12, 39.7700, 37.0400),
+        ]
+        .into_iter()
+        .map(|(id, lat, long)| Station {
+            id,
+            human_name: format!("Station {id}"),
+            coords: Coords { lat, long },
+        })
+        .collect()
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_760_000_000 + secs, 0).unwrap()
+    }
+
+    /// A record of `plate` `secs` into the track, next to `station` or out
+    /// on the road with `None`.
+    fn record(plate: &str, secs: i64, station: Option<i32>) -> PositionRecord {
+        let coords = match station {
+            // About 10 m north of the station.
+            Some(id) => {
+                let station = stations().into_iter().find(|s| s.id == id).unwrap();
+                Coords {
+                    lat: station.coords.lat + 0.0001,
+                    long: station.coords.long,
+                }
+            }
+            None => Coords {
+                lat: 39.7600,
+                long: 37.0300,
+            },
+        };
+        PositionRecord {
+            timestamp: at(secs),
+            line: Some("1".to_string()),
+            license_plate: plate.to_string(),
+            coords,
+        }
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn dwell(records: Vec<PositionRecord>) -> Vec<DwellStat> {
+        analyze_dwell(records.into_iter(), &stations(), 50.0, secs(600))
+    }
+
+    #[test]
+    fn dwell_statistics_per_station() {
+        let records = vec![
+            // 60 s at 10, on the road, 90 s at 11, a 20 minute layover at 12.
+            record("58 AC 123", 0, Some(10)),
+            record("58 AC 123", 30, Some(10)),
+            record("58 AC 123", 60, Some(10)),
+            record("58 AC 123", 90, None),
+            record("58 AC 123", 120, Some(11)),
+            record("58 AC 123", 210, Some(11)),
+            record("58 AC 123", 240, None),
+            record("58 AC 123", 300, Some(12)),
+            record("58 AC 123", 1500, Some(12)),
+            // 120 s at 10, out of order and with its plate spelled two ways.
+            record("58AB999", 120, Some(10)),
+            record("58 AB 999", 0, Some(10)),
+            record("58 AB 999", 150, None),
+            // 30 s at 10.
+            record("58 KP 007", 0, Some(10)),
+            record("58 KP 007", 30, Some(10)),
+        ];
+        let stats = dwell(records);
+
+        assert_eq!(
+            stats,
+            [
+                DwellStat {
+                    station: 10,
+                    dwell: Some(DwellSummary {
+                        samples: 3,
+                        mean: secs(70),
+                        median: secs(60),
+                        p90: secs(120),
+                    }),
+                    layover: None,
+                },
+                DwellStat {
+                    station: 11,
+                    dwell: Some(DwellSummary {
+                        samples: 1,
+                        mean: secs(90),
+                        median: secs(90),
+                        p90: secs(90),
+                    }),
+                    layover: None,
+                },
+                DwellStat {
+                    station: 12,
+                    dwell: None,
+                    layover: Some(DwellSummary {
+                        samples: 1,
+                        mean: secs(1200),
+                        median: secs(1200),
+                        p90: secs(1200),
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_stops_are_separate_samples() {
+        // Two visits to 10 with a trip in between, and a drive past 11.
+        let records = vec![
+            record("58 AC 123", 0, Some(10)),
+            record("58 AC 123", 40, Some(10)),
+            record("58 AC 123", 60, Some(11)),
+            record("58 AC 123", 90, None),
+            record("58 AC 123", 900, Some(10)),
+            record("58 AC 123", 920, Some(10)),
+        ];
+        let stats = dwell(records);
+
+        let at_10 = stats[0].dwell.as_ref().unwrap();
+        assert_eq!((stats[0].station, at_10.samples), (10, 2));
+        assert_eq!((at_10.mean, at_10.median), (secs(30), secs(30)));
+        assert_eq!(at_10.p90, secs(40));
+        // Seen at 11 only once, so it didn't stop there.
+        assert_eq!(stats[1].dwell.as_ref().unwrap().mean, secs(0));
+    }
+
+    #[test]
+    fn median_of_an_even_count_and_p90() {
+        let summary = DwellSummary::new((1..=10).map(|n| secs(n * 10)).collect()).unwrap();
+        assert_eq!(summary.median, secs(55));
+        assert_eq!(summary.p90, secs(90));
+        assert_eq!(summary.mean, secs(55));
+        assert_eq!(DwellSummary::new(Vec::new()), None);
+    }
+
+    #[test]
+    fn dwell_stats_serialize() {
+        let stats = dwell(vec![
+            record("58 AC 123", 0, Some(10)),
+            record("58 AC 123", 45, Some(10)),
+        ]);
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json[0]["station"], 10);
+        assert_eq!(json[0]["dwell"]["samples"], 1);
+        assert_eq!(json[0]["dwell"]["median"]["secs"], 45);
+        assert_eq!(json[0]["layover"], serde_json::Value::Null);
+    }
+
+    /// A record of `plate` on `line`, `secs` into the day in Turkey.
+    fn sample(plate: &str, line: &str, secs: i64, lat: f64, long: f64) -> PositionRecord {
+        // Midnight starting 2025-10-10 in Turkey.
+        let midnight = DateTime::from_timestamp(1_760_043_600, 0).unwrap();
+        PositionRecord {
+            timestamp: midnight + TimeDelta::seconds(secs),
+            line: Some(line.to_string()),
+            license_plate: plate.to_string(),
+            coords: Coords { lat, long },
+        }
+    }
+
+    fn leg_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+        let a = Coords {
+            lat: a.0,
+            long: a.1,
+        };
+        a.distance_m(&Coords {
+            lat: b.0,
+            long: b.1,
+        }) / 1000.0
+    }
+
+    fn utilization_day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 10, 10).unwrap()
+    }
+
+    #[test]
+    fn utilization_skips_dropouts_and_teleports() {
+        const HOUR: i64 = 3600;
+        let route = [(39.7505, 37.0150), (39.7520, 37.0180), (39.7600, 37.0300)];
+        let records = vec![
+            // Drives the route from 06:00, drops out for 20 minutes, jumps
+            // to Ankara and back within a minute, and is seen again at
+            // 08:00.
+            sample("58 AC 123", "1", 6 * HOUR, route[0].0, route[0].1),
+            sample("58 AC 123", "1", 6 * HOUR + 60, route[1].0, route[1].1),
+            sample("58 AC 123", "1", 6 * HOUR + 1260, route[2].0, route[2].1),
+            sample("58 AC 123", "1", 6 * HOUR + 1320, 39.9334, 32.8597),
+            sample("58 AC 123", "1", 6 * HOUR + 1380, route[2].0, route[2].1),
+            sample("58AC123", "1", 8 * HOUR, route[0].0, route[0].1),
+            // Works two lines, with a bad fix and a record from the next day.
+            sample("58 KP 007", "7A", 12 * HOUR, route[0].0, route[0].1),
+            sample("58 KP 007", "7A", 12 * HOUR + 30, 0.0, 0.0),
+            sample("58 KP 007", "1", 18 * HOUR, route[1].0, route[1].1),
+            sample("58 KP 007", "1", 25 * HOUR, route[2].0, route[2].1),
+        ];
+        let days = vehicle_utilization(records, utilization_day());
+        assert_eq!(days.len(), 2);
+
+        let ac = &days[0];
+        assert_eq!(ac.license_plate, "58 AC 123");
+        let driven =
+            leg_km(route[0], route[1]) + leg_km(route[1], route[2]) + leg_km(route[2], route[0]);
+        assert!((ac.distance_km - driven).abs() < 1e-9, "{ac:?}");
+        assert_eq!(ac.gaps, 2);
+        assert_eq!(ac.active_secs, 2 * 3600);
+        assert!((ac.utilization - 2.0 / 24.0).abs() < 1e-12);
+        assert_eq!(ac.lines, ["1"]);
+
+        let kp = &days[1];
+        assert_eq!(kp.license_plate, "58 KP 007");
+        assert_eq!(kp.active_secs, 6 * 3600);
+        assert_eq!(kp.gaps, 1);
+        assert_eq!(kp.lines, ["1", "7A"]);
+        assert!((kp.distance_km - leg_km(route[0], route[1])).abs() < 1e-9);
+    }
+
+    #[test]
+    fn utilization_skips_out_of_order_records() {
+        let records = vec![
+            sample("58 AC 123", "1", 3600, 39.7505, 37.0150),
+            sample("58 AC 123", "1", 3660, 39.7520, 37.0180),
+            sample("58 AC 123", "1", 3630, 39.7600, 37.0300),
+        ];
+        let days = vehicle_utilization(records, utilization_day());
+        assert_eq!(days[0].last_seen, sample("", "", 3660, 0.0, 0.0).timestamp);
+        let leg = leg_km((39.7505, 37.0150), (39.7520, 37.0180));
+        assert!((days[0].distance_km - leg).abs() < 1e-9);
+    }
+
+    #[test]
+    fn utilization_serializes_as_ndjson() {
+        let records = vec![
+            sample("58 AC 123", "1", 3600, 39.7505, 37.0150),
+            sample("58 AC 123", "1", 3660, 39.7520, 37.0180),
+        ];
+        let days = vehicle_utilization(records, utilization_day());
+        let line: serde_json::Value =
+            serde_json::from_str(crate::serialise::to_ndjson(&days).trim_end()).unwrap();
+        assert_eq!(line["license_plate"], "58 AC 123");
+        assert_eq!(line["gaps"], 0);
+        assert_eq!(line["lines"], serde_json::json!(["1"]));
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn utilization_writes_as_csv() {
+        let records = vec![
+            sample("58 AC 123", "1", 3600, 39.7505, 37.0150),
+            sample("58 AC 123", "7A", 3660, 39.7520, 37.0180),
+            sample("58 KP 007", "7A", 7200, 39.7700, 37.0400),
+        ];
+        let days = vehicle_utilization(records, utilization_day());
+        let mut csv = Vec::new();
+        write_vehicle_days_csv(&days, &mut csv).unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            rows[0],
+            "license_plate,distance_km,first_seen,last_seen,active_secs,utilization,gaps,lines"
+        );
+        assert_eq!(rows.len(), 3);
+        let ac: Vec<&str> = rows[1].split(',').collect();
+        assert_eq!(ac[0], "58 AC 123");
+        assert_eq!(ac[2], "2025-10-09T22:00:00Z");
+        assert_eq!(&ac[4..], ["60", &(60.0 / 86400.0).to_string(), "0", "1 7A"]);
+        assert!(rows[2].starts_with("58 KP 007,0.0,"), "{}", rows[2]);
+    }
+}
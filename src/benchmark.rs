@@ -0,0 +1,19 @@
+//! The page parsers, exposed for `benches/parsing.rs`. Not a stable API.
+
+use crate::{Line, Result, Station};
+
+pub fn extract_stations(doc: &str) -> Result<Vec<Station>> {
+    crate::extract_stations(doc)
+}
+
+pub fn extract_lines(doc: &str) -> Vec<Line> {
+    crate::extract_lines(doc)
+}
+
+pub fn extract_token(doc: &str) -> Option<String> {
+    crate::extract_token(doc)
+}
+
+pub fn extract_line_id(doc: &str) -> Option<&str> {
+    crate::extract_line_id(doc)
+}
@@ -0,0 +1,408 @@
+//! Bus bunching: buses of the same line running so close together that
+//! they'd better be spread out.
+//!
+//! Buses are placed along their line's [`RouteShape`] and consecutive ones
+//! closer than the threshold are reported. The site doesn't say which way
+//! a bus is heading, so two buses passing each other in opposite
+//! directions look bunched for a moment; [`BunchingMonitor`] only raises
+//! incidents that last for a few polls.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::geo::project_onto_segment;
+use crate::{Coords, Line, LineBus, Station};
+
+/// Buses this close to either end of a route are at a terminus, where
+/// buses wait together by design.
+const TERMINUS_MARGIN_M: f64 = 200.0;
+/// Buses further than this from the route are on a detour or have a bad
+/// position, and can't be placed along it.
+const MAX_OFF_ROUTE_M: f64 = 300.0;
+
+/// The path a line takes, as a polyline with the distance along it to each
+/// point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteShape {
+    points: Vec<Coords>,
+    along_m: Vec<f64>,
+}
+
+impl RouteShape {
+    pub fn new(points: Vec<Coords>) -> RouteShape {
+        let mut along_m = Vec::with_capacity(points.len());
+        let mut total = 0.0;
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                total += points[i - 1].distance_m(point);
+            }
+            along_m.push(total);
+        }
+        RouteShape { points, along_m }
+    }
+
+    /// The straight lines between a line's stations, as the site has no
+    /// actual route shapes.
+    pub fn from_stations(stations: &[Station]) -> RouteShape {
+        RouteShape::new(
+            stations
+                .iter()
+                .map(|station| station.coords.clone())
+                .collect(),
+        )
+    }
+
+    pub fn length_m(&self) -> f64 {
+        self.along_m.last().copied().unwrap_or(0.0)
+    }
+
+    /// How far along the route `coords` is, if it's on the route.
+    pub fn position_m(&self, coords: &Coords) -> Option<f64> {
+        let (along, offset) = (1..self.points.len())
+            .map(|i| {
+                let (t, offset) =
+                    project_onto_segment(coords, &self.points[i - 1], &self.points[i]);
+                let along = self.along_m[i - 1] + t * (self.along_m[i] - self.along_m[i - 1]);
+                (along, offset)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        (offset <= MAX_OFF_ROUTE_M).then_some(along)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BunchingThreshold {
+    /// Buses closer than this many metres along the route are bunched.
+    Distance(f64),
+    /// Buses less than this far apart in time, at the given average speed
+    /// in km/h, are bunched.
+    Time(Duration, f64),
+}
+
+impl BunchingThreshold {
+    fn metres(&self) -> f64 {
+        match *self {
+            BunchingThreshold::Distance(metres) => metres,
+            BunchingThreshold::Time(gap, speed_kmh) => gap.as_secs_f64() * speed_kmh / 3.6,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BunchingIncident {
+    pub line: String,
+    /// Plate of the bus further along the route.
+    pub leading: String,
+    pub trailing: String,
+    pub separation_m: f64,
+    /// The newer of the two buses' GPS reports, when the site sends them.
+    pub observed_at: Option<DateTime<Utc>>,
+}
+
+impl BunchingIncident {
+    fn key(&self) -> (String, String, String) {
+        let (a, b) = match self.leading < self.trailing {
+            true => (&self.leading, &self.trailing),
+            false => (&self.trailing, &self.leading),
+        };
+        (self.line.clone(), a.clone(), b.clone())
+    }
+}
+
+/// Consecutive buses of each line closer together than `threshold` along
+/// the line's shape from `shapes`, keyed by line id.
+///
+/// Lines without a shape, buses off the route and buses at either
+/// terminus are left out.
+pub fn detect_bunching(
+    snapshot: &[(Line, Vec<LineBus>)],
+    shapes: &HashMap<String, RouteShape>,
+    threshold: BunchingThreshold,
+) -> Vec<BunchingIncident> {
+    let mut incidents = Vec::new();
+    for (line, buses) in snapshot {
+        let Some(shape) = shapes.get(&line.id) else {
+            continue;
+        };
+        let mut placed: Vec<(f64, &LineBus)> = buses
+            .iter()
+            .filter_map(|bus| Some((shape.position_m(&bus.coords)?, bus)))
+            .filter(|(along, _)| {
+                *along > TERMINUS_MARGIN_M && *along < shape.length_m() - TERMINUS_MARGIN_M
+            })
+            .collect();
+        placed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for pair in placed.windows(2) {
+            let ((trailing_at, trailing), (leading_at, leading)) = (pair[0], pair[1]);
+            let separation_m = leading_at - trailing_at;
+            if separation_m < threshold.metres() {
+                incidents.push(BunchingIncident {
+                    line: line.id.clone(),
+                    leading: leading.license_plate.clone(),
+                    trailing: trailing.license_plate.clone(),
+                    separation_m,
+                    observed_at: leading.last_update.max(trailing.last_update),
+                });
+            }
+        }
+    }
+    incidents
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BunchingEvent {
+    Raised {
+        incident: BunchingIncident,
+        /// The poll where the pair was first seen bunched.
+        since: DateTime<Utc>,
+    },
+    Cleared {
+        incident: BunchingIncident,
+        since: DateTime<Utc>,
+        /// The poll where the pair was first seen apart again.
+        until: DateTime<Utc>,
+    },
+}
+
+struct Tracked {
+    incident: BunchingIncident,
+    since: DateTime<Utc>,
+    /// Consecutive polls the pair was seen bunched, or apart once raised.
+    streak: usize,
+    raised: bool,
+    apart_since: Option<DateTime<Utc>>,
+}
+
+/// Runs [`detect_bunching`] on each poll and turns the results into
+/// incidents that are raised after a pair is bunched in `polls` polls in a
+/// row, and cleared after it's apart in as many.
+pub struct BunchingMonitor {
+    shapes: HashMap<String, RouteShape>,
+    threshold: BunchingThreshold,
+    polls: usize,
+    tracked: HashMap<(String, String, String), Tracked>,
+}
+
+impl BunchingMonitor {
+    pub fn new(
+        shapes: HashMap<String, RouteShape>,
+        threshold: BunchingThreshold,
+        polls: usize,
+    ) -> BunchingMonitor {
+        BunchingMonitor {
+            shapes,
+            threshold,
+            polls: polls.max(1),
+            tracked: HashMap::new(),
+        }
+    }
+
+    /// Takes the buses polled at `at` and returns the incidents raised or
+    /// cleared by them.
+    pub fn update(
+        &mut self,
+        at: DateTime<Utc>,
+        snapshot: &[(Line, Vec<LineBus>)],
+    ) -> Vec<BunchingEvent> {
+        let mut events = Vec::new();
+        let mut seen = HashSet::new();
+
+        for incident in detect_bunching(snapshot, &self.shapes, self.threshold) {
+            let key = incident.key();
+            seen.insert(key.clone());
+            let tracked = self.tracked.entry(key).or_insert_with(|| Tracked {
+                incident: incident.clone(),
+                since: at,
+                streak: 0,
+                raised: false,
+                apart_since: None,
+            });
+            tracked.incident = incident;
+            if tracked.raised {
+                tracked.streak = 0;
+                tracked.apart_since = None;
+                continue;
+            }
+            tracked.streak += 1;
+            if tracked.streak >= self.polls {
+                tracked.raised = true;
+                tracked.streak = 0;
+                events.push(BunchingEvent::Raised {
+                    incident: tracked.incident.clone(),
+                    since: tracked.since,
+                });
+            }
+        }
+
+        let polls = self.polls;
+        self.tracked.retain(|key, tracked| {
+            if seen.contains(key) {
+                return true;
+            }
+            if !tracked.raised {
+                return false;
+            }
+            let until = *tracked.apart_since.get_or_insert(at);
+            tracked.streak += 1;
+            if tracked.streak < polls {
+                return true;
+            }
+            events.push(BunchingEvent::Cleared {
+                incident: tracked.incident.clone(),
+                since: tracked.since,
+                until,
+            });
+            false
+        });
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAT: f64 = 39.75;
+
+    /// A straight route east along `LAT`, about 4.3 km long.
+    fn shapes() -> HashMap<String, RouteShape> {
+        let shape = RouteShape::new(vec![
+            Coords {
+                lat: LAT,
+                long: 37.00,
+            },
+            Coords {
+                lat: LAT,
+                long: 37.05,
+            },
+        ]);
+        HashMap::from([("1".to_string(), shape)])
+    }
+
+    fn line(id: &str) -> Line {
+        Line {
+            id: id.to_string(),
+            human_name: format!("{id} - Test"),
+        }
+    }
+
+    fn bus(plate: &str, long: f64) -> LineBus {
+        LineBus {
+            license_plate: plate.to_string(),
+            coords: Coords { lat: LAT, long },
+            last_update: None,
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_760_000_000 + secs, 0).unwrap()
+    }
+
+    fn detect(buses: Vec<LineBus>, threshold: BunchingThreshold) -> Vec<BunchingIncident> {
+        detect_bunching(&[(line("1"), buses)], &shapes(), threshold)
+    }
+
+    #[test]
+    fn flags_a_bunched_pair() {
+        let mut trailing = bus("58 AC 123", 37.020);
+        trailing.last_update = Some(at(0));
+        let mut leading = bus("58 AB 999", 37.021);
+        leading.last_update = Some(at(20));
+        let far = bus("58 KP 007", 37.035);
+
+        let incidents = detect(
+            vec![leading, far, trailing],
+            BunchingThreshold::Distance(200.0),
+        );
+        assert_eq!(incidents.len(), 1);
+        let incident = &incidents[0];
+        assert_eq!(incident.line, "1");
+        assert_eq!(incident.leading, "58 AB 999");
+        assert_eq!(incident.trailing, "58 AC 123");
+        assert!((incident.separation_m - 85.6).abs() < 0.5, "{incident:?}");
+        assert_eq!(incident.observed_at, Some(at(20)));
+    }
+
+    #[test]
+    fn time_thresholds_use_the_assumed_speed() {
+        let buses = || vec![bus("58 AC 123", 37.020), bus("58 AB 999", 37.023)];
+        // About 257 m apart: under a minute at 18 km/h (300 m), over 30 s.
+        let minute = BunchingThreshold::Time(Duration::from_secs(60), 18.0);
+        let half = BunchingThreshold::Time(Duration::from_secs(30), 18.0);
+        assert_eq!(detect(buses(), minute).len(), 1);
+        assert!(detect(buses(), half).is_empty());
+    }
+
+    #[test]
+    fn buses_at_a_terminus_are_not_bunched() {
+        let start = vec![bus("58 AC 123", 37.0005), bus("58 AB 999", 37.001)];
+        let end = vec![bus("58 AC 123", 37.0495), bus("58 AB 999", 37.049)];
+        for buses in [start, end] {
+            assert!(detect(buses, BunchingThreshold::Distance(200.0)).is_empty());
+        }
+    }
+
+    #[test]
+    fn buses_off_route_or_without_a_shape_are_skipped() {
+        let mut off_route = bus("58 AB 999", 37.0205);
+        off_route.coords.lat += 0.01;
+        let buses = vec![bus("58 AC 123", 37.020), off_route];
+        assert!(detect(buses, BunchingThreshold::Distance(200.0)).is_empty());
+
+        let unknown = [(
+            line("7A"),
+            vec![bus("58 AC 123", 37.020), bus("58 AB 999", 37.0205)],
+        )];
+        let incidents = detect_bunching(&unknown, &shapes(), BunchingThreshold::Distance(200.0));
+        assert!(incidents.is_empty());
+    }
+
+    fn poll(monitor: &mut BunchingMonitor, secs: i64, gap: f64) -> Vec<BunchingEvent> {
+        let buses = vec![bus("58 AC 123", 37.020), bus("58 AB 999", 37.020 + gap)];
+        monitor.update(at(secs), &[(line("1"), buses)])
+    }
+
+    #[test]
+    fn monitor_raises_and_clears_after_consecutive_polls() {
+        let mut monitor = BunchingMonitor::new(shapes(), BunchingThreshold::Distance(200.0), 2);
+
+        assert!(poll(&mut monitor, 0, 0.001).is_empty());
+        let raised = poll(&mut monitor, 30, 0.001);
+        let [BunchingEvent::Raised { incident, since }] = raised.as_slice() else {
+            panic!("{raised:?}");
+        };
+        assert_eq!((incident.leading.as_str(), *since), ("58 AB 999", at(0)));
+        assert!(poll(&mut monitor, 60, 0.001).is_empty());
+
+        assert!(poll(&mut monitor, 90, 0.01).is_empty());
+        let cleared = poll(&mut monitor, 120, 0.01);
+        let [BunchingEvent::Cleared { since, until, .. }] = cleared.as_slice() else {
+            panic!("{cleared:?}");
+        };
+        assert_eq!((*since, *until), (at(0), at(90)));
+        assert!(poll(&mut monitor, 150, 0.01).is_empty());
+    }
+
+    #[test]
+    fn monitor_ignores_momentary_overlaps() {
+        let mut monitor = BunchingMonitor::new(shapes(), BunchingThreshold::Distance(200.0), 2);
+        for (secs, gap) in [(0, 0.001), (30, 0.01), (60, 0.001), (90, 0.01)] {
+            assert!(poll(&mut monitor, secs, gap).is_empty(), "at {secs}");
+        }
+    }
+
+    #[test]
+    fn monitor_keeps_an_incident_through_a_single_apart_poll() {
+        let mut monitor = BunchingMonitor::new(shapes(), BunchingThreshold::Distance(200.0), 2);
+        poll(&mut monitor, 0, 0.001);
+        assert_eq!(poll(&mut monitor, 30, 0.001).len(), 1);
+        assert!(poll(&mut monitor, 60, 0.01).is_empty());
+        assert!(poll(&mut monitor, 90, 0.001).is_empty());
+        assert!(poll(&mut monitor, 120, 0.01).is_empty());
+        assert_eq!(poll(&mut monitor, 150, 0.01).len(), 1);
+    }
+}
@@ -0,0 +1,465 @@
+mod config;
+mod export;
+mod lang;
+mod nearest;
+mod notify;
+mod output;
+mod plate;
+mod resolve;
+mod route;
+mod snapshot;
+mod statusbar;
+#[cfg(feature = "tui")]
+mod tui;
+mod watch;
+
+use clap::{Args, Parser, Subcommand};
+use config::FileConfig;
+use lang::Lang;
+use output::Format;
+use sivasbus::config::Config;
+use sivasbus::favorites::DuplicateLabel;
+use sivasbus::{Client, ClientBuilder, Error, ErrorKind, StationId};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+use watch::WatchArgs;
+
+#[derive(thiserror::Error, Debug)]
+pub enum CliError {
+    #[error(transparent)]
+    Lib(#[from] Error),
+    #[error("can't access {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("invalid config file {0}")]
+    Config(PathBuf, #[source] toml::de::Error),
+    #[error("conflicting favorites in {0}")]
+    Favorites(PathBuf, #[source] DuplicateLabel),
+    #[error("{0} already exists, pass --force to overwrite it")]
+    Exists(PathBuf),
+    #[error("can't determine the config directory, set --config")]
+    NoConfigPath,
+    #[error("no {kind} matches {query:?}")]
+    NoMatch { kind: &'static str, query: String },
+    #[error("several {kind}s match {query:?}: {}", .candidates.join(", "))]
+    Ambiguous {
+        kind: &'static str,
+        query: String,
+        candidates: Vec<String>,
+    },
+    #[cfg(feature = "tui")]
+    #[error("no favorites to show, add station or line aliases to the config file")]
+    NoFavorites,
+    #[error("can't tell the export format from {0}, pass --as")]
+    UnknownExportFormat(PathBuf),
+    #[error("{format} export isn't compiled in, rebuild with `--features {feature}`")]
+    ExportNotCompiled {
+        format: &'static str,
+        feature: &'static str,
+    },
+    #[error("{what} can't be exported as {format}")]
+    UnsupportedExport { what: String, format: &'static str },
+    #[error("invalid route shape {0}, expected a GeoJSON LineString")]
+    Shape(PathBuf, #[source] serde_json::Error),
+    #[error("can't load snapshot {0}")]
+    Snapshot(PathBuf, #[source] std::io::Error),
+    #[error("no bus with plate {0} found")]
+    PlateNotFound(String),
+    #[cfg(feature = "tui")]
+    #[error("terminal error")]
+    Terminal(#[source] std::io::Error),
+}
+
+impl CliError {
+    /// The message in the current language, without its sources.
+    fn localized(&self) -> String {
+        match self {
+            CliError::Lib(error) => lang::error(error),
+            CliError::Io(path, _) => lang::tf("can't access {path}", &[("path", &path.display())]),
+            CliError::Config(path, _) => {
+                lang::tf("invalid config file {path}", &[("path", &path.display())])
+            }
+            CliError::Favorites(path, _) => lang::tf(
+                "conflicting favorites in {path}",
+                &[("path", &path.display())],
+            ),
+            CliError::Exists(path) => lang::tf(
+                "{path} already exists, pass --force to overwrite it",
+                &[("path", &path.display())],
+            ),
+            CliError::NoConfigPath => {
+                lang::t("can't determine the config directory, set --config").to_string()
+            }
+            CliError::NoMatch { kind, query } => lang::tf(
+                "no {kind} matches {query}",
+                &[("kind", &lang::t(kind)), ("query", &format!("{query:?}"))],
+            ),
+            CliError::Ambiguous {
+                kind,
+                query,
+                candidates,
+            } => lang::tf(
+                "several {kind}s match {query}: {candidates}",
+                &[
+                    ("kind", &lang::t(kind)),
+                    ("query", &format!("{query:?}")),
+                    ("candidates", &candidates.join(", ")),
+                ],
+            ),
+            #[cfg(feature = "tui")]
+            CliError::NoFavorites => {
+                lang::t("no favorites to show, add station or line aliases to the config file")
+                    .to_string()
+            }
+            CliError::UnknownExportFormat(path) => lang::tf(
+                "can't tell the export format from {path}, pass --as",
+                &[("path", &path.display())],
+            ),
+            CliError::ExportNotCompiled { format, feature } => lang::tf(
+                "{format} export isn't compiled in, rebuild with `--features {feature}`",
+                &[("format", format), ("feature", feature)],
+            ),
+            CliError::UnsupportedExport { what, format } => lang::tf(
+                "{what} can't be exported as {format}",
+                &[("what", what), ("format", format)],
+            ),
+            CliError::Shape(path, _) => lang::tf(
+                "invalid route shape {path}, expected a GeoJSON LineString",
+                &[("path", &path.display())],
+            ),
+            CliError::Snapshot(path, _) => {
+                lang::tf("can't load snapshot {path}", &[("path", &path.display())])
+            }
+            CliError::PlateNotFound(plate) => {
+                lang::tf("no bus with plate {plate} found", &[("plate", plate)])
+            }
+            #[cfg(feature = "tui")]
+            CliError::Terminal(_) => lang::t("terminal error").to_string(),
+        }
+    }
+
+    fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            CliError::Lib(error) => match error.kind() {
+                ErrorKind::NotFound => 3,
+                ErrorKind::Network => 4,
+                ErrorKind::Parse => 5,
+                ErrorKind::TokenMissing => 6,
+                ErrorKind::Unavailable => 7,
+            },
+            CliError::NoMatch { .. } | CliError::Ambiguous { .. } => 3,
+            CliError::PlateNotFound(_) => 8,
+            CliError::Snapshot(..) => 2,
+            _ => 1,
+        })
+    }
+}
+
+#[derive(Parser)]
+#[command(name = "sivasbus", version, about)]
+struct Cli {
+    /// Config file, defaults to ~/.config/sivasbus/config.toml
+    #[arg(long, global = true, env = "SIVASBUS_CONFIG")]
+    config: Option<PathBuf>,
+    /// Root URL of the bus site
+    #[arg(long, global = true, env = "SIVASBUS_BASE_URL")]
+    base_url: Option<String>,
+    /// Request timeout in seconds
+    #[arg(long, global = true, value_name = "SECS")]
+    timeout: Option<u64>,
+    /// Delay after each request in milliseconds
+    #[arg(long, global = true, value_name = "MS")]
+    throttle_ms: Option<u64>,
+    /// Output format, a table on terminals and JSON otherwise
+    #[arg(long, global = true, value_enum)]
+    format: Option<Format>,
+    /// Language of the output, from the locale by default
+    #[arg(long, global = true, value_enum)]
+    lang: Option<Lang>,
+    /// Don't show progress bars
+    #[arg(long, global = true)]
+    no_progress: bool,
+    /// Shorthand for --format json
+    #[arg(long, global = true, hide = true, conflicts_with = "format")]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct StationArg {
+    #[arg(
+        value_name = "STATION",
+        required_unless_present = "station_flag",
+        conflicts_with = "station_flag"
+    )]
+    station: Option<String>,
+    #[arg(long = "station", id = "station_flag", value_name = "STATION")]
+    station_flag: Option<String>,
+}
+
+impl StationArg {
+    async fn resolve(&self, client: &Client, config: &FileConfig) -> Result<i32, CliError> {
+        let station = self.station.as_ref().or(self.station_flag.as_ref());
+        resolve::station(client, config, station.unwrap()).await
+    }
+}
+
+#[derive(Args)]
+struct LineArg {
+    #[arg(
+        value_name = "LINE",
+        required_unless_present = "line_flag",
+        conflicts_with = "line_flag"
+    )]
+    line: Option<String>,
+    #[arg(long = "line", id = "line_flag", value_name = "LINE")]
+    line_flag: Option<String>,
+}
+
+impl LineArg {
+    async fn resolve(&self, client: &Client, config: &FileConfig) -> Result<String, CliError> {
+        let line = self.line.as_ref().or(self.line_flag.as_ref());
+        resolve::line(client, config, line.unwrap()).await
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List all lines
+    Lines,
+    /// List the stations of a line, or every station without --line
+    Stations {
+        #[arg(long)]
+        line: Option<String>,
+    },
+    /// Show the buses arriving at a station
+    Arrivals {
+        #[command(flatten)]
+        station: StationArg,
+        #[command(flatten)]
+        watch: WatchArgs,
+    },
+    /// Show the buses currently on a line
+    Buses {
+        #[command(flatten)]
+        line: LineArg,
+        #[command(flatten)]
+        watch: WatchArgs,
+    },
+    /// List every smart station
+    AllStations,
+    /// Keep showing the buses arriving at a station
+    Watch {
+        #[command(flatten)]
+        station: StationArg,
+        /// Time between refreshes, e.g. 30s or 1m
+        #[arg(long, default_value = "30s", value_parser = watch::parse_interval)]
+        interval: Duration,
+    },
+    /// List the stations closest to a coordinate
+    Nearest(nearest::NearestArgs),
+    /// Open a live dashboard of the favorite stations and lines
+    #[cfg(feature = "tui")]
+    Tui {
+        /// Time between refreshes, e.g. 20s or 1m
+        #[arg(long, default_value = "20s", value_parser = watch::parse_interval)]
+        interval: Duration,
+    },
+    /// Notify when a bus is about to arrive at a station
+    Notify(notify::NotifyArgs),
+    /// Export stations, lines, a route or the whole network to a file
+    Export(export::ExportArgs),
+    /// Save the network, and optionally its buses, to a file
+    Snapshot(snapshot::SnapshotArgs),
+    /// Compare two snapshots, exiting with 1 if they differ
+    Diff(snapshot::DiffArgs),
+    /// Find which line a bus is on and where it is
+    Plate(plate::PlateArgs),
+    /// Show the stops of a line in order with distances along the route
+    Route(route::RouteArgs),
+    /// Print the next arrival at a station for waybar or i3bar
+    Statusbar(statusbar::StatusbarArgs),
+    /// Manage the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the config file location
+    Path,
+    /// Write a commented config template
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+impl Cli {
+    fn config_path(&self) -> Result<PathBuf, CliError> {
+        match &self.config {
+            Some(path) => Ok(path.clone()),
+            None => config::default_path().ok_or(CliError::NoConfigPath),
+        }
+    }
+
+    fn format(&self, config: &FileConfig) -> Format {
+        match self.format {
+            Some(format) => format,
+            None if self.json => Format::Json,
+            None => match config.format {
+                Some(format) => format,
+                None if std::io::stdout().is_terminal() => Format::Table,
+                None => Format::Json,
+            },
+        }
+    }
+
+    fn client(&self, config: &FileConfig) -> Result<Client, Error> {
+        let mut builder = ClientBuilder::new();
+        if let Some(base_url) = self.base_url.as_ref().or(config.base_url.as_ref()) {
+            builder = builder.base_url(base_url);
+        }
+        if let Some(timeout) = self.timeout.or(config.timeout) {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(throttle) = self.throttle_ms.or(config.throttle_ms) {
+            builder = builder.request_delay(Duration::from_millis(throttle));
+        }
+        builder.build()
+    }
+}
+
+async fn run(cli: Cli) -> Result<ExitCode, CliError> {
+    if let Some(lang) = cli.lang {
+        lang::set(lang);
+    }
+    if cli.no_progress {
+        output::disable_progress();
+    }
+    let path = cli.config_path();
+    if let Command::Config { action } = &cli.command {
+        let path = path?;
+        match action {
+            ConfigCommand::Path => println!("{}", path.display()),
+            ConfigCommand::Init { force } => {
+                config::init(&path, *force)?;
+                eprintln!("{}", lang::tf("wrote {path}", &[("path", &path.display())]));
+            }
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let config = match path {
+        Ok(path) => config::load(&path, cli.config.is_some()).await?,
+        Err(_) => FileConfig::default(),
+    };
+    let client = cli.client(&config)?;
+    let format = cli.format(&config);
+
+    match cli.command {
+        Command::Lines => output::print(&client.get_lines().await?, format),
+        Command::Stations { line } => {
+            let stations = match line {
+                Some(line) => {
+                    let line = resolve::line(&client, &config, &line).await?;
+                    client.get_stations(&line).await?
+                }
+                None => client.get_all_stations().await?,
+            };
+            output::print(&stations, format);
+        }
+        Command::Arrivals { station, watch } if watch.watch => {
+            let session = client.open_session().await?;
+            let station = station.resolve(&client, &config).await?;
+            let target = watch::Target::Station(station);
+            watch::run(watch.interval, format, target, async || {
+                session.get_station_buses(StationId(station)).await
+            })
+            .await?
+        }
+        Command::Arrivals { station, .. } => {
+            let station = station.resolve(&client, &config).await?;
+            output::print(&client.get_station_buses(StationId(station)).await?, format)
+        }
+        Command::Buses { line, watch } if watch.watch => {
+            let session = client.open_session().await?;
+            let line = line.resolve(&client, &config).await?;
+            let target = watch::Target::Line(line.clone());
+            watch::run(watch.interval, format, target, async || {
+                session.get_line_buses(&line).await
+            })
+            .await?
+        }
+        Command::Buses { line, .. } => {
+            let line = line.resolve(&client, &config).await?;
+            output::print(&client.get_line_buses(&line).await?, format)
+        }
+        Command::Nearest(args) => nearest::run(&client, args, format).await?,
+        Command::Export(args) => export::run(&client, &config, args).await?,
+        Command::Snapshot(args) => snapshot::snapshot(&client, args).await?,
+        Command::Diff(args) => return snapshot::diff(args, format).await,
+        Command::Plate(args) => plate::run(&client, &config, args, format).await?,
+        Command::Route(args) => route::run(&client, &config, args, format).await?,
+        Command::Statusbar(args) => statusbar::run(&client, &config, args).await?,
+        Command::Notify(args) => {
+            #[cfg(feature = "notify")]
+            let mut notifier = notify::Desktop;
+            #[cfg(not(feature = "notify"))]
+            let mut notifier = notify::Bell;
+            notify::run(&client, &config, args, &mut notifier).await?
+        }
+        Command::AllStations => output::print(&client.get_all_stations().await?, format),
+        Command::Watch { station, interval } => {
+            let session = client.open_session().await?;
+            let station = station.resolve(&client, &config).await?;
+            let target = watch::Target::Station(station);
+            watch::run(interval, format, target, async || {
+                session.get_station_buses(StationId(station)).await
+            })
+            .await?
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui { interval } => tui::run(&client, &config, interval).await?,
+        Command::Config { .. } => unreachable!(),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Logs to stderr at `SIVASBUS_LOG_LEVEL`, which takes `tracing` filter
+/// directives like `debug` or `sivasbus=trace`.
+fn init_tracing() {
+    let level = Config::from_env()
+        .map(|config| config.log_level)
+        .unwrap_or_else(|_| Config::default().log_level);
+    let filter = EnvFilter::try_new(&level).unwrap_or_else(|_| EnvFilter::new("warn"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+pub async fn main() -> ExitCode {
+    init_tracing();
+    match run(Cli::parse()).await {
+        Ok(code) => code,
+        Err(error) => {
+            let message = error.localized();
+            eprint!("{}", lang::tf("error: {message}", &[("message", &message)]));
+            let mut source = std::error::Error::source(&error);
+            while let Some(cause) = source {
+                eprint!(": {cause}");
+                source = cause.source();
+            }
+            eprintln!();
+            error.exit_code()
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use serde::Deserialize;
+use sivasbus::favorites::{Favorites, Target};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::CliError;
+use super::output::Format;
+
+pub const TEMPLATE: &str = r#"# sivasbus configuration
+#
+# Command line flags take precedence over the values in this file.
+
+# Root URL of the bus site.
+# base_url = "https://ulasim.sivas.bel.tr"
+
+# Request timeout in seconds.
+# timeout = 30
+
+# Delay after each request in milliseconds.
+# throttle_ms = 200
+
+# Output format: "table", "json", "csv" or "ndjson".
+# format = "table"
+
+# Station aliases, usable wherever a station id is expected. Station and
+# line names are also accepted and looked up in the cached catalog. Aliases
+# are added to the favorites in favorites.json next to this file, and each
+# label must be unique across both.
+[stations]
+# home = 10
+
+# Line aliases, usable wherever a line id is expected.
+[lines]
+# work = "7A"
+"#;
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub base_url: Option<String>,
+    pub timeout: Option<u64>,
+    pub throttle_ms: Option<u64>,
+    pub format: Option<Format>,
+    stations: BTreeMap<String, i32>,
+    lines: BTreeMap<String, String>,
+    /// The favorites file merged with the aliases above.
+    #[serde(skip)]
+    pub favorites: Favorites,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("sivasbus").join("config.toml"))
+}
+
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("sivasbus"))
+}
+
+async fn load_favorites(path: &Path) -> Result<Favorites, CliError> {
+    match Favorites::load(path).await {
+        Ok(favorites) => Ok(favorites),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Favorites::new()),
+        Err(error) => Err(CliError::Io(path.to_path_buf(), error)),
+    }
+}
+
+pub async fn load(path: &Path, explicit: bool) -> Result<FileConfig, CliError> {
+    let mut config: FileConfig = match std::fs::read_to_string(path) {
+        Ok(text) => {
+            toml::from_str(&text).map_err(|error| CliError::Config(path.to_path_buf(), error))?
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound && !explicit => {
+            FileConfig::default()
+        }
+        Err(error) => return Err(CliError::Io(path.to_path_buf(), error)),
+    };
+
+    config.favorites = load_favorites(&path.with_file_name("favorites.json")).await?;
+    let conflict = |error| CliError::Favorites(path.to_path_buf(), error);
+    for (label, id) in std::mem::take(&mut config.stations) {
+        config.favorites.add_station(id, label).map_err(conflict)?;
+    }
+    for (label, id) in std::mem::take(&mut config.lines) {
+        config.favorites.add_line(id, label).map_err(conflict)?;
+    }
+    Ok(config)
+}
+
+pub fn init(path: &Path, force: bool) -> Result<(), CliError> {
+    if path.exists() && !force {
+        return Err(CliError::Exists(path.to_path_buf()));
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|error| CliError::Io(dir.to_path_buf(), error))?;
+    }
+    std::fs::write(path, TEMPLATE).map_err(|error| CliError::Io(path.to_path_buf(), error))
+}
+
+impl FileConfig {
+    /// Resolves a station id or alias without going to the network.
+    pub fn station(&self, name: &str) -> Option<i32> {
+        name.parse()
+            .ok()
+            .or_else(|| match self.favorites.resolve(name)? {
+                Target::Station(id) => Some(*id),
+                Target::Line(_) => None,
+            })
+    }
+
+    /// Resolves a line alias, or anything that looks like a line id such as
+    /// "7A", without going to the network.
+    pub fn line(&self, name: &str) -> Option<String> {
+        if let Some(Target::Line(line)) = self.favorites.resolve(name) {
+            return Some(line.clone());
+        }
+        let slug = name.len() <= 8
+            && name.chars().all(|c| c.is_ascii_alphanumeric())
+            && name.chars().any(|c| c.is_ascii_digit());
+        slug.then(|| name.to_string())
+    }
+}
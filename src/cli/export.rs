@@ -0,0 +1,255 @@
+use clap::{Args, ValueEnum};
+use sivasbus::{Client, Line, NetworkSnapshot, Station};
+use std::path::{Path, PathBuf};
+
+use super::CliError;
+use super::config::FileConfig;
+use super::lang;
+use super::output::{self, line_progress, progress_bar};
+use super::resolve;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum What {
+    Stations,
+    Lines,
+    Network,
+    Route,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Geojson,
+    Gtfs,
+    Csv,
+    Kml,
+}
+
+impl ExportFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Geojson => "geojson",
+            ExportFormat::Gtfs => "gtfs",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Kml => "kml",
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<ExportFormat> {
+        match path.extension()?.to_str()? {
+            "geojson" | "json" => Some(ExportFormat::Geojson),
+            "csv" => Some(ExportFormat::Csv),
+            "kml" => Some(ExportFormat::Kml),
+            _ => None,
+        }
+    }
+
+    fn feature(&self) -> Option<(&'static str, bool)> {
+        match self {
+            ExportFormat::Geojson => Some(("geojson", cfg!(feature = "geojson"))),
+            ExportFormat::Gtfs => Some(("gtfs-static", cfg!(feature = "gtfs-static"))),
+            ExportFormat::Kml => Some(("kml", cfg!(feature = "kml"))),
+            ExportFormat::Csv => None,
+        }
+    }
+
+    fn supports(&self, what: What) -> bool {
+        match self {
+            ExportFormat::Geojson => what != What::Lines,
+            ExportFormat::Gtfs => what == What::Network,
+            ExportFormat::Csv => what != What::Network,
+            ExportFormat::Kml => matches!(what, What::Stations | What::Route),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    what: What,
+    /// Line to export, with `route`
+    #[arg(required_if_eq("what", "route"))]
+    line: Option<String>,
+    /// File to write, or directory for GTFS
+    #[arg(long)]
+    to: PathBuf,
+    /// Export format, guessed from the --to extension if not given
+    #[arg(long = "as", value_enum)]
+    export_format: Option<ExportFormat>,
+    /// Overwrite an existing file
+    #[arg(long)]
+    force: bool,
+    /// Cluster stations for a map at this zoom level, with `stations` as
+    /// GeoJSON
+    #[arg(long, value_name = "ZOOM")]
+    cluster_zoom: Option<u8>,
+}
+
+enum Data {
+    Stations(Vec<Station>),
+    Lines(Vec<Line>),
+    Network(NetworkSnapshot),
+    Route(Line, Vec<Station>),
+}
+
+impl Data {
+    fn summary(&self) -> String {
+        match self {
+            Data::Stations(stations) => lang::tf("{count} stations", &[("count", &stations.len())]),
+            Data::Lines(lines) => lang::tf("{count} lines", &[("count", &lines.len())]),
+            Data::Network(snapshot) => lang::tf(
+                "{lines} lines and {stations} stations",
+                &[
+                    ("lines", &snapshot.lines.len()),
+                    ("stations", &snapshot.stations.len()),
+                ],
+            ),
+            Data::Route(line, stations) => lang::tf(
+                "line {id} with {count} stops",
+                &[("id", &line.id), ("count", &stations.len())],
+            ),
+        }
+    }
+}
+
+async fn fetch_network(client: &Client) -> Result<NetworkSnapshot, CliError> {
+    let bar = progress_bar("lines");
+    let snapshot = client.get_all_data_with_progress(line_progress(&bar)).await;
+    bar.finish_and_clear();
+    Ok(snapshot?)
+}
+
+async fn fetch(
+    client: &Client,
+    config: &FileConfig,
+    what: What,
+    line: Option<&str>,
+) -> Result<Data, CliError> {
+    Ok(match what {
+        What::Stations => Data::Stations(client.get_all_stations().await?),
+        What::Lines => Data::Lines(client.get_lines().await?),
+        What::Network => Data::Network(fetch_network(client).await?),
+        What::Route => {
+            let id = resolve::line(client, config, line.unwrap()).await?;
+            let stations = client.get_stations(&id).await?;
+            let line = client
+                .get_lines()
+                .await?
+                .into_iter()
+                .find(|line| line.id == id)
+                .unwrap_or(Line {
+                    human_name: id.clone(),
+                    id,
+                });
+            Data::Route(line, stations)
+        }
+    })
+}
+
+#[cfg(feature = "geojson")]
+fn geojson(data: &Data, cluster_zoom: Option<u8>) -> String {
+    use sivasbus::{export, geo};
+
+    let value = match (data, cluster_zoom) {
+        (Data::Stations(stations), Some(zoom)) => {
+            export::station_clusters_geojson(&geo::cluster_stations_for_zoom(stations, zoom))
+        }
+        (Data::Stations(stations), None) => export::stations_geojson(stations),
+        (Data::Route(line, stations), _) => export::route_geojson(line, stations),
+        (Data::Network(snapshot), _) => export::network_geojson(snapshot),
+        (Data::Lines(_), _) => unreachable!(),
+    };
+    serde_json::to_string_pretty(&value).unwrap()
+}
+
+#[cfg(not(feature = "geojson"))]
+fn geojson(_: &Data, _: Option<u8>) -> String {
+    unreachable!()
+}
+
+#[cfg(feature = "kml")]
+fn kml(data: &Data) -> String {
+    use sivasbus::export;
+
+    match data {
+        Data::Stations(stations) => export::stations_kml(stations),
+        Data::Route(line, stations) => export::route_kml(line, stations),
+        Data::Lines(_) | Data::Network(_) => unreachable!(),
+    }
+}
+
+#[cfg(not(feature = "kml"))]
+fn kml(_: &Data) -> String {
+    unreachable!()
+}
+
+#[cfg(feature = "gtfs-static")]
+fn gtfs(data: &Data, path: &Path) -> std::io::Result<()> {
+    match data {
+        Data::Network(snapshot) => snapshot.to_gtfs(path),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(not(feature = "gtfs-static"))]
+fn gtfs(_: &Data, _: &Path) -> std::io::Result<()> {
+    unreachable!()
+}
+
+fn csv(data: &Data) -> Vec<u8> {
+    let mut out = Vec::new();
+    match data {
+        Data::Stations(stations) | Data::Route(_, stations) => {
+            output::write_csv(stations, &mut out)
+        }
+        Data::Lines(lines) => output::write_csv(lines, &mut out),
+        Data::Network(_) => unreachable!(),
+    }
+    .unwrap();
+    out
+}
+
+pub async fn run(client: &Client, config: &FileConfig, args: ExportArgs) -> Result<(), CliError> {
+    let format = match args.export_format {
+        Some(format) => format,
+        None => ExportFormat::from_path(&args.to)
+            .ok_or_else(|| CliError::UnknownExportFormat(args.to.clone()))?,
+    };
+    if let Some((feature, false)) = format.feature() {
+        return Err(CliError::ExportNotCompiled {
+            format: format.name(),
+            feature,
+        });
+    }
+    if !format.supports(args.what) {
+        return Err(CliError::UnsupportedExport {
+            what: args
+                .what
+                .to_possible_value()
+                .unwrap()
+                .get_name()
+                .to_string(),
+            format: format.name(),
+        });
+    }
+    if args.to.exists() && !args.force {
+        return Err(CliError::Exists(args.to));
+    }
+
+    let data = fetch(client, config, args.what, args.line.as_deref()).await?;
+    let io = |error| CliError::Io(args.to.clone(), error);
+    match format {
+        ExportFormat::Gtfs => gtfs(&data, &args.to).map_err(io)?,
+        ExportFormat::Geojson => {
+            std::fs::write(&args.to, geojson(&data, args.cluster_zoom)).map_err(io)?
+        }
+        ExportFormat::Kml => std::fs::write(&args.to, kml(&data)).map_err(io)?,
+        ExportFormat::Csv => std::fs::write(&args.to, csv(&data)).map_err(io)?,
+    }
+    eprintln!(
+        "{}",
+        lang::tf(
+            "wrote {summary} to {path}",
+            &[("summary", &data.summary()), ("path", &args.to.display())]
+        )
+    );
+    Ok(())
+}
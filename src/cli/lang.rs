@@ -0,0 +1,269 @@
+//! English and Turkish texts for the CLI.
+//!
+//! Messages are keyed by their English text, with `{name}` placeholders
+//! filled in by [`tf`]. English needs no entries; a message missing from a
+//! catalog falls back to English.
+
+use clap::ValueEnum;
+use sivasbus::Error;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lang {
+    En,
+    Tr,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+impl Lang {
+    /// Turkish if the locale is Turkish, English otherwise.
+    pub fn from_env() -> Lang {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty());
+        match locale {
+            Some(locale) if locale.starts_with("tr") => Lang::Tr,
+            _ => Lang::En,
+        }
+    }
+}
+
+/// Sets the language, which must happen before the first lookup.
+pub fn set(lang: Lang) {
+    LANG.set(lang).ok();
+}
+
+fn current() -> Lang {
+    *LANG.get_or_init(Lang::from_env)
+}
+
+const TR: &[(&str, &str)] = &[
+    // Table headers
+    ("ID", "No"),
+    ("Name", "Ad"),
+    ("Lat", "Enlem"),
+    ("Long", "Boylam"),
+    ("Plate", "Plaka"),
+    ("Arrives in", "Varış"),
+    ("Line", "Hat"),
+    ("Distance", "Mesafe"),
+    ("Bearing", "Yön"),
+    ("Map", "Harita"),
+    ("Next bus", "Sıradaki otobüs"),
+    ("Smart", "Akıllı"),
+    ("Buses", "Otobüsler"),
+    ("Age", "Geçen süre"),
+    // Durations
+    ("now", "şimdi"),
+    ("{mins} min", "{mins} dk"),
+    ("{mins}m", "{mins}dk"),
+    ("{plate} in {time}", "{plate}, {time} içinde"),
+    // Watch and dashboard status
+    ("never", "hiç"),
+    ("Last updated {time}", "Son güncelleme {time}"),
+    (
+        "STALE, last updated {time} ({error})",
+        "ESKİ, son güncelleme {time} ({error})",
+    ),
+    ("updated {time}", "güncelleme {time}"),
+    ("connecting", "bağlanıyor"),
+    ("online", "çevrimiçi"),
+    ("offline", "çevrimdışı"),
+    ("loading...", "yükleniyor..."),
+    ("terminal too small", "terminal çok küçük"),
+    ("Favorites", "Favoriler"),
+    (
+        "q quit, tab pane, r refresh",
+        "q çıkış, tab bölme, r yenile",
+    ),
+    // Notifications and status bar
+    ("Bus arriving", "Otobüs geliyor"),
+    ("line {line}", "{line} hattı"),
+    ("unknown line", "bilinmeyen hat"),
+    (
+        "{plate} ({line}) arrives in {time}",
+        "{plate} ({line}) {time} içinde geliyor",
+    ),
+    ("no data", "veri yok"),
+    ("no buses", "otobüs yok"),
+    // Progress, warnings and summaries
+    ("lines", "hat"),
+    ("eta", "kalan"),
+    ("line {line}: {count} buses", "{line} hattı: {count} otobüs"),
+    (
+        "line {line} failed: {error}",
+        "{line} hattı alınamadı: {error}",
+    ),
+    ("warning: {message}", "uyarı: {message}"),
+    ("error: {message}", "hata: {message}"),
+    (
+        "searching all {count} lines, pass --lines to make fewer requests",
+        "{count} hattın hepsi aranıyor, daha az istek için --lines verin",
+    ),
+    (
+        "fetching arrivals for the nearest {count} stations only",
+        "yalnızca en yakın {count} durağın varışları alınıyor",
+    ),
+    ("invalid coordinate {text}", "geçersiz koordinat {text}"),
+    (
+        "some stops are off the route shape, using straight-line distances",
+        "bazı duraklar güzergah şeklinin dışında, düz çizgi mesafeleri kullanılıyor",
+    ),
+    (
+        "expected \"lat,long\", got {text}",
+        "\"enlem,boylam\" bekleniyordu, {text} verildi",
+    ),
+    ("wrote {path}", "{path} yazıldı"),
+    (
+        "wrote {summary} to {path}",
+        "{summary}, {path} dosyasına yazıldı",
+    ),
+    ("{count} stations", "{count} durak"),
+    ("{count} lines", "{count} hat"),
+    (
+        "{lines} lines and {stations} stations",
+        "{lines} hat ve {stations} durak",
+    ),
+    ("line {id} with {count} stops", "{count} duraklı {id} hattı"),
+    // Name resolution
+    ("station", "durak"),
+    ("line", "hat"),
+    (
+        "several {kind}s match {query}:",
+        "{query} ile birden çok {kind} eşleşiyor:",
+    ),
+    ("choose 1-{count}: ", "seçin 1-{count}: "),
+    // Snapshot diffs
+    (
+        "~ {kind} {id} renamed {old} -> {new}",
+        "~ {kind} {id} yeniden adlandırıldı {old} -> {new}",
+    ),
+    (
+        "~ station {id} {name} moved {distance} m",
+        "~ durak {id} {name} {distance} m taşındı",
+    ),
+    ("~ line {id} stops changed", "~ hat {id} durakları değişti"),
+    // Errors
+    ("can't access {path}", "{path} dosyasına erişilemiyor"),
+    (
+        "invalid config file {path}",
+        "geçersiz yapılandırma dosyası {path}",
+    ),
+    (
+        "conflicting favorites in {path}",
+        "{path} içinde çakışan favoriler var",
+    ),
+    (
+        "{path} already exists, pass --force to overwrite it",
+        "{path} zaten var, üzerine yazmak için --force verin",
+    ),
+    (
+        "can't determine the config directory, set --config",
+        "yapılandırma dizini belirlenemiyor, --config verin",
+    ),
+    (
+        "no {kind} matches {query}",
+        "{query} ile eşleşen {kind} yok",
+    ),
+    (
+        "several {kind}s match {query}: {candidates}",
+        "{query} ile birden çok {kind} eşleşiyor: {candidates}",
+    ),
+    (
+        "no favorites to show, add station or line aliases to the config file",
+        "gösterilecek favori yok, yapılandırma dosyasına durak veya hat takma adları ekleyin",
+    ),
+    (
+        "can't tell the export format from {path}, pass --as",
+        "{path} dosyasından dışa aktarma biçimi anlaşılamıyor, --as verin",
+    ),
+    (
+        "{format} export isn't compiled in, rebuild with `--features {feature}`",
+        "{format} dışa aktarma derlenmemiş, `--features {feature}` ile yeniden derleyin",
+    ),
+    (
+        "{what} can't be exported as {format}",
+        "{what} {format} olarak dışa aktarılamaz",
+    ),
+    (
+        "invalid route shape {path}, expected a GeoJSON LineString",
+        "geçersiz güzergah şekli {path}, GeoJSON LineString bekleniyordu",
+    ),
+    (
+        "can't load snapshot {path}",
+        "{path} anlık görüntüsü yüklenemiyor",
+    ),
+    (
+        "no bus with plate {plate} found",
+        "{plate} plakalı otobüs bulunamadı",
+    ),
+    ("terminal error", "terminal hatası"),
+];
+
+/// Turkish for the library's errors, by [`Error::code`].
+const TR_ERRORS: &[(&str, &str)] = &[
+    ("request", "istek hatası"),
+    ("json", "JSON çözümlenemedi"),
+    ("station", "durak ayrıştırılamadı"),
+    ("line_bus", "hattaki otobüs ayrıştırılamadı"),
+    ("no_token", "doğrulama anahtarı bulunamadı"),
+    ("no_stations", "durak bulunamadı"),
+    ("no_line_id", "hat bulunamadı"),
+    (
+        "circuit_open",
+        "art arda hatalar yüzünden istekler durduruldu",
+    ),
+    ("timeout", "istek zaman aşımına uğradı"),
+    ("no_schedule", "sefer çizelgesi bulunamadı"),
+    ("retry_budget_exhausted", "yeniden deneme hakkı tükendi"),
+    ("invalid_resolve", "geçersiz DNS yönlendirmesi"),
+    (
+        "too_many_parse_failures",
+        "ayrıştırılamayan kayıt sayısı çok fazla",
+    ),
+    ("rate_limited", "site istek sınırına ulaşıldı"),
+    ("unexpected_content_type", "beklenmeyen içerik türü"),
+    ("media_too_large", "dosya çok büyük"),
+    ("invalid_proxy", "geçersiz vekil sunucu adresi"),
+];
+
+fn lookup(catalog: &[(&'static str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog
+        .iter()
+        .find(|(english, _)| *english == key)
+        .map(|(_, text)| *text)
+}
+
+/// The text for `english` in the current language.
+pub fn t(english: &'static str) -> &'static str {
+    match current() {
+        Lang::En => english,
+        Lang::Tr => lookup(TR, english).unwrap_or(english),
+    }
+}
+
+/// Like [`t`], filling in the `{name}` placeholders from `args`.
+pub fn tf(english: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter()
+        .fold(t(english).to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), &value.to_string())
+        })
+}
+
+/// Prints a warning to stderr.
+pub fn warn(message: &str) {
+    eprintln!("{}", tf("warning: {message}", &[("message", &message)]));
+}
+
+/// A library error's message in the current language, without its sources.
+pub fn error(error: &Error) -> String {
+    match current() {
+        Lang::En => error.to_string(),
+        Lang::Tr => lookup(TR_ERRORS, error.code())
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string()),
+    }
+}
@@ -0,0 +1,150 @@
+use clap::Args;
+use serde::Serialize;
+use sivasbus::{Client, Coords, Error, StationBus, StationId, geo};
+
+use super::lang;
+use super::output::{self, Format, Render, humanize};
+
+const MAX_ARRIVAL_LOOKUPS: usize = 5;
+
+#[derive(Args)]
+pub struct NearestArgs {
+    /// Coordinates as "lat,long", decimal commas are accepted
+    #[arg(value_parser = parse_coords, allow_hyphen_values = true)]
+    coords: Coords,
+    /// Number of stations to show
+    #[arg(long, default_value_t = 5)]
+    count: usize,
+    /// Search radius in meters
+    #[arg(long, default_value_t = 800.0)]
+    radius: f64,
+    /// Also fetch the next arrival of each station
+    #[arg(long)]
+    with_arrivals: bool,
+}
+
+pub fn parse_coords(text: &str) -> Result<Coords, String> {
+    let number = |part: &str| {
+        part.trim().replace(',', ".").parse::<f64>().map_err(|_| {
+            lang::tf(
+                "invalid coordinate {text}",
+                &[("text", &format!("{part:?}"))],
+            )
+        })
+    };
+
+    let parts: Vec<&str> = if text.contains([';', ' ']) {
+        text.split([';', ' '])
+            .filter(|part| !part.is_empty())
+            .collect()
+    } else {
+        text.split(',').collect()
+    };
+
+    let (lat, long) = match parts.as_slice() {
+        [lat, long] => (number(lat)?, number(long)?),
+        [lat, lat_frac, long, long_frac] => (
+            number(&format!("{lat}.{lat_frac}"))?,
+            number(&format!("{long}.{long_frac}"))?,
+        ),
+        _ => {
+            let text = format!("{text:?}");
+            return Err(lang::tf(
+                "expected \"lat,long\", got {text}",
+                &[("text", &text)],
+            ));
+        }
+    };
+
+    Ok(Coords { lat, long })
+}
+
+#[derive(Serialize)]
+struct NearbyStation {
+    id: i32,
+    human_name: String,
+    distance_m: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_arrival: Option<StationBus>,
+}
+
+impl Render for NearbyStation {
+    const HEADER: &'static [&'static str] = &["ID", "Name", "Distance", "Next bus"];
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "human_name",
+        "distance_m",
+        "next_plate",
+        "next_arrive_secs",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.human_name.clone(),
+            format!("{:.0}", self.distance_m),
+            self.next_arrival
+                .as_ref()
+                .map(|bus| bus.license_plate.clone())
+                .unwrap_or_default(),
+            self.next_arrival
+                .as_ref()
+                .map(|bus| bus.arrive_time.as_secs().to_string())
+                .unwrap_or_default(),
+        ]
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.human_name.clone(),
+            format!("{:.0} m", self.distance_m),
+            self.next_arrival
+                .as_ref()
+                .map(|bus| {
+                    lang::tf(
+                        "{plate} in {time}",
+                        &[
+                            ("plate", &bus.license_plate),
+                            ("time", &humanize(bus.arrive_time)),
+                        ],
+                    )
+                })
+                .unwrap_or_else(|| "-".to_string()),
+        ]
+    }
+}
+
+pub async fn run(client: &Client, args: NearestArgs, format: Format) -> Result<(), Error> {
+    let stations = client.get_all_stations().await?;
+    let found = geo::stations_within(&stations, &args.coords, args.radius);
+
+    let mut nearby: Vec<NearbyStation> = found
+        .into_iter()
+        .take(args.count)
+        .map(|(station, distance_m)| NearbyStation {
+            id: station.id,
+            human_name: station.human_name.clone(),
+            distance_m,
+            next_arrival: None,
+        })
+        .collect();
+
+    if args.with_arrivals {
+        if nearby.len() > MAX_ARRIVAL_LOOKUPS {
+            let message = lang::tf(
+                "fetching arrivals for the nearest {count} stations only",
+                &[("count", &MAX_ARRIVAL_LOOKUPS)],
+            );
+            lang::warn(&message);
+        }
+        let session = client.open_session().await?;
+        for station in nearby.iter_mut().take(MAX_ARRIVAL_LOOKUPS) {
+            let buses = session.get_station_buses(StationId(station.id)).await?;
+            station.next_arrival = buses.into_iter().min_by_key(|bus| bus.arrive_time);
+        }
+    }
+
+    output::print(&nearby, format);
+    Ok(())
+}
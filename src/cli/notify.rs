@@ -0,0 +1,310 @@
+use clap::Args;
+use sivasbus::{Client, StationBus, StationId, normalize_plate};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use super::config::FileConfig;
+use super::lang;
+use super::output::humanize;
+use super::resolve;
+use super::watch::parse_interval;
+use super::{CliError, StationArg};
+
+#[derive(Args)]
+pub struct NotifyArgs {
+    #[command(flatten)]
+    station: StationArg,
+    /// Notify when a bus is closer than this, e.g. 5m or 90s
+    #[arg(long, default_value = "5m", value_parser = parse_interval)]
+    threshold: Duration,
+    /// Only buses currently on this line
+    #[arg(long)]
+    line: Option<String>,
+    /// Only the bus with this license plate
+    #[arg(long)]
+    plate: Option<String>,
+    /// Keep watching after the first notification
+    #[arg(long)]
+    repeat: bool,
+    /// Don't notify about the same bus again for this long
+    #[arg(long, default_value = "10m", value_parser = parse_interval)]
+    cooldown: Duration,
+    /// Time between refreshes, e.g. 20s or 1m
+    #[arg(long, default_value = "20s", value_parser = parse_interval)]
+    interval: Duration,
+}
+
+pub trait Notifier {
+    fn notify(&mut self, summary: &str, body: &str);
+}
+
+pub struct Bell;
+
+impl Notifier for Bell {
+    fn notify(&mut self, summary: &str, body: &str) {
+        println!("\x07{summary}: {body}");
+        std::io::stdout().flush().ok();
+    }
+}
+
+#[cfg(feature = "notify")]
+pub struct Desktop;
+
+#[cfg(feature = "notify")]
+impl Notifier for Desktop {
+    fn notify(&mut self, summary: &str, body: &str) {
+        let shown = notify_rust::Notification::new()
+            .appname("sivasbus")
+            .summary(summary)
+            .body(body)
+            .show();
+        if shown.is_err() {
+            Bell.notify(summary, body);
+        }
+    }
+}
+
+pub struct Trigger {
+    threshold: Duration,
+    cooldown: Duration,
+    plate: Option<String>,
+    notified: HashMap<String, Instant>,
+}
+
+impl Trigger {
+    pub fn new(threshold: Duration, cooldown: Duration, plate: Option<&str>) -> Trigger {
+        Trigger {
+            threshold,
+            cooldown,
+            plate: plate.map(normalize_plate),
+            notified: HashMap::new(),
+        }
+    }
+
+    /// Returns the buses that should be notified about now, `on_line` being
+    /// the plates allowed by the line filter if there is one.
+    pub fn check<'a>(
+        &mut self,
+        buses: &'a [StationBus],
+        on_line: Option<&HashSet<String>>,
+        now: Instant,
+    ) -> Vec<&'a StationBus> {
+        self.notified
+            .retain(|_, at| now.duration_since(*at) < self.cooldown);
+
+        let mut due = Vec::new();
+        for bus in buses {
+            let plate = normalize_plate(&bus.license_plate);
+            if bus.arrive_time > self.threshold
+                || self.plate.as_ref().is_some_and(|wanted| *wanted != plate)
+                || on_line.is_some_and(|plates| !plates.contains(&plate))
+                || self.notified.contains_key(&plate)
+            {
+                continue;
+            }
+            self.notified.insert(plate, now);
+            due.push(bus);
+        }
+        due
+    }
+}
+
+fn body(bus: &StationBus, line: Option<&str>) -> String {
+    let line = match line {
+        Some(line) => lang::tf("line {line}", &[("line", &line)]),
+        None => lang::t("unknown line").to_string(),
+    };
+    lang::tf(
+        "{plate} ({line}) arrives in {time}",
+        &[
+            ("plate", &bus.license_plate),
+            ("line", &line),
+            ("time", &humanize(bus.arrive_time)),
+        ],
+    )
+}
+
+/// Sends a notification for each bus `trigger` says is due, returning
+/// whether there were any.
+fn notify_due(
+    trigger: &mut Trigger,
+    notifier: &mut dyn Notifier,
+    buses: &[StationBus],
+    on_line: Option<&HashSet<String>>,
+    line: Option<&str>,
+    now: Instant,
+) -> bool {
+    let due = trigger.check(buses, on_line, now);
+    for bus in &due {
+        notifier.notify(lang::t("Bus arriving"), &body(bus, line));
+    }
+    !due.is_empty()
+}
+
+pub async fn run(
+    client: &Client,
+    config: &FileConfig,
+    args: NotifyArgs,
+    notifier: &mut dyn Notifier,
+) -> Result<(), CliError> {
+    let station = args.station.resolve(client, config).await?;
+    let line = match &args.line {
+        Some(line) => Some(resolve::line(client, config, line).await?),
+        None => None,
+    };
+    let session = client.open_session().await?;
+    let mut trigger = Trigger::new(args.threshold, args.cooldown, args.plate.as_deref());
+
+    loop {
+        let poll = async {
+            let buses = session.get_station_buses(StationId(station)).await?;
+            let on_line = match &line {
+                Some(line) => Some(
+                    session
+                        .get_line_buses(line)
+                        .await?
+                        .iter()
+                        .map(|bus| normalize_plate(&bus.license_plate))
+                        .collect::<HashSet<_>>(),
+                ),
+                None => None,
+            };
+            Ok::<_, sivasbus::Error>((buses, on_line))
+        };
+
+        let notified = tokio::select! {
+            result = poll => match result {
+                Ok((buses, on_line)) => notify_due(
+                    &mut trigger,
+                    notifier,
+                    &buses,
+                    on_line.as_ref(),
+                    line.as_deref(),
+                    Instant::now(),
+                ),
+                Err(error) => {
+                    let message = lang::error(&error);
+                    lang::warn(&message);
+                    false
+                }
+            },
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        if notified && !args.repeat {
+            return Ok(());
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(args.interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<(String, String)>);
+
+    impl Notifier for Recorder {
+        fn notify(&mut self, summary: &str, body: &str) {
+            self.0.push((summary.to_string(), body.to_string()));
+        }
+    }
+
+    fn bus(plate: &str, minutes: u64) -> StationBus {
+        StationBus {
+            license_plate: plate.to_string(),
+            arrive_time: Duration::from_secs(minutes * 60),
+        }
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn notifies_buses_under_the_threshold() {
+        let mut trigger = Trigger::new(minutes(5), minutes(10), None);
+        let mut recorder = Recorder::default();
+        let buses = [bus("58 AC 123", 4), bus("58 AB 999", 11)];
+
+        assert!(notify_due(
+            &mut trigger,
+            &mut recorder,
+            &buses,
+            None,
+            Some("1"),
+            Instant::now(),
+        ));
+        assert_eq!(recorder.0.len(), 1);
+        let (summary, body) = &recorder.0[0];
+        assert_eq!(summary, "Bus arriving");
+        assert!(body.starts_with("58 AC 123 (line 1)"), "{body}");
+    }
+
+    #[test]
+    fn nothing_due_sends_nothing() {
+        let mut trigger = Trigger::new(minutes(5), minutes(10), None);
+        let mut recorder = Recorder::default();
+
+        let buses = [bus("58 AB 999", 11)];
+        let notified = notify_due(
+            &mut trigger,
+            &mut recorder,
+            &buses,
+            None,
+            None,
+            Instant::now(),
+        );
+        assert!(!notified);
+        assert!(recorder.0.is_empty());
+    }
+
+    #[test]
+    fn cooldown_suppresses_repeats_until_it_passes() {
+        let mut trigger = Trigger::new(minutes(5), minutes(10), None);
+        let start = Instant::now();
+        let buses = [bus("58 AC 123", 4)];
+
+        assert_eq!(trigger.check(&buses, None, start).len(), 1);
+        assert!(trigger.check(&buses, None, start + minutes(1)).is_empty());
+        assert!(trigger.check(&buses, None, start + minutes(9)).is_empty());
+        assert_eq!(trigger.check(&buses, None, start + minutes(10)).len(), 1);
+    }
+
+    #[test]
+    fn cooldown_is_per_normalized_plate() {
+        let mut trigger = Trigger::new(minutes(5), minutes(10), None);
+        let now = Instant::now();
+
+        assert_eq!(trigger.check(&[bus("58 AC 123", 4)], None, now).len(), 1);
+        assert!(trigger.check(&[bus(" 58ac123 ", 3)], None, now).is_empty());
+        assert_eq!(trigger.check(&[bus("58 AB 999", 3)], None, now).len(), 1);
+    }
+
+    #[test]
+    fn filters_by_plate_and_line() {
+        let now = Instant::now();
+        let buses = [bus("58 AC 123", 2), bus("58 AB 999", 3)];
+
+        let mut trigger = Trigger::new(minutes(5), minutes(10), Some("58ab999"));
+        let due = trigger.check(&buses, None, now);
+        assert_eq!(due, [&buses[1]]);
+
+        let on_line = HashSet::from([normalize_plate("58 AC 123")]);
+        let mut trigger = Trigger::new(minutes(5), minutes(10), None);
+        let due = trigger.check(&buses, Some(&on_line), now);
+        assert_eq!(due, [&buses[0]]);
+    }
+
+    #[test]
+    fn threshold_is_inclusive() {
+        let mut trigger = Trigger::new(minutes(5), minutes(10), None);
+        let buses = [bus("58 AC 123", 5)];
+        assert_eq!(trigger.check(&buses, None, Instant::now()).len(), 1);
+    }
+}
@@ -0,0 +1,205 @@
+use clap::ValueEnum;
+use comfy_table::Table;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use sivasbus::{Line, LineBus, LineProgress, Station, StationBus};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::lang;
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+pub trait Render: Serialize {
+    const HEADER: &'static [&'static str];
+    const COLUMNS: &'static [&'static str];
+
+    fn record(&self) -> Vec<String>;
+
+    fn table_row(&self) -> Vec<String> {
+        self.record()
+    }
+}
+
+pub fn print<T: Render>(items: &[T], format: Format) {
+    match format {
+        Format::Table => {
+            let mut table = Table::new();
+            table.load_preset(comfy_table::presets::UTF8_BORDERS_ONLY);
+            table.set_header(T::HEADER.iter().map(|header| lang::t(header)));
+            for item in items {
+                table.add_row(item.table_row());
+            }
+            println!("{table}");
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(items).unwrap()),
+        Format::Csv => write_csv(items, std::io::stdout()).unwrap(),
+        Format::Ndjson => {
+            for item in items {
+                println!("{}", serde_json::to_string(item).unwrap());
+            }
+        }
+    }
+}
+
+pub fn write_csv<T: Render>(items: &[T], writer: impl std::io::Write) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(T::COLUMNS)?;
+    for item in items {
+        writer.write_record(item.record())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+static PROGRESS: AtomicBool = AtomicBool::new(true);
+
+/// Turns progress bars off, for `--no-progress`.
+pub fn disable_progress() {
+    PROGRESS.store(false, Ordering::Relaxed);
+}
+
+/// A progress bar on stderr, hidden with `--no-progress` or when stdout or
+/// stderr isn't a terminal, so it doesn't end up in pipes and logs.
+pub fn progress_bar(unit: &'static str) -> ProgressBar {
+    let template = format!(
+        "{{bar:30}} {{pos}}/{{len}} {} {{elapsed}} ({} {{eta}}) {{msg}}",
+        lang::t(unit),
+        lang::t("eta"),
+    );
+    let bar = ProgressBar::new(0).with_style(ProgressStyle::with_template(&template).unwrap());
+    let shown = PROGRESS.load(Ordering::Relaxed)
+        && std::io::stdout().is_terminal()
+        && std::io::stderr().is_terminal();
+    if !shown {
+        bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    bar
+}
+
+/// Moves `bar` along a line by line fetch, labelling it with the last line
+/// done. Failed lines get a warning above the bar instead of stopping it.
+pub fn line_progress(bar: &ProgressBar) -> impl FnMut(LineProgress) + '_ {
+    |progress| {
+        bar.set_length(progress.total as u64);
+        bar.set_position(progress.done as u64);
+        match progress.result {
+            Ok(Some(count)) => bar.set_message(lang::tf(
+                "line {line}: {count} buses",
+                &[("line", &progress.line), ("count", &count)],
+            )),
+            Ok(None) => bar.set_message(lang::tf("line {line}", &[("line", &progress.line)])),
+            Err(error) => bar.suspend(|| {
+                lang::warn(&lang::tf(
+                    "line {line} failed: {error}",
+                    &[("line", &progress.line), ("error", &lang::error(error))],
+                ))
+            }),
+        }
+    }
+}
+
+pub fn humanize(duration: Duration) -> String {
+    match duration.as_secs() / 60 {
+        0 => lang::t("now").to_string(),
+        mins => lang::tf("{mins} min", &[("mins", &mins)]),
+    }
+}
+
+impl Render for Line {
+    const HEADER: &'static [&'static str] = &["ID", "Name"];
+    const COLUMNS: &'static [&'static str] = &["id", "human_name"];
+
+    fn record(&self) -> Vec<String> {
+        vec![self.id.clone(), self.human_name.clone()]
+    }
+}
+
+impl Render for Station {
+    const HEADER: &'static [&'static str] = &["ID", "Name", "Lat", "Long"];
+    const COLUMNS: &'static [&'static str] = &["id", "human_name", "lat", "long"];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.human_name.clone(),
+            self.coords.lat.to_string(),
+            self.coords.long.to_string(),
+        ]
+    }
+}
+
+impl Render for StationBus {
+    const HEADER: &'static [&'static str] = &["Plate", "Arrives in"];
+    const COLUMNS: &'static [&'static str] = &["license_plate", "arrive_secs"];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.license_plate.clone(),
+            self.arrive_time.as_secs().to_string(),
+        ]
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        vec![self.license_plate.clone(), humanize(self.arrive_time)]
+    }
+}
+
+impl Render for LineBus {
+    const HEADER: &'static [&'static str] = &["Plate", "Lat", "Long"];
+    const COLUMNS: &'static [&'static str] = &["license_plate", "lat", "long"];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.license_plate.clone(),
+            self.coords.lat.to_string(),
+            self.coords.long.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sivasbus::Error;
+
+    fn progress<'a>(
+        done: usize,
+        line: &'a str,
+        result: Result<Option<usize>, &'a Error>,
+    ) -> LineProgress<'a> {
+        LineProgress {
+            done,
+            total: 3,
+            line,
+            result,
+        }
+    }
+
+    #[test]
+    fn line_progress_moves_and_labels_the_bar() {
+        let bar = ProgressBar::hidden();
+        let mut tick = line_progress(&bar);
+
+        tick(progress(1, "1", Ok(Some(2))));
+        assert_eq!((bar.position(), bar.length()), (1, Some(3)));
+        assert_eq!(bar.message(), "line 1: 2 buses");
+
+        tick(progress(2, "7A", Err(&Error::NoToken)));
+        assert_eq!(bar.position(), 2);
+        assert_eq!(bar.message(), "line 1: 2 buses");
+
+        tick(progress(3, "9", Ok(None)));
+        assert_eq!(bar.position(), 3);
+        assert_eq!(bar.message(), "line 9");
+    }
+}
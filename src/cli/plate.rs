@@ -0,0 +1,135 @@
+use clap::Args;
+use serde::Serialize;
+use sivasbus::{Client, Coords, normalize_plate};
+
+use super::CliError;
+use super::config::FileConfig;
+use super::lang;
+use super::nearest::parse_coords;
+use super::output::{self, Format, Render, line_progress, progress_bar};
+use super::resolve;
+
+const SEARCH_CONCURRENCY: usize = 4;
+
+#[derive(Args)]
+pub struct PlateArgs {
+    /// License plate, spaces and case don't matter
+    plate: String,
+    /// Lines to search, comma separated, all lines if not given
+    #[arg(long, value_delimiter = ',')]
+    lines: Vec<String>,
+    /// Show the distance and bearing from these coordinates
+    #[arg(long, value_parser = parse_coords, allow_hyphen_values = true)]
+    from: Option<Coords>,
+}
+
+#[derive(Serialize)]
+struct Location {
+    line: String,
+    license_plate: String,
+    lat: f64,
+    long: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distance_m: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bearing_deg: Option<f64>,
+    map_url: String,
+}
+
+impl Render for Location {
+    const HEADER: &'static [&'static str] =
+        &["Line", "Plate", "Lat", "Long", "Distance", "Bearing", "Map"];
+    const COLUMNS: &'static [&'static str] = &[
+        "line",
+        "license_plate",
+        "lat",
+        "long",
+        "distance_m",
+        "bearing_deg",
+        "map_url",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.line.clone(),
+            self.license_plate.clone(),
+            self.lat.to_string(),
+            self.long.to_string(),
+            self.distance_m
+                .map(|distance| format!("{distance:.0}"))
+                .unwrap_or_default(),
+            self.bearing_deg
+                .map(|bearing| format!("{bearing:.0}"))
+                .unwrap_or_default(),
+            self.map_url.clone(),
+        ]
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        vec![
+            self.line.clone(),
+            self.license_plate.clone(),
+            self.lat.to_string(),
+            self.long.to_string(),
+            self.distance_m
+                .map(|distance| format!("{distance:.0} m"))
+                .unwrap_or_else(|| "-".to_string()),
+            self.bearing_deg
+                .map(|bearing| format!("{bearing:.0}°"))
+                .unwrap_or_else(|| "-".to_string()),
+            self.map_url.clone(),
+        ]
+    }
+}
+
+pub async fn run(
+    client: &Client,
+    config: &FileConfig,
+    args: PlateArgs,
+    format: Format,
+) -> Result<(), CliError> {
+    let lines = if args.lines.is_empty() {
+        let lines: Vec<String> = client
+            .get_lines()
+            .await?
+            .into_iter()
+            .map(|line| line.id)
+            .collect();
+        let message = lang::tf(
+            "searching all {count} lines, pass --lines to make fewer requests",
+            &[("count", &lines.len())],
+        );
+        lang::warn(&message);
+        lines
+    } else {
+        let mut lines = Vec::new();
+        for line in &args.lines {
+            lines.push(resolve::line(client, config, line).await?);
+        }
+        lines
+    };
+
+    let bar = progress_bar("lines");
+    let found = client
+        .find_bus_with_progress(&args.plate, &lines, SEARCH_CONCURRENCY, line_progress(&bar))
+        .await;
+    bar.finish_and_clear();
+    let found = found.ok_or_else(|| CliError::PlateNotFound(normalize_plate(&args.plate)))?;
+
+    let coords = &found.bus.coords;
+    let location = Location {
+        line: found.line,
+        license_plate: found.bus.license_plate.clone(),
+        lat: coords.lat,
+        long: coords.long,
+        distance_m: args.from.as_ref().map(|from| from.distance_m(coords)),
+        bearing_deg: args.from.as_ref().map(|from| from.bearing_deg(coords)),
+        map_url: format!(
+            "https://www.openstreetmap.org/?mlat={lat}&mlon={long}#map=17/{lat}/{long}",
+            lat = coords.lat,
+            long = coords.long
+        ),
+    };
+    output::print(&[location], format);
+    Ok(())
+}
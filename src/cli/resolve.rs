@@ -0,0 +1,288 @@
+//! Resolves station and line names to ids with a fuzzy search over a catalog
+//! cached in the cache directory, along with the names already resolved.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use sivasbus::{Client, Line, Station};
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use super::CliError;
+use super::config::{self, FileConfig};
+use super::lang;
+
+/// Names scoring below this aren't considered a match.
+const MATCH_THRESHOLD: f64 = 0.75;
+const MAX_CANDIDATES: usize = 10;
+const CATALOG_TTL: TimeDelta = TimeDelta::days(1);
+
+#[derive(Serialize, Deserialize)]
+struct Catalog<T> {
+    fetched_at: DateTime<Utc>,
+    items: Vec<T>,
+    /// Queries already resolved against these items.
+    resolved: BTreeMap<String, usize>,
+}
+
+impl<T> Catalog<T> {
+    fn is_fresh(&self) -> bool {
+        Utc::now() - self.fetched_at < CATALOG_TTL
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Cache {
+    stations: Option<Catalog<Station>>,
+    lines: Option<Catalog<Line>>,
+}
+
+fn cache_path() -> Option<PathBuf> {
+    Some(config::cache_dir()?.join("catalog.json"))
+}
+
+fn load_cache(path: Option<&PathBuf>) -> Cache {
+    path.and_then(|path| std::fs::read(path).ok())
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(path: Option<&PathBuf>, cache: &Cache) {
+    let Some(path) = path else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).ok();
+    }
+    if let Ok(json) = serde_json::to_vec(cache) {
+        std::fs::write(path, json).ok();
+    }
+}
+
+/// Lowercases and strips Turkish diacritics and punctuation, so that
+/// "Cumhuriyet Meydanı" and "CUMHURİYET MEYDANI" compare equal.
+fn normalize(text: &str) -> String {
+    let folded: String = text
+        .chars()
+        .flat_map(|c| match c {
+            'İ' | 'I' | 'ı' => vec!['i'],
+            'Ş' | 'ş' => vec!['s'],
+            'Ğ' | 'ğ' => vec!['g'],
+            'Ü' | 'ü' => vec!['u'],
+            'Ö' | 'ö' => vec!['o'],
+            'Ç' | 'ç' => vec!['c'],
+            c if c.is_alphanumeric() => c.to_lowercase().collect(),
+            _ => vec![' '],
+        })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn word_score(query: &str, word: &str) -> f64 {
+    if query == word {
+        1.0
+    } else if word.starts_with(query) {
+        0.9
+    } else if word.contains(query) {
+        0.8
+    } else {
+        let length = query.chars().count().max(word.chars().count());
+        1.0 - edit_distance(query, word) as f64 / length as f64
+    }
+}
+
+/// How well `query` matches `name`, from 0 to 1. Each query word is matched
+/// against its best name word, so typos and missing words lower the score.
+fn score(query: &str, name: &str) -> f64 {
+    let name = normalize(name);
+    if query == name {
+        return 1.0;
+    }
+    let words: Vec<&str> = name.split(' ').collect();
+    let query: Vec<&str> = query.split(' ').collect();
+    let total: f64 = query
+        .iter()
+        .map(|q| {
+            words
+                .iter()
+                .map(|word| word_score(q, word))
+                .fold(0.0, f64::max)
+        })
+        .sum();
+    // Slightly below an exact match, so "meydan" prefers "Meydan" over
+    // "Meydan 2".
+    (total / query.len() as f64).min(0.99)
+}
+
+/// Indices of the items matching `query`, best first.
+fn search<T>(items: &[T], query: &str, names: impl Fn(&T) -> Vec<&str>) -> Vec<(usize, f64)> {
+    let query = normalize(query);
+    let mut matches: Vec<(usize, f64)> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let best = names(item)
+                .into_iter()
+                .map(|name| score(&query, name))
+                .fold(0.0, f64::max);
+            (i, best)
+        })
+        .filter(|(_, score)| *score >= MATCH_THRESHOLD)
+        .collect();
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+    matches
+}
+
+fn choose(kind: &'static str, query: &str, candidates: Vec<String>) -> Result<usize, CliError> {
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if !interactive {
+        return Err(CliError::Ambiguous {
+            kind,
+            query: query.to_string(),
+            candidates,
+        });
+    }
+
+    let mut stderr = std::io::stderr();
+    eprintln!(
+        "{}",
+        lang::tf(
+            "several {kind}s match {query}:",
+            &[("kind", &lang::t(kind)), ("query", &format!("{query:?}"))],
+        )
+    );
+    for (i, candidate) in candidates.iter().enumerate() {
+        eprintln!("{:>3}) {candidate}", i + 1);
+    }
+    eprint!(
+        "{}",
+        lang::tf("choose 1-{count}: ", &[("count", &candidates.len())])
+    );
+    stderr.flush().ok();
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok();
+    match answer.trim().parse::<usize>() {
+        Ok(n) if (1..=candidates.len()).contains(&n) => Ok(n - 1),
+        _ => Err(CliError::Ambiguous {
+            kind,
+            query: query.to_string(),
+            candidates,
+        }),
+    }
+}
+
+/// Picks the item for `query`, prompting when several match about as well.
+fn pick<T>(
+    kind: &'static str,
+    catalog: &mut Catalog<T>,
+    query: &str,
+    names: impl Fn(&T) -> Vec<&str>,
+    label: impl Fn(&T) -> String,
+) -> Result<usize, CliError> {
+    let key = normalize(query);
+    if let Some(&index) = catalog.resolved.get(&key) {
+        return Ok(index);
+    }
+
+    let matches = search(&catalog.items, query, names);
+    let index = match matches.as_slice() {
+        [] => {
+            return Err(CliError::NoMatch {
+                kind,
+                query: query.to_string(),
+            });
+        }
+        [(index, _)] => *index,
+        [(index, best), (_, second), ..] if *best == 1.0 && *second < 1.0 => *index,
+        _ => {
+            let shown = &matches[..matches.len().min(MAX_CANDIDATES)];
+            let candidates = shown
+                .iter()
+                .map(|(i, _)| label(&catalog.items[*i]))
+                .collect();
+            shown[choose(kind, query, candidates)?].0
+        }
+    };
+    catalog.resolved.insert(key, index);
+    Ok(index)
+}
+
+fn fresh<T>(catalog: Option<Catalog<T>>) -> Option<Catalog<T>> {
+    catalog.filter(Catalog::is_fresh)
+}
+
+fn new_catalog<T>(items: Vec<T>) -> Catalog<T> {
+    Catalog {
+        fetched_at: Utc::now(),
+        items,
+        resolved: BTreeMap::new(),
+    }
+}
+
+/// Resolves a station id, alias or name. Ids and aliases never touch the
+/// network or the cache.
+pub async fn station(client: &Client, config: &FileConfig, query: &str) -> Result<i32, CliError> {
+    if let Some(id) = config.station(query) {
+        return Ok(id);
+    }
+
+    let path = cache_path();
+    let mut cache = load_cache(path.as_ref());
+    let mut catalog = match fresh(cache.stations.take()) {
+        Some(catalog) => catalog,
+        None => new_catalog(client.get_all_stations().await?),
+    };
+    let picked = pick(
+        "station",
+        &mut catalog,
+        query,
+        |station| vec![&station.human_name],
+        |station| format!("{} ({})", station.human_name, station.id),
+    );
+    let id = picked.map(|index| catalog.items[index].id);
+    cache.stations = Some(catalog);
+    save_cache(path.as_ref(), &cache);
+    id
+}
+
+/// Resolves a line id, alias or name. Ids and aliases never touch the
+/// network or the cache.
+pub async fn line(client: &Client, config: &FileConfig, query: &str) -> Result<String, CliError> {
+    if let Some(id) = config.line(query) {
+        return Ok(id);
+    }
+
+    let path = cache_path();
+    let mut cache = load_cache(path.as_ref());
+    let mut catalog = match fresh(cache.lines.take()) {
+        Some(catalog) => catalog,
+        None => new_catalog(client.get_lines().await?),
+    };
+    let picked = pick(
+        "line",
+        &mut catalog,
+        query,
+        |line| vec![&line.id, &line.human_name],
+        |line| format!("{} {}", line.id, line.human_name),
+    );
+    let id = picked.map(|index| catalog.items[index].id.clone());
+    cache.lines = Some(catalog);
+    save_cache(path.as_ref(), &cache);
+    id
+}
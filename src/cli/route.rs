@@ -0,0 +1,224 @@
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use sivasbus::bunching::RouteShape;
+use sivasbus::{Client, Coords, Station, geo};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::CliError;
+use super::config::FileConfig;
+use super::lang;
+use super::output::{self, Format, Render};
+use super::resolve;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    /// Stops in the order the line page lists them
+    Outbound,
+    /// Stops in reverse order
+    Inbound,
+}
+
+#[derive(Args)]
+pub struct RouteArgs {
+    line: String,
+    #[arg(long, value_enum, default_value = "outbound")]
+    direction: Direction,
+    /// Show which leg each bus on the line is currently on
+    #[arg(long)]
+    live: bool,
+    /// GeoJSON LineString of the line's path, for distances along it
+    /// rather than straight between stops
+    #[arg(long)]
+    shape: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct RouteStop {
+    sequence: usize,
+    id: i32,
+    human_name: String,
+    smart: bool,
+    distance_m: f64,
+    // Without a --shape, distances add up straight-line legs.
+    approximate: bool,
+}
+
+impl RouteStop {
+    fn distance(&self) -> String {
+        let prefix = if self.approximate { "~" } else { "" };
+        format!("{prefix}{:.2} km", self.distance_m / 1000.0)
+    }
+}
+
+impl Render for RouteStop {
+    const HEADER: &'static [&'static str] = &["#", "ID", "Name", "Smart", "Distance"];
+    const COLUMNS: &'static [&'static str] = &[
+        "sequence",
+        "id",
+        "human_name",
+        "smart",
+        "distance_m",
+        "approximate",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.sequence.to_string(),
+            self.id.to_string(),
+            self.human_name.clone(),
+            self.smart.to_string(),
+            format!("{:.0}", self.distance_m),
+            self.approximate.to_string(),
+        ]
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        vec![
+            self.sequence.to_string(),
+            self.id.to_string(),
+            self.human_name.clone(),
+            if self.smart { "*" } else { "" }.to_string(),
+            self.distance(),
+        ]
+    }
+}
+
+/// A stop with the buses on the leg towards the next stop.
+#[derive(Serialize)]
+struct LiveRouteStop {
+    #[serde(flatten)]
+    stop: RouteStop,
+    buses: Vec<String>,
+}
+
+impl Render for LiveRouteStop {
+    const HEADER: &'static [&'static str] = &["#", "ID", "Name", "Smart", "Distance", "Buses"];
+    const COLUMNS: &'static [&'static str] = &[
+        "sequence",
+        "id",
+        "human_name",
+        "smart",
+        "distance_m",
+        "approximate",
+        "buses",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        let mut record = self.stop.record();
+        record.push(self.buses.join(" "));
+        record
+    }
+
+    fn table_row(&self) -> Vec<String> {
+        let mut row = self.stop.table_row();
+        row.push(self.buses.join("\n"));
+        row
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Geometry {
+    LineString { coordinates: Vec<[f64; 2]> },
+}
+
+fn load_shape(path: &Path) -> Result<RouteShape, CliError> {
+    let json = std::fs::read(path).map_err(|error| CliError::Io(path.to_path_buf(), error))?;
+    let Geometry::LineString { coordinates } = serde_json::from_slice(&json)
+        .map_err(|error| CliError::Shape(path.to_path_buf(), error))?;
+    Ok(RouteShape::new(
+        coordinates
+            .into_iter()
+            .map(|[long, lat]| Coords { lat, long })
+            .collect(),
+    ))
+}
+
+/// How far along the route each stop is, following `shape` if every stop
+/// is on it and adding up straight-line legs otherwise.
+fn distances(stations: &[Station], shape: Option<&RouteShape>) -> (Vec<f64>, bool) {
+    let along: Option<Vec<f64>> = shape.and_then(|shape| {
+        stations
+            .iter()
+            .map(|station| shape.position_m(&station.coords))
+            .collect()
+    });
+    if let Some(along) = along {
+        let start = along.first().copied().unwrap_or(0.0);
+        return (along.iter().map(|m| (m - start).abs()).collect(), false);
+    }
+    if shape.is_some() {
+        lang::warn(lang::t(
+            "some stops are off the route shape, using straight-line distances",
+        ));
+    }
+
+    let mut distance_m = 0.0;
+    let straight = stations
+        .iter()
+        .enumerate()
+        .map(|(index, station)| {
+            if index > 0 {
+                distance_m += stations[index - 1].coords.distance_m(&station.coords);
+            }
+            distance_m
+        })
+        .collect();
+    (straight, true)
+}
+
+pub async fn run(
+    client: &Client,
+    config: &FileConfig,
+    args: RouteArgs,
+    format: Format,
+) -> Result<(), CliError> {
+    let shape = args.shape.as_deref().map(load_shape).transpose()?;
+    let line = resolve::line(client, config, &args.line).await?;
+    let mut stations = client.get_stations(&line).await?;
+    if args.direction == Direction::Inbound {
+        stations.reverse();
+    }
+    let smart: HashSet<i32> = client
+        .get_all_stations()
+        .await?
+        .iter()
+        .map(|station| station.id)
+        .collect();
+
+    let (distances, approximate) = distances(&stations, shape.as_ref());
+    let stops: Vec<RouteStop> = stations
+        .iter()
+        .zip(distances)
+        .enumerate()
+        .map(|(index, (station, distance_m))| RouteStop {
+            sequence: index + 1,
+            id: station.id,
+            human_name: station.human_name.clone(),
+            smart: smart.contains(&station.id),
+            distance_m,
+            approximate,
+        })
+        .collect();
+
+    if !args.live {
+        output::print(&stops, format);
+        return Ok(());
+    }
+
+    let mut stops: Vec<LiveRouteStop> = stops
+        .into_iter()
+        .map(|stop| LiveRouteStop {
+            stop,
+            buses: Vec::new(),
+        })
+        .collect();
+    for bus in client.get_line_buses(&line).await? {
+        if let Some(leg) = geo::nearest_leg(&stations, &bus.coords) {
+            stops[leg].buses.push(bus.license_plate);
+        }
+    }
+    output::print(&stops, format);
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use clap::Args;
+use sivasbus::diff::{self, Renamed, SnapshotDiff};
+use sivasbus::{Client, NetworkSnapshot, io};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use super::CliError;
+use super::lang;
+use super::output::{Format, line_progress, progress_bar};
+
+#[derive(Args)]
+pub struct SnapshotArgs {
+    /// File to write the snapshot to
+    #[arg(long)]
+    out: PathBuf,
+    /// Also record the buses on every line
+    #[arg(long)]
+    with_buses: bool,
+    /// Overwrite an existing file
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    old: PathBuf,
+    new: PathBuf,
+}
+
+pub async fn snapshot(client: &Client, args: SnapshotArgs) -> Result<(), CliError> {
+    if args.out.exists() && !args.force {
+        return Err(CliError::Exists(args.out));
+    }
+
+    let bar = progress_bar("lines");
+    let progress = line_progress(&bar);
+    let snapshot = if args.with_buses {
+        client.get_all_data_with_progress(progress).await
+    } else {
+        client.get_network_with_progress(progress).await
+    };
+    bar.finish_and_clear();
+    let snapshot = snapshot?;
+
+    io::save_snapshot(&snapshot, &args.out)
+        .await
+        .map_err(|error| CliError::Io(args.out.clone(), error))?;
+    let summary = lang::tf(
+        "{lines} lines and {stations} stations",
+        &[
+            ("lines", &snapshot.lines.len()),
+            ("stations", &snapshot.stations.len()),
+        ],
+    );
+    eprintln!(
+        "{}",
+        lang::tf(
+            "wrote {summary} to {path}",
+            &[("summary", &summary), ("path", &args.out.display())]
+        )
+    );
+    Ok(())
+}
+
+async fn load(path: &Path) -> Result<NetworkSnapshot, CliError> {
+    io::load_snapshot(path)
+        .await
+        .map_err(|error| CliError::Snapshot(path.to_path_buf(), error))
+}
+
+fn renamed<Id: Display>(kind: &'static str, renamed: &Renamed<Id>) -> String {
+    lang::tf(
+        "~ {kind} {id} renamed {old} -> {new}",
+        &[
+            ("kind", &lang::t(kind)),
+            ("id", &renamed.id),
+            ("old", &format!("{:?}", renamed.old_name)),
+            ("new", &format!("{:?}", renamed.new_name)),
+        ],
+    )
+}
+
+fn print_human(diff: &SnapshotDiff) {
+    let (station, line) = (lang::t("station"), lang::t("line"));
+    for added in &diff.added_stations {
+        println!("+ {station} {} {}", added.id, added.human_name);
+    }
+    for removed in &diff.removed_stations {
+        println!("- {station} {} {}", removed.id, removed.human_name);
+    }
+    for renamed_station in &diff.renamed_stations {
+        println!("{}", renamed("station", renamed_station));
+    }
+    for moved in &diff.moved_stations {
+        let distance = format!("{:.0}", moved.distance_m);
+        println!(
+            "{}",
+            lang::tf(
+                "~ station {id} {name} moved {distance} m",
+                &[
+                    ("id", &moved.id),
+                    ("name", &moved.human_name),
+                    ("distance", &distance),
+                ],
+            )
+        );
+    }
+    for added in &diff.added_lines {
+        println!("+ {line} {} {}", added.id, added.human_name);
+    }
+    for removed in &diff.removed_lines {
+        println!("- {line} {} {}", removed.id, removed.human_name);
+    }
+    for renamed_line in &diff.renamed_lines {
+        println!("{}", renamed("line", renamed_line));
+    }
+    for rerouted in &diff.rerouted_lines {
+        println!(
+            "{}",
+            lang::tf("~ line {id} stops changed", &[("id", rerouted)])
+        );
+    }
+}
+
+/// Exits with 0 when the snapshots match and 1 when they differ, like
+/// diff(1).
+pub async fn diff(args: DiffArgs, format: Format) -> Result<ExitCode, CliError> {
+    let old = load(&args.old).await?;
+    let new = load(&args.new).await?;
+    let diff = diff::diff_snapshots(&old, &new);
+
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&diff).unwrap()),
+        Format::Ndjson => println!("{}", serde_json::to_string(&diff).unwrap()),
+        Format::Table | Format::Csv => print_human(&diff),
+    }
+
+    Ok(if diff.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
@@ -0,0 +1,171 @@
+use clap::{Args, ValueEnum};
+use serde_json::json;
+use sivasbus::session::SessionState;
+use sivasbus::{Client, StationBus, StationId};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::config::{self, FileConfig};
+use super::lang;
+use super::resolve;
+use super::watch::parse_interval;
+use super::{CliError, StationArg};
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Style {
+    Waybar,
+    I3,
+}
+
+#[derive(Args)]
+pub struct StatusbarArgs {
+    #[command(flatten)]
+    station: StationArg,
+    /// Only buses currently on this line
+    #[arg(long)]
+    line: Option<String>,
+    #[arg(long, value_enum, default_value = "waybar")]
+    style: Style,
+    /// Arrivals closer than this get the "soon" class
+    #[arg(long, default_value = "5m", value_parser = parse_interval)]
+    soon: Duration,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Class {
+    Soon,
+    Later,
+    None,
+}
+
+impl Class {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Class::Soon => "soon",
+            Class::Later => "later",
+            Class::None => "none",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Class::Soon => "#50fa7b",
+            Class::Later => "#f8f8f2",
+            Class::None => "#6272a4",
+        }
+    }
+}
+
+fn minutes(bus: &StationBus) -> String {
+    let mins = bus.arrive_time.as_secs() / 60;
+    lang::tf("{mins}m", &[("mins", &mins)])
+}
+
+/// Builds the status bar payload for a board, `label` naming the line if
+/// the board was filtered to one.
+pub fn payload(
+    buses: Option<&[StationBus]>,
+    label: Option<&str>,
+    soon: Duration,
+    style: Style,
+) -> serde_json::Value {
+    let next = buses.and_then(|buses| buses.iter().min());
+    let (text, class) = match next {
+        Some(bus) => {
+            let name = label.unwrap_or(&bus.license_plate);
+            let class = match bus.arrive_time <= soon {
+                true => Class::Soon,
+                false => Class::Later,
+            };
+            (format!("{name} {}", minutes(bus)), class)
+        }
+        None if buses.is_some() => ("-".to_string(), Class::None),
+        None => (lang::t("no data").to_string(), Class::None),
+    };
+
+    match style {
+        Style::Waybar => {
+            let tooltip = match buses {
+                Some(buses) if !buses.is_empty() => {
+                    let mut buses = buses.to_vec();
+                    buses.sort();
+                    buses
+                        .iter()
+                        .map(|bus| format!("{} {}", bus.license_plate, minutes(bus)))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+                Some(_) => lang::t("no buses").to_string(),
+                None => lang::t("no data").to_string(),
+            };
+            json!({"text": text, "tooltip": tooltip, "class": class.as_str()})
+        }
+        Style::I3 => json!({
+            "name": "sivasbus",
+            "full_text": text,
+            "color": class.color(),
+        }),
+    }
+}
+
+fn state_path() -> Option<PathBuf> {
+    Some(config::cache_dir()?.join("session.json"))
+}
+
+fn load_state(path: Option<&PathBuf>) -> SessionState {
+    path.and_then(|path| std::fs::read(path).ok())
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: Option<&PathBuf>, state: &SessionState) {
+    let Some(path) = path else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).ok();
+    }
+    if let Ok(json) = serde_json::to_vec(state) {
+        std::fs::write(path, json).ok();
+    }
+}
+
+pub async fn run(
+    client: &Client,
+    config: &FileConfig,
+    args: StatusbarArgs,
+) -> Result<(), CliError> {
+    let station = args.station.resolve(client, config).await?;
+    let line = match &args.line {
+        Some(line) => Some(resolve::line(client, config, line).await?),
+        None => None,
+    };
+
+    let path = state_path();
+    let session = client.restore_session(load_state(path.as_ref()));
+    let board = async {
+        let mut buses = session.get_station_buses(StationId(station)).await?;
+        if let Some(line) = &line {
+            let plates: HashSet<String> = session
+                .get_line_buses(line)
+                .await?
+                .into_iter()
+                .map(|bus| bus.license_plate)
+                .collect();
+            buses.retain(|bus| plates.contains(&bus.license_plate));
+        }
+        Ok::<_, sivasbus::Error>(buses)
+    }
+    .await;
+    save_state(path.as_ref(), &session.state());
+
+    let payload = payload(
+        board.as_deref().ok(),
+        line.as_deref(),
+        args.soon,
+        args.style,
+    );
+    println!("{payload}");
+    Ok(())
+}
@@ -0,0 +1,552 @@
+use chrono::{DateTime, Local, Utc};
+use futures::{Stream, StreamExt};
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line as TextLine;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use sivasbus::favorites;
+use sivasbus::{Client, Error, LineBus, StationBus, StationId};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::CliError;
+use super::config::FileConfig;
+use super::lang;
+use super::output::humanize;
+
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 6;
+const SIDEBAR_MIN_WIDTH: u16 = 60;
+
+#[derive(Clone, Debug)]
+pub enum Target {
+    Station { name: String, id: i32 },
+    Line { name: String, id: String },
+}
+
+impl Target {
+    fn label(&self) -> String {
+        match self {
+            Target::Station { name, id } => format!("S {name} ({id})"),
+            Target::Line { name, id } => format!("L {name} ({id})"),
+        }
+    }
+
+    fn updates(
+        &self,
+        client: &Client,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<Board, Error>> + Send + 'static {
+        match self.clone() {
+            Target::Line { id, .. } => client
+                .watch_line_buses(&id, interval)
+                .map(|result| result.map(Board::Buses))
+                .boxed(),
+            Target::Station { id, .. } => client
+                .watch_station_buses(StationId(id), interval)
+                .map(|result| result.map(Board::Arrivals))
+                .boxed(),
+        }
+    }
+}
+
+pub fn targets(config: &FileConfig) -> Vec<Target> {
+    config
+        .favorites
+        .list()
+        .iter()
+        .map(|favorite| match &favorite.target {
+            favorites::Target::Station(id) => Target::Station {
+                name: favorite.label.clone(),
+                id: *id,
+            },
+            favorites::Target::Line(id) => Target::Line {
+                name: favorite.label.clone(),
+                id: id.clone(),
+            },
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum Board {
+    Arrivals(Vec<StationBus>),
+    Buses(Vec<LineBus>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Pane {
+    Targets,
+    Board,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Connection {
+    Connecting,
+    Online,
+    Offline,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Key(KeyEvent),
+    Resize,
+    Update {
+        generation: u64,
+        result: Result<Board, Error>,
+    },
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum Action {
+    None,
+    Redraw,
+    Subscribe,
+    Quit,
+}
+
+pub struct App {
+    targets: Vec<Target>,
+    selected: usize,
+    focus: Pane,
+    generation: u64,
+    board: Option<Board>,
+    board_offset: usize,
+    updated: Option<DateTime<Local>>,
+    connection: Connection,
+    last_error: Option<String>,
+}
+
+impl App {
+    pub fn new(targets: Vec<Target>) -> App {
+        App {
+            targets,
+            selected: 0,
+            focus: Pane::Targets,
+            generation: 0,
+            board: None,
+            board_offset: 0,
+            updated: None,
+            connection: Connection::Connecting,
+            last_error: None,
+        }
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.targets[self.selected]
+    }
+
+    fn board_len(&self) -> usize {
+        match &self.board {
+            Some(Board::Arrivals(buses)) => buses.len(),
+            Some(Board::Buses(buses)) => buses.len(),
+            None => 0,
+        }
+    }
+
+    fn select(&mut self, index: usize) -> Action {
+        if index == self.selected {
+            return Action::None;
+        }
+        self.selected = index;
+        self.retarget()
+    }
+
+    fn retarget(&mut self) -> Action {
+        self.generation += 1;
+        self.board = None;
+        self.board_offset = 0;
+        self.updated = None;
+        self.connection = Connection::Connecting;
+        Action::Subscribe
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Action {
+        if key.kind == KeyEventKind::Release {
+            return Action::None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+            KeyCode::Char('r') => self.retarget(),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Pane::Targets => Pane::Board,
+                    Pane::Board => Pane::Targets,
+                };
+                Action::Redraw
+            }
+            KeyCode::Up | KeyCode::Char('k') => match self.focus {
+                Pane::Targets => self.select(self.selected.saturating_sub(1)),
+                Pane::Board => {
+                    self.board_offset = self.board_offset.saturating_sub(1);
+                    Action::Redraw
+                }
+            },
+            KeyCode::Down | KeyCode::Char('j') => match self.focus {
+                Pane::Targets => self.select((self.selected + 1).min(self.targets.len() - 1)),
+                Pane::Board => {
+                    self.board_offset =
+                        (self.board_offset + 1).min(self.board_len().saturating_sub(1));
+                    Action::Redraw
+                }
+            },
+            KeyCode::Char(c @ '1'..='9') => match c as usize - '1' as usize {
+                index if index < self.targets.len() => self.select(index),
+                _ => Action::None,
+            },
+            _ => Action::None,
+        }
+    }
+
+    pub fn update(&mut self, event: Event) -> Action {
+        match event {
+            Event::Key(key) => self.key(key),
+            Event::Resize => Action::Redraw,
+            Event::Update { generation, .. } if generation != self.generation => Action::None,
+            Event::Update { result, .. } => {
+                match result {
+                    Ok(board) => {
+                        self.board = Some(board);
+                        self.board_offset =
+                            self.board_offset.min(self.board_len().saturating_sub(1));
+                        self.updated = Some(Local::now());
+                        self.connection = Connection::Online;
+                        self.last_error = None;
+                    }
+                    Err(error) => {
+                        self.connection = Connection::Offline;
+                        self.last_error = Some(lang::error(&error));
+                    }
+                }
+                Action::Redraw
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let area = frame.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            frame.render_widget(Paragraph::new(lang::t("terminal too small")), area);
+            return;
+        }
+
+        let [body, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        if area.width < SIDEBAR_MIN_WIDTH {
+            self.draw_board(frame, body);
+        } else {
+            let [sidebar, main] =
+                Layout::horizontal([Constraint::Length(24), Constraint::Min(0)]).areas(body);
+            self.draw_targets(frame, sidebar);
+            self.draw_board(frame, main);
+        }
+        frame.render_widget(Paragraph::new(self.status()), status);
+    }
+
+    fn block(&self, title: String, pane: Pane) -> Block<'static> {
+        let style = match self.focus == pane {
+            true => Style::new().fg(Color::Yellow),
+            false => Style::new(),
+        };
+        Block::bordered().title(title).border_style(style)
+    }
+
+    fn draw_targets(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .targets
+            .iter()
+            .map(|t| ListItem::new(t.label()))
+            .collect();
+        let list = List::new(items)
+            .block(self.block(lang::t("Favorites").to_string(), Pane::Targets))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_board(&self, frame: &mut Frame, area: Rect) {
+        let block = self.block(self.target().label(), Pane::Board);
+        let (header, widths, rows): (Row, Vec<Constraint>, Vec<Row>) = match &self.board {
+            None => {
+                frame.render_widget(Paragraph::new(lang::t("loading...")).block(block), area);
+                return;
+            }
+            Some(Board::Arrivals(buses)) => (
+                Row::new(["Plate", "Arrives in"].map(lang::t)),
+                vec![Constraint::Length(12), Constraint::Min(0)],
+                buses
+                    .iter()
+                    .map(|bus| Row::new([bus.license_plate.clone(), humanize(bus.arrive_time)]))
+                    .collect(),
+            ),
+            Some(Board::Buses(buses)) => (
+                Row::new(["Plate", "Lat", "Long", "Age"].map(lang::t)),
+                vec![
+                    Constraint::Length(12),
+                    Constraint::Length(11),
+                    Constraint::Length(11),
+                    Constraint::Min(0),
+                ],
+                buses
+                    .iter()
+                    .map(|bus| {
+                        Row::new([
+                            bus.license_plate.clone(),
+                            format!("{:.5}", bus.coords.lat),
+                            format!("{:.5}", bus.coords.long),
+                            age(bus.last_update),
+                        ])
+                    })
+                    .collect(),
+            ),
+        };
+
+        let table = Table::new(rows, widths)
+            .header(header.style(Style::new().add_modifier(Modifier::BOLD)))
+            .block(block);
+        let mut state = TableState::default().with_offset(self.board_offset);
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn status(&self) -> TextLine<'static> {
+        let updated = match self.updated {
+            Some(time) => time.format("%H:%M:%S").to_string(),
+            None => lang::t("never").to_string(),
+        };
+        let (state, color) = match self.connection {
+            Connection::Connecting => ("connecting", Color::Yellow),
+            Connection::Online => ("online", Color::Green),
+            Connection::Offline => ("offline", Color::Red),
+        };
+        let updated = lang::tf("updated {time}", &[("time", &updated)]);
+        let mut text = format!(" {} | {updated}", lang::t(state));
+        if let Some(error) = &self.last_error {
+            text.push_str(&format!(" | {error}"));
+        }
+        text.push_str(" | ");
+        text.push_str(lang::t("q quit, tab pane, r refresh"));
+        TextLine::styled(text, Style::new().fg(color))
+    }
+}
+
+fn age(time: Option<DateTime<Utc>>) -> String {
+    match time {
+        Some(time) => humanize((Utc::now() - time).to_std().unwrap_or_default()),
+        None => "-".to_string(),
+    }
+}
+
+struct Subscription(JoinHandle<()>);
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn subscribe(
+    app: &App,
+    client: &Client,
+    interval: Duration,
+    events: mpsc::UnboundedSender<Event>,
+) -> Subscription {
+    let generation = app.generation;
+    let mut updates = Box::pin(app.target().updates(client, interval));
+    Subscription(tokio::spawn(async move {
+        while let Some(result) = updates.next().await {
+            if events.send(Event::Update { generation, result }).is_err() {
+                break;
+            }
+        }
+    }))
+}
+
+fn spawn_input(events: mpsc::UnboundedSender<Event>) {
+    std::thread::spawn(move || {
+        loop {
+            let event = match event::read() {
+                Ok(TermEvent::Key(key)) => Event::Key(key),
+                Ok(TermEvent::Resize(..)) => Event::Resize,
+                Ok(_) => continue,
+                Err(_) => break,
+            };
+            if events.send(event).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: &Client,
+    mut app: App,
+    interval: Duration,
+) -> std::io::Result<()> {
+    let (sender, mut events) = mpsc::unbounded_channel();
+    spawn_input(sender.clone());
+    let mut _subscription = subscribe(&app, client, interval, sender.clone());
+
+    terminal.draw(|frame| app.draw(frame))?;
+    while let Some(event) = events.recv().await {
+        match app.update(event) {
+            Action::None => continue,
+            Action::Redraw => {}
+            Action::Subscribe => _subscription = subscribe(&app, client, interval, sender.clone()),
+            Action::Quit => break,
+        }
+        terminal.draw(|frame| app.draw(frame))?;
+    }
+
+    Ok(())
+}
+
+pub async fn run(client: &Client, config: &FileConfig, interval: Duration) -> Result<(), CliError> {
+    let targets = targets(config);
+    if targets.is_empty() {
+        return Err(CliError::NoFavorites);
+    }
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, client, App::new(targets), interval).await;
+    ratatui::restore();
+    result.map_err(CliError::Terminal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn app() -> App {
+        App::new(vec![
+            Target::Station {
+                name: "home".to_string(),
+                id: 10,
+            },
+            Target::Line {
+                name: "work".to_string(),
+                id: "7A".to_string(),
+            },
+        ])
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn board(minutes: &[u64]) -> Board {
+        Board::Arrivals(
+            minutes
+                .iter()
+                .map(|minutes| StationBus {
+                    license_plate: format!("58 AC {minutes}"),
+                    arrive_time: Duration::from_secs(minutes * 60),
+                })
+                .collect(),
+        )
+    }
+
+    fn update(app: &App, result: Result<Board, Error>) -> Event {
+        Event::Update {
+            generation: app.generation,
+            result,
+        }
+    }
+
+    #[test]
+    fn updates_fill_the_board_and_go_online() {
+        let mut app = app();
+        assert_eq!(app.connection, Connection::Connecting);
+
+        assert_eq!(
+            app.update(update(&app, Ok(board(&[4, 11])))),
+            Action::Redraw
+        );
+        assert_eq!(app.connection, Connection::Online);
+        assert_eq!(app.board_len(), 2);
+        assert!(app.updated.is_some());
+    }
+
+    #[test]
+    fn errors_keep_the_last_board() {
+        let mut app = app();
+        app.update(update(&app, Ok(board(&[4, 11]))));
+        let updated = app.updated;
+
+        assert_eq!(
+            app.update(update(&app, Err(Error::NoToken))),
+            Action::Redraw
+        );
+        assert_eq!(app.connection, Connection::Offline);
+        assert_eq!(app.board_len(), 2);
+        assert_eq!(app.updated, updated);
+        assert!(app.last_error.is_some());
+
+        app.update(update(&app, Ok(board(&[3]))));
+        assert_eq!(app.connection, Connection::Online);
+        assert_eq!(app.last_error, None);
+        assert_eq!(app.board_len(), 1);
+    }
+
+    #[test]
+    fn updates_from_an_old_subscription_are_ignored() {
+        let mut app = app();
+        let stale = update(&app, Ok(board(&[4])));
+
+        assert_eq!(app.update(key(KeyCode::Down)), Action::Subscribe);
+        assert_eq!(app.update(stale), Action::None);
+        assert_eq!(app.board_len(), 0);
+        assert_eq!(app.connection, Connection::Connecting);
+    }
+
+    #[test]
+    fn selecting_a_target_resubscribes_once() {
+        let mut app = app();
+        app.update(update(&app, Ok(board(&[4]))));
+
+        assert_eq!(app.update(key(KeyCode::Char('2'))), Action::Subscribe);
+        assert!(matches!(app.target(), Target::Line { id, .. } if id == "7A"));
+        assert_eq!(app.board_len(), 0);
+        assert_eq!(app.updated, None);
+
+        assert_eq!(app.update(key(KeyCode::Down)), Action::None);
+        assert_eq!(app.update(key(KeyCode::Char('9'))), Action::None);
+        assert_eq!(app.update(key(KeyCode::Char('k'))), Action::Subscribe);
+        assert_eq!(app.selected, 0);
+        assert_eq!(app.update(key(KeyCode::Char('r'))), Action::Subscribe);
+    }
+
+    #[test]
+    fn board_scrolling_stays_in_range() {
+        let mut app = app();
+        app.update(update(&app, Ok(board(&[1, 2, 3]))));
+
+        assert_eq!(app.update(key(KeyCode::Tab)), Action::Redraw);
+        assert_eq!(app.focus, Pane::Board);
+        for _ in 0..5 {
+            app.update(key(KeyCode::Down));
+        }
+        assert_eq!(app.board_offset, 2);
+
+        app.update(update(&app, Ok(board(&[1]))));
+        assert_eq!(app.board_offset, 0);
+        assert_eq!(app.update(key(KeyCode::Up)), Action::Redraw);
+    }
+
+    #[test]
+    fn quits_and_ignores_releases() {
+        let mut app = app();
+        let mut release = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
+        release.kind = KeyEventKind::Release;
+
+        assert_eq!(app.update(Event::Key(release)), Action::None);
+        assert_eq!(app.update(Event::Resize), Action::Redraw);
+        assert_eq!(app.update(key(KeyCode::Char('q'))), Action::Quit);
+        assert_eq!(app.update(key(KeyCode::Esc)), Action::Quit);
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Local, Utc};
+use clap::Args;
+use serde::Serialize;
+use serde_json::json;
+use sivasbus::Error;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+use super::lang;
+use super::output::{self, Format, Render};
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Keep refreshing the output
+    #[arg(long)]
+    pub watch: bool,
+    /// Time between refreshes, e.g. 20s or 1m
+    #[arg(long, default_value = "20s", value_parser = parse_interval, requires = "watch")]
+    pub interval: Duration,
+}
+
+/// What a watch is refreshing, included in each NDJSON record.
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Station(i32),
+    Line(String),
+}
+
+pub fn parse_interval(text: &str) -> Result<Duration, humantime::DurationError> {
+    match text.parse() {
+        Ok(secs) => Ok(Duration::from_secs(secs)),
+        Err(_) => humantime::parse_duration(text),
+    }
+}
+
+fn status(updated: Option<DateTime<Local>>, error: Option<&Error>) -> String {
+    let updated = match updated {
+        Some(time) => time.format("%H:%M:%S").to_string(),
+        None => lang::t("never").to_string(),
+    };
+
+    match error {
+        Some(error) => lang::tf(
+            "STALE, last updated {time} ({error})",
+            &[("time", &updated), ("error", &lang::error(error))],
+        ),
+        None => lang::tf("Last updated {time}", &[("time", &updated)]),
+    }
+}
+
+/// Writes one line per refresh, with errors as records too so the output
+/// stays parseable.
+fn print_ndjson<T: Render>(target: &Target, result: &Result<Vec<T>, Error>) {
+    let timestamp = Utc::now();
+    let record = match result {
+        Ok(items) => json!({"timestamp": timestamp, "target": target, "data": items}),
+        Err(error) => json!({
+            "timestamp": timestamp,
+            "target": target,
+            "error": {"code": error.code(), "message": error.to_string()},
+        }),
+    };
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{record}").ok();
+    stdout.flush().ok();
+}
+
+pub async fn run<T: Render>(
+    interval: Duration,
+    format: Format,
+    target: Target,
+    mut fetch: impl AsyncFnMut() -> Result<Vec<T>, Error>,
+) -> Result<(), Error> {
+    let tty = std::io::stdout().is_terminal();
+    let mut last: Option<Vec<T>> = None;
+    let mut updated = None;
+
+    loop {
+        let result = tokio::select! {
+            result = fetch() => result,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+
+        if format == Format::Ndjson {
+            print_ndjson(&target, &result);
+        } else {
+            let error = match result {
+                Ok(items) => {
+                    last = Some(items);
+                    updated = Some(Local::now());
+                    None
+                }
+                Err(error) => Some(error),
+            };
+
+            if tty {
+                print!("\x1b[2J\x1b[H");
+            }
+            if let Some(items) = &last {
+                output::print(items, format);
+            }
+            let status = status(updated, error.as_ref());
+            if format == Format::Table {
+                println!("{status}");
+            } else {
+                eprintln!("{status}");
+            }
+            std::io::stdout().flush().ok();
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
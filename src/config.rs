@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::env::VarError;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::{Client, ClientBuilder, SITE_ROOT};
+
+/// Why a [`Config`] couldn't be read or a client's settings can't work.
+///
+/// [`Config::from_env`] reports this rather than a bare [`VarError`], which
+/// can't say which variable failed or carry a value that didn't parse; the
+/// `VarError` is still there as the source of [`ConfigError::Var`].
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("can't read environment variable {name}")]
+    Var {
+        name: &'static str,
+        #[source]
+        source: VarError,
+    },
+    #[error("invalid value {value:?} for {name}")]
+    Invalid { name: &'static str, value: String },
+    #[error("{name} {reason}")]
+    Unusable {
+        name: &'static str,
+        reason: &'static str,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Config {
+    pub base_url: String,
+    pub request_delay_ms: u64,
+    pub timeout_secs: u64,
+    pub log_level: String,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            base_url: SITE_ROOT.to_string(),
+            request_delay_ms: 200,
+            timeout_secs: 30,
+            log_level: "info".to_string(),
+        }
+    }
+}
+
+fn var(name: &'static str) -> Result<Option<String>, ConfigError> {
+    match std::env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(source) => Err(ConfigError::Var { name, source }),
+    }
+}
+
+fn number_var(name: &'static str) -> Result<Option<u64>, ConfigError> {
+    var(name)?
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| ConfigError::Invalid { name, value })
+        })
+        .transpose()
+}
+
+impl Config {
+    /// Reads the `SIVASBUS_*` variables, using the defaults for unset ones.
+    ///
+    /// `SIVASBUS_LOG_LEVEL` is a `tracing` filter; the CLI logs to stderr
+    /// with it.
+    pub fn from_env() -> Result<Config, ConfigError> {
+        let default = Config::default();
+
+        Ok(Config {
+            base_url: var("SIVASBUS_BASE_URL")?.unwrap_or(default.base_url),
+            request_delay_ms: number_var("SIVASBUS_REQUEST_DELAY_MS")?
+                .unwrap_or(default.request_delay_ms),
+            timeout_secs: number_var("SIVASBUS_TIMEOUT_SECS")?.unwrap_or(default.timeout_secs),
+            log_level: var("SIVASBUS_LOG_LEVEL")?.unwrap_or(default.log_level),
+        })
+    }
+}
+
+impl ClientBuilder {
+    pub fn from_config(config: &Config) -> ClientBuilder {
+        let builder = ClientBuilder::new()
+            .base_url(&config.base_url)
+            .request_delay(Duration::from_millis(config.request_delay_ms));
+
+        match config.timeout_secs {
+            0 => builder,
+            secs => builder.timeout(Duration::from_secs(secs)),
+        }
+    }
+}
+
+impl Client {
+    /// Checks the settings the client was built with, to catch mistakes
+    /// before a long job rather than partway through it.
+    pub fn validate_config(&self) -> Result<(), ConfigError> {
+        let unusable = |name, reason| Err(ConfigError::Unusable { name, reason });
+
+        let url = reqwest::Url::parse(&self.base_url).ok();
+        if !url.is_some_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host()) {
+            return Err(ConfigError::Invalid {
+                name: "base_url",
+                value: self.base_url.clone(),
+            });
+        }
+        if self.timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return unusable("timeout", "is zero, so every request would time out");
+        }
+        if self
+            .rate_limiter
+            .as_ref()
+            .is_some_and(|limiter| limiter.refill_per_second() <= 0.0)
+        {
+            return unusable(
+                "rate_limiter",
+                "never refills, so requests stop once it's empty",
+            );
+        }
+        if self
+            .max_parse_failure_ratio
+            .is_some_and(|ratio| !(0.0..=1.0).contains(&ratio))
+        {
+            return unusable("max_parse_failure_ratio", "isn't between 0 and 1");
+        }
+        if self.circuit_breaker_threshold == Some(0) {
+            return unusable("circuit_breaker_threshold", "is zero");
+        }
+        if self.token_ttl.is_zero() {
+            return unusable("token_ttl", "is zero");
+        }
+        if self.token_refresh_lead >= self.token_ttl {
+            return unusable(
+                "token_refresh_lead",
+                "isn't shorter than token_ttl, so tokens would be refreshed nonstop",
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! Station arrivals logged to CSV as a time series, one row per bus per
+//! poll.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::io::{self, Write};
+
+use crate::{StationBus, StationId};
+
+const HEADER: [&str; 4] = ["timestamp", "station_id", "plate", "eta_secs"];
+
+/// Appends each poll's arrivals to a CSV with the columns
+/// `timestamp,station_id,plate,eta_secs`.
+pub struct StationBusCsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+impl<W: Write> StationBusCsvWriter<W> {
+    /// Writes the header to `writer` right away. Use
+    /// [`StationBusCsvWriter::without_header`] to append to an existing log.
+    pub fn new(writer: W) -> io::Result<StationBusCsvWriter<W>> {
+        let mut log = StationBusCsvWriter::without_header(writer);
+        log.writer.write_record(HEADER)?;
+        log.writer.flush()?;
+        Ok(log)
+    }
+
+    /// For appending to a log that already has its header.
+    pub fn without_header(writer: W) -> StationBusCsvWriter<W> {
+        StationBusCsvWriter {
+            writer: csv::Writer::from_writer(writer),
+        }
+    }
+
+    /// Writes a row for each of `buses`, polled at `station_id` at `ts`, and
+    /// flushes them.
+    pub fn write_batch(
+        &mut self,
+        station_id: StationId,
+        buses: &[StationBus],
+        ts: DateTime<Utc>,
+    ) -> io::Result<()> {
+        let timestamp = ts.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let station_id = station_id.to_string();
+        for bus in buses {
+            self.writer.write_record([
+                timestamp.as_str(),
+                station_id.as_str(),
+                bus.license_plate.as_str(),
+                bus.arrive_time.as_secs().to_string().as_str(),
+            ])?;
+        }
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(|error| error.into_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    /// A writer whose contents can be read while the log still owns it.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn bus(plate: &str, secs: u64) -> StationBus {
+        StationBus {
+            license_plate: plate.to_string(),
+            arrive_time: Duration::from_secs(secs),
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_760_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn header_is_written_once() {
+        let out = Shared::default();
+        let mut log = StationBusCsvWriter::new(out.clone()).unwrap();
+        assert_eq!(out.text(), "timestamp,station_id,plate,eta_secs\n");
+
+        log.write_batch(
+            StationId(10),
+            &[bus("58 AC 123", 240), bus("58 AB 999", 660)],
+            at(0),
+        )
+        .unwrap();
+        log.write_batch(StationId(10), &[], at(30)).unwrap();
+        log.write_batch(StationId(12), &[bus("58 KP 007", 90)], at(60))
+            .unwrap();
+        assert_eq!(
+            out.text(),
+            "timestamp,station_id,plate,eta_secs\n\
+             2025-10-09T08:53:20Z,10,58 AC 123,240\n\
+             2025-10-09T08:53:20Z,10,58 AB 999,660\n\
+             2025-10-09T08:54:20Z,12,58 KP 007,90\n"
+        );
+    }
+
+    #[test]
+    fn batches_are_flushed_as_they_are_written() {
+        let out = Shared::default();
+        let mut log = StationBusCsvWriter::without_header(out.clone());
+        assert_eq!(out.text(), "");
+
+        log.write_batch(StationId(10), &[bus("58 AC 123", 240)], at(0))
+            .unwrap();
+        assert_eq!(out.text(), "2025-10-09T08:53:20Z,10,58 AC 123,240\n");
+    }
+
+    #[test]
+    fn plates_are_quoted_when_needed() {
+        let mut log = StationBusCsvWriter::without_header(Vec::new());
+        log.write_batch(StationId(10), &[bus("58 \"AC\", 123", 0)], at(0))
+            .unwrap();
+        let out = String::from_utf8(log.into_inner().unwrap()).unwrap();
+        assert_eq!(out, "2025-10-09T08:53:20Z,10,\"58 \"\"AC\"\", 123\",0\n");
+    }
+}
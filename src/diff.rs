@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{Coords, Line, LineBus, NetworkSnapshot, Station, normalize_plate};
+
+/// Stations and buses that moved less than this are considered unchanged,
+/// so GPS jitter in the source data doesn't show up as a move.
+pub const MOVE_THRESHOLD_M: f64 = 10.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Renamed<Id> {
+    pub id: Id,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Moved {
+    pub id: i32,
+    pub human_name: String,
+    pub distance_m: f64,
+}
+
+/// Changes between two network snapshots. Buses are not compared.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SnapshotDiff {
+    pub added_stations: Vec<Station>,
+    pub removed_stations: Vec<Station>,
+    pub renamed_stations: Vec<Renamed<i32>>,
+    pub moved_stations: Vec<Moved>,
+    pub added_lines: Vec<Line>,
+    pub removed_lines: Vec<Line>,
+    pub renamed_lines: Vec<Renamed<String>>,
+    /// Lines whose station order changed, only for lines with known station
+    /// order in both snapshots.
+    pub rerouted_lines: Vec<String>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == SnapshotDiff::default()
+    }
+}
+
+pub fn diff_snapshots(old: &NetworkSnapshot, new: &NetworkSnapshot) -> SnapshotDiff {
+    let mut diff = SnapshotDiff::default();
+
+    let old_stations: HashMap<i32, &Station> = old.stations.iter().map(|s| (s.id, s)).collect();
+    let new_stations: HashMap<i32, &Station> = new.stations.iter().map(|s| (s.id, s)).collect();
+    for station in &new.stations {
+        let Some(previous) = old_stations.get(&station.id) else {
+            diff.added_stations.push(station.clone());
+            continue;
+        };
+        if previous.human_name != station.human_name {
+            diff.renamed_stations.push(Renamed {
+                id: station.id,
+                old_name: previous.human_name.clone(),
+                new_name: station.human_name.clone(),
+            });
+        }
+        let distance_m = previous.coords.distance_m(&station.coords);
+        if distance_m >= MOVE_THRESHOLD_M {
+            diff.moved_stations.push(Moved {
+                id: station.id,
+                human_name: station.human_name.clone(),
+                distance_m,
+            });
+        }
+    }
+    diff.removed_stations = old
+        .stations
+        .iter()
+        .filter(|station| !new_stations.contains_key(&station.id))
+        .cloned()
+        .collect();
+
+    let old_lines: HashMap<&str, &Line> = old.lines.iter().map(|l| (l.id.as_str(), l)).collect();
+    let new_lines: HashMap<&str, &Line> = new.lines.iter().map(|l| (l.id.as_str(), l)).collect();
+    for line in &new.lines {
+        let Some(previous) = old_lines.get(line.id.as_str()) else {
+            diff.added_lines.push(line.clone());
+            continue;
+        };
+        if previous.human_name != line.human_name {
+            diff.renamed_lines.push(Renamed {
+                id: line.id.clone(),
+                old_name: previous.human_name.clone(),
+                new_name: line.human_name.clone(),
+            });
+        }
+        if let (Some(before), Some(after)) = (
+            old.line_stations.get(&line.id),
+            new.line_stations.get(&line.id),
+        ) && before != after
+        {
+            diff.rerouted_lines.push(line.id.clone());
+        }
+    }
+    diff.removed_lines = old
+        .lines
+        .iter()
+        .filter(|line| !new_lines.contains_key(line.id.as_str()))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BusMove {
+    pub plate: String,
+    pub from: Coords,
+    pub to: Coords,
+    pub distance_m: f64,
+}
+
+/// Changes between two polls of bus positions, matching buses by
+/// [`normalize_plate`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PositionDiff {
+    pub appeared: Vec<LineBus>,
+    pub disappeared: Vec<LineBus>,
+    pub moved: Vec<BusMove>,
+}
+
+impl PositionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.appeared.is_empty() && self.disappeared.is_empty() && self.moved.is_empty()
+    }
+}
+
+/// Like [`diff_positions_with`], with moves under [`MOVE_THRESHOLD_M`]
+/// ignored.
+pub fn diff_positions(old: &[LineBus], new: &[LineBus]) -> PositionDiff {
+    diff_positions_with(old, new, MOVE_THRESHOLD_M)
+}
+
+/// The buses that appeared, disappeared or moved at least `min_move_m`
+/// between `old` and `new`, in the order of the poll they're in.
+pub fn diff_positions_with(old: &[LineBus], new: &[LineBus], min_move_m: f64) -> PositionDiff {
+    let mut diff = PositionDiff::default();
+
+    let old_buses: HashMap<String, &LineBus> = old
+        .iter()
+        .map(|bus| (normalize_plate(&bus.license_plate), bus))
+        .collect();
+    let new_buses: HashMap<String, &LineBus> = new
+        .iter()
+        .map(|bus| (normalize_plate(&bus.license_plate), bus))
+        .collect();
+    for bus in new {
+        let Some(previous) = old_buses.get(&normalize_plate(&bus.license_plate)) else {
+            diff.appeared.push(bus.clone());
+            continue;
+        };
+        let distance_m = previous.coords.distance_m(&bus.coords);
+        if distance_m >= min_move_m {
+            diff.moved.push(BusMove {
+                plate: bus.license_plate.clone(),
+                from: previous.coords.clone(),
+                to: bus.coords.clone(),
+                distance_m,
+            });
+        }
+    }
+    diff.disappeared = old
+        .iter()
+        .filter(|bus| !new_buses.contains_key(&normalize_plate(&bus.license_plate)))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A point `north_m` metres north of a fixed one in Sivas.
+    fn coords(north_m: f64) -> Coords {
+        Coords {
+            lat: 39.75 + north_m / 111_195.0,
+            long: 37.02,
+        }
+    }
+
+    fn bus(plate: &str, north_m: f64) -> LineBus {
+        LineBus {
+            license_plate: plate.to_string(),
+            coords: coords(north_m),
+            last_update: None,
+        }
+    }
+
+    #[test]
+    fn buses_appear_disappear_and_move() {
+        let old = [
+            bus("58 AC 123", 0.0),
+            bus("58 AB 999", 0.0),
+            bus("58 KP 007", 0.0),
+        ];
+        let new = [
+            bus("58AC123", 250.0),
+            bus("58 KP 007", 0.0),
+            bus("58 TT 100", 0.0),
+        ];
+        let diff = diff_positions(&old, &new);
+
+        assert_eq!(diff.appeared, [bus("58 TT 100", 0.0)]);
+        assert_eq!(diff.disappeared, [bus("58 AB 999", 0.0)]);
+        assert_eq!(diff.moved.len(), 1);
+        let moved = &diff.moved[0];
+        assert_eq!(moved.plate, "58AC123");
+        assert_eq!((&moved.from, &moved.to), (&coords(0.0), &coords(250.0)));
+        assert!((moved.distance_m - 250.0).abs() < 0.5, "{moved:?}");
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn unchanged_polls_are_empty() {
+        let buses = [bus("58 AC 123", 0.0), bus("58 AB 999", 40.0)];
+        assert!(diff_positions(&buses, &buses).is_empty());
+        assert!(diff_positions(&[], &[]).is_empty());
+        assert!(!diff_positions(&[], &buses).is_empty());
+    }
+
+    #[test]
+    fn jitter_under_the_minimum_move_is_ignored() {
+        let old = [bus("58 AC 123", 0.0)];
+        let new = [bus("58 AC 123", 25.0)];
+        let distance_m = old[0].coords.distance_m(&new[0].coords);
+
+        assert_eq!(diff_positions_with(&old, &new, distance_m).moved.len(), 1);
+        assert!(diff_positions_with(&old, &new, distance_m + 0.01).is_empty());
+
+        let jitter = [bus("58 AC 123", 9.0)];
+        assert!(diff_positions(&old, &jitter).is_empty());
+        let drive = [bus("58 AC 123", 11.0)];
+        assert_eq!(diff_positions(&old, &drive).moved.len(), 1);
+    }
+}
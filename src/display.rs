@@ -0,0 +1,91 @@
+//! Plain text renderings for kiosks and other displays.
+
+use std::fmt;
+
+use crate::{Station, StationBus};
+
+/// A station's arrivals as a text board, soonest first:
+///
+/// ```
+/// use std::time::Duration;
+/// use sivasbus::display::StationBoard;
+/// use sivasbus::{Coords, Station, StationBus};
+///
+/// let station = Station {
+///     id: 42,
+///     human_name: "Merkez Garı".to_string(),
+///     coords: Coords { lat: 39.7505, long: 37.0150 },
+/// };
+/// let arrivals = [
+///     StationBus {
+///         license_plate: "60SV0099".to_string(),
+///         arrive_time: Duration::from_secs(7 * 60 + 2),
+///     },
+///     StationBus {
+///         license_plate: "60SV0001".to_string(),
+///         arrive_time: Duration::from_secs(2 * 60 + 14),
+///     },
+/// ];
+///
+/// assert_eq!(
+///     StationBoard::new(&station, &arrivals).to_string(),
+///     "\
+/// === Merkez Garı (Stop 42) ===
+///   1. 60SV0001  arriving in 2m 14s
+///   2. 60SV0099  arriving in 7m 02s
+///   (No more buses tracked)
+/// ",
+/// );
+/// ```
+///
+/// The last line is always there, on its own when there are no arrivals:
+///
+/// ```
+/// # use sivasbus::display::StationBoard;
+/// # use sivasbus::{Coords, Station};
+/// # let station = Station {
+/// #     id: 42,
+/// #     human_name: "Merkez Garı".to_string(),
+/// #     coords: Coords { lat: 39.7505, long: 37.0150 },
+/// # };
+/// assert_eq!(
+///     StationBoard::new(&station, &[]).to_string(),
+///     "=== Merkez Garı (Stop 42) ===\n  (No more buses tracked)\n",
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct StationBoard<'a> {
+    pub station: &'a Station,
+    pub arrivals: &'a [StationBus],
+}
+
+impl<'a> StationBoard<'a> {
+    pub fn new(station: &'a Station, arrivals: &'a [StationBus]) -> StationBoard<'a> {
+        StationBoard { station, arrivals }
+    }
+}
+
+impl fmt::Display for StationBoard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "=== {} (Stop {}) ===",
+            self.station.human_name, self.station.id
+        )?;
+
+        let mut arrivals: Vec<&StationBus> = self.arrivals.iter().collect();
+        arrivals.sort();
+        for (i, bus) in arrivals.into_iter().enumerate() {
+            let secs = bus.arrive_time.as_secs();
+            writeln!(
+                f,
+                "  {}. {}  arriving in {}m {:02}s",
+                i + 1,
+                bus.license_plate,
+                secs / 60,
+                secs % 60
+            )?;
+        }
+        writeln!(f, "  (No more buses tracked)")
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, ErrorKind};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiError {
+    pub code: String,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl From<&Error> for ApiError {
+    fn from(error: &Error) -> ApiError {
+        ApiError {
+            code: error.code().to_string(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiEnvelope<T> {
+    pub data: Option<T>,
+    pub error: Option<ApiError>,
+}
+
+impl<T> ApiEnvelope<T> {
+    pub fn ok(data: T) -> ApiEnvelope<T> {
+        ApiEnvelope {
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    pub fn err(error: Error) -> ApiEnvelope<T> {
+        ApiEnvelope {
+            data: None,
+            error: Some((&error).into()),
+        }
+    }
+}
+
+impl<T> From<Result<T, Error>> for ApiEnvelope<T> {
+    fn from(result: Result<T, Error>) -> ApiEnvelope<T> {
+        match result {
+            Ok(data) => ApiEnvelope::ok(data),
+            Err(error) => ApiEnvelope::err(error),
+        }
+    }
+}
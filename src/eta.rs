@@ -0,0 +1,185 @@
+//! Smoothing of the arrival times on station boards.
+//!
+//! The site's estimates jump around between polls, 4 minutes then 7 then 3,
+//! as buses report their positions. An [`EtaSmoother`] keeps an estimate
+//! per bus that counts down between polls and moves towards each new value
+//! by a fraction, and never goes up unless the site's value jumps up by
+//! enough to mean the bus has actually been held up or rerouted.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::{StationBus, normalize_plate};
+
+/// How long a bus can be missing from the board before its estimate is
+/// forgotten and it starts over when it comes back.
+const FORGET_AFTER: TimeDelta = TimeDelta::minutes(5);
+
+struct Estimate {
+    secs: f64,
+    at: DateTime<Utc>,
+}
+
+/// Smooths the arrival times of successive polls of a station.
+pub struct EtaSmoother {
+    alpha: f64,
+    reroute_jump: Duration,
+    estimates: HashMap<String, Estimate>,
+}
+
+impl Default for EtaSmoother {
+    /// Moves a third of the way towards each new value and resets on jumps
+    /// of over 3 minutes.
+    fn default() -> EtaSmoother {
+        EtaSmoother::new(1.0 / 3.0, Duration::from_secs(3 * 60))
+    }
+}
+
+impl EtaSmoother {
+    /// Moves each estimate by `alpha`, between 0 and 1, of the way towards
+    /// the site's value, with 1 meaning no smoothing. An estimate is only
+    /// allowed to go up, and then straight to the site's value, when that
+    /// is over `reroute_jump` more than the estimate counted down.
+    pub fn new(alpha: f64, reroute_jump: Duration) -> EtaSmoother {
+        EtaSmoother {
+            alpha: alpha.clamp(f64::EPSILON, 1.0),
+            reroute_jump,
+            estimates: HashMap::new(),
+        }
+    }
+
+    /// Takes a station's board polled at `at` and returns it with smoothed
+    /// arrival times, sorted by them. Polls should be fed in time order.
+    pub fn update(&mut self, at: DateTime<Utc>, buses: &[StationBus]) -> Vec<StationBus> {
+        self.estimates
+            .retain(|_, estimate| at - estimate.at <= FORGET_AFTER);
+
+        let mut smoothed: Vec<StationBus> = buses
+            .iter()
+            .map(|bus| {
+                let raw = bus.arrive_time.as_secs_f64();
+                let secs = match self.estimates.get(&normalize_plate(&bus.license_plate)) {
+                    Some(previous) => {
+                        let elapsed = (at - previous.at).as_seconds_f64().max(0.0);
+                        let predicted = (previous.secs - elapsed).max(0.0);
+                        if raw > predicted + self.reroute_jump.as_secs_f64() {
+                            raw
+                        } else {
+                            let blended = predicted + self.alpha * (raw - predicted);
+                            blended.min(previous.secs)
+                        }
+                    }
+                    None => raw,
+                };
+                self.estimates
+                    .insert(normalize_plate(&bus.license_plate), Estimate { secs, at });
+                StationBus {
+                    license_plate: bus.license_plate.clone(),
+                    arrive_time: Duration::from_secs_f64(secs.round()),
+                }
+            })
+            .collect();
+        smoothed.sort();
+        smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Polls 30 seconds apart, starting at 12:00 UTC.
+    fn at(poll: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_760_097_600 + 30 * poll, 0).unwrap()
+    }
+
+    fn board(buses: &[(&str, f64)]) -> Vec<StationBus> {
+        buses
+            .iter()
+            .map(|&(plate, secs)| StationBus {
+                license_plate: plate.to_string(),
+                arrive_time: Duration::from_secs_f64(secs),
+            })
+            .collect()
+    }
+
+    fn secs(board: &[StationBus]) -> Vec<f64> {
+        board
+            .iter()
+            .map(|bus| bus.arrive_time.as_secs_f64())
+            .collect()
+    }
+
+    /// Noise added to a bus really due in 12 minutes, counting down.
+    const NOISE: [f64; 20] = [
+        0.0, 120.0, -60.0, 90.0, -120.0, 60.0, 120.0, -90.0, 30.0, -60.0, 120.0, -120.0, 90.0, 0.0,
+        -90.0, 60.0, 120.0, -30.0, -120.0, 90.0,
+    ];
+
+    #[test]
+    fn noisy_countdown_is_steady() {
+        let mut smoother = EtaSmoother::default();
+        let mut last = f64::INFINITY;
+        let (mut raw_error, mut smoothed_error) = (0.0, 0.0);
+        for (poll, noise) in NOISE.iter().enumerate() {
+            let truth = 720.0 - 30.0 * poll as f64;
+            let raw = truth + noise;
+            let smoothed =
+                secs(&smoother.update(at(poll as i64), &board(&[("58 AC 123", raw)])))[0];
+
+            assert!(
+                smoothed <= last,
+                "poll {poll}: went up from {last} to {smoothed}"
+            );
+            assert!(
+                (smoothed - truth).abs() <= 120.0,
+                "poll {poll}: {smoothed} for {truth}"
+            );
+            raw_error += (raw - truth).abs();
+            smoothed_error += (smoothed - truth).abs();
+            last = smoothed;
+        }
+        assert!(
+            smoothed_error < raw_error / 2.0,
+            "{smoothed_error} vs {raw_error}"
+        );
+    }
+
+    #[test]
+    fn large_jumps_reset_the_estimate() {
+        let mut smoother = EtaSmoother::default();
+        smoother.update(at(0), &board(&[("58 AC 123", 300.0)]));
+        // Up by 2 minutes on a prediction of 270: held down as noise.
+        let held = smoother.update(at(1), &board(&[("58 AC 123", 390.0)]));
+        assert!(secs(&held)[0] <= 300.0, "{held:?}");
+        // Up by 10 minutes: rerouted, taken as is.
+        let rerouted = smoother.update(at(2), &board(&[("58 AC 123", 900.0)]));
+        assert_eq!(secs(&rerouted), [900.0]);
+    }
+
+    #[test]
+    fn plates_are_remembered_while_briefly_gone() {
+        let mut smoother = EtaSmoother::new(0.5, Duration::from_secs(180));
+        smoother.update(at(0), &board(&[("58 AC 123", 600.0)]));
+        smoother.update(at(1), &board(&[("58 AB 999", 60.0)]));
+        // Back after a minute as "58AC123", predicted at 540: halfway to 480.
+        let back = smoother.update(at(2), &board(&[("58AC123", 480.0)]));
+        assert_eq!(secs(&back), [510.0]);
+
+        // Gone for over 5 minutes, the site's value is taken as is.
+        let later = smoother.update(at(14), &board(&[("58 AC 123", 420.0)]));
+        assert_eq!(secs(&later), [420.0]);
+    }
+
+    #[test]
+    fn boards_are_sorted_by_smoothed_time() {
+        let mut smoother = EtaSmoother::new(0.5, Duration::from_secs(180));
+        smoother.update(at(0), &board(&[("58 AC 123", 200.0), ("58 AB 999", 300.0)]));
+        let board = smoother.update(at(1), &board(&[("58 AC 123", 320.0), ("58 AB 999", 60.0)]));
+        let plates: Vec<_> = board.iter().map(|bus| bus.license_plate.as_str()).collect();
+        assert_eq!(plates, ["58 AB 999", "58 AC 123"]);
+        // 58 AC 123 is held at its last estimate rather than going up.
+        assert_eq!(secs(&board), [165.0, 200.0]);
+    }
+}
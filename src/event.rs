@@ -0,0 +1,126 @@
+//! Arrival events pushed to subscribers of stations, polled in the
+//! background.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::{Client, StationBus, StationId, normalize_plate};
+
+/// Events each subscriber can fall behind by before newer ones are dropped.
+const SUBSCRIBER_CAPACITY: usize = 64;
+
+/// A bus that showed up on a station's board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrivalEvent {
+    pub station_id: StationId,
+    pub bus: StationBus,
+}
+
+type Subscribers = Mutex<HashMap<StationId, Vec<mpsc::Sender<ArrivalEvent>>>>;
+
+/// Sends an [`ArrivalEvent`] to a station's subscribers whenever a bus with
+/// a plate that wasn't on the board in the previous poll appears.
+///
+/// The first poll of a station only records the buses already on its
+/// board. Polls that fail are skipped, so buses aren't announced again
+/// after an error.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Subscribers>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus::default()
+    }
+
+    /// Receives the arrivals at `station_id` from the next poll on. A
+    /// subscriber that falls behind by more than 64 events misses the
+    /// newer ones until it catches up.
+    pub fn subscribe(&self, station_id: StationId) -> mpsc::Receiver<ArrivalEvent> {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_CAPACITY);
+        self.subscribers
+            .lock()
+            .unwrap()
+            .entry(station_id)
+            .or_default()
+            .push(sender);
+        receiver
+    }
+
+    /// Polls the subscribed stations every `poll_interval` in the
+    /// background. Stations whose subscribers have all gone are dropped, and
+    /// the task ends once the bus and all its clones have been dropped.
+    pub fn run(&self, client: Arc<Client>, poll_interval: Duration) -> JoinHandle<()> {
+        let subscribers = Arc::downgrade(&self.subscribers);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(poll_interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut seen: HashMap<StationId, HashSet<String>> = HashMap::new();
+
+            loop {
+                ticker.tick().await;
+                let Some(stations) = subscribed_stations(&subscribers) else {
+                    return;
+                };
+                seen.retain(|station, _| stations.contains(station));
+
+                let results = client.get_station_buses_many(&stations).await;
+                let mut events = Vec::new();
+                for (&station_id, result) in stations.iter().zip(results) {
+                    let Ok(buses) = result else {
+                        continue;
+                    };
+                    let plates = buses
+                        .iter()
+                        .map(|bus| normalize_plate(&bus.license_plate))
+                        .collect();
+                    let Some(previous) = seen.insert(station_id, plates) else {
+                        continue;
+                    };
+                    events.extend(
+                        buses
+                            .into_iter()
+                            .filter(|bus| !previous.contains(&normalize_plate(&bus.license_plate)))
+                            .map(|bus| ArrivalEvent { station_id, bus }),
+                    );
+                }
+
+                let Some(subscribers) = subscribers.upgrade() else {
+                    return;
+                };
+                publish(&subscribers, events);
+            }
+        })
+    }
+}
+
+/// The stations with subscribers left, or `None` once the bus is gone.
+fn subscribed_stations(subscribers: &Weak<Subscribers>) -> Option<Vec<StationId>> {
+    let subscribers = subscribers.upgrade()?;
+    let mut subscribers = subscribers.lock().unwrap();
+    subscribers.retain(|_, senders| {
+        senders.retain(|sender| !sender.is_closed());
+        !senders.is_empty()
+    });
+    Some(subscribers.keys().copied().collect())
+}
+
+fn publish(subscribers: &Subscribers, events: Vec<ArrivalEvent>) {
+    let subscribers = subscribers.lock().unwrap();
+    for event in events {
+        for sender in subscribers.get(&event.station_id).into_iter().flatten() {
+            if let Err(TrySendError::Full(event)) = sender.try_send(event.clone()) {
+                tracing::warn!(
+                    station = event.station_id.0,
+                    plate = event.bus.license_plate,
+                    "subscriber is behind, dropping arrival event"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! GeoJSON and KML exports of stations and routes.
+
+#[cfg(feature = "geojson")]
+use crate::NetworkSnapshot;
+#[cfg(feature = "geojson")]
+use crate::geo::StationCluster;
+use crate::{Line, Station};
+#[cfg(feature = "geojson")]
+use serde_json::{Value, json};
+#[cfg(feature = "geojson")]
+use std::collections::HashMap;
+
+#[cfg(feature = "geojson")]
+fn station_feature(station: &Station) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "Point",
+            "coordinates": [station.coords.long, station.coords.lat],
+        },
+        "properties": {
+            "id": station.id,
+            "name": station.human_name,
+        },
+    })
+}
+
+#[cfg(feature = "geojson")]
+fn route_feature<'a>(line: &Line, stations: impl Iterator<Item = &'a Station>) -> Value {
+    let coordinates: Vec<[f64; 2]> = stations
+        .map(|station| [station.coords.long, station.coords.lat])
+        .collect();
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "LineString",
+            "coordinates": coordinates,
+        },
+        "properties": {
+            "id": line.id,
+            "name": line.human_name,
+        },
+    })
+}
+
+/// A FeatureCollection with a Point for each station.
+#[cfg(feature = "geojson")]
+pub fn stations_geojson(stations: &[Station]) -> Value {
+    let features: Vec<Value> = stations.iter().map(station_feature).collect();
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+/// A FeatureCollection with a Point for each cluster from
+/// [`grid_cluster_stations`](crate::geo::grid_cluster_stations), at its
+/// centroid with `count` and `ids` properties, and for each station alone
+/// in its cell as in [`stations_geojson`].
+#[cfg(feature = "geojson")]
+pub fn station_clusters_geojson(clusters: &[StationCluster]) -> Value {
+    let features: Vec<Value> = clusters
+        .iter()
+        .map(|cluster| match cluster {
+            StationCluster::Station(station) => station_feature(station),
+            StationCluster::Cluster {
+                centroid,
+                station_ids,
+                count,
+            } => json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [centroid.long, centroid.lat],
+                },
+                "properties": {
+                    "count": count,
+                    "ids": station_ids,
+                },
+            }),
+        })
+        .collect();
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+/// A FeatureCollection with the route as a LineString through its stations,
+/// followed by a Point for each station.
+#[cfg(feature = "geojson")]
+pub fn route_geojson(line: &Line, stations: &[Station]) -> Value {
+    let mut features = vec![route_feature(line, stations.iter())];
+    features.extend(stations.iter().map(station_feature));
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+/// A FeatureCollection with a LineString for each line with known station
+/// order, followed by a Point for each station.
+#[cfg(feature = "geojson")]
+pub fn network_geojson(snapshot: &NetworkSnapshot) -> Value {
+    let stations: HashMap<i32, &Station> = snapshot
+        .stations
+        .iter()
+        .map(|station| (station.id, station))
+        .collect();
+
+    let mut features: Vec<Value> = snapshot
+        .lines
+        .iter()
+        .filter_map(|line| {
+            let ids = snapshot.line_stations.get(&line.id)?;
+            Some(route_feature(
+                line,
+                ids.iter().filter_map(|id| stations.get(id).copied()),
+            ))
+        })
+        .collect();
+    features.extend(snapshot.stations.iter().map(station_feature));
+    json!({"type": "FeatureCollection", "features": features})
+}
+
+#[cfg(feature = "kml")]
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(feature = "kml")]
+fn kml_document(name: &str, placemarks: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n\
+         <Document>\n<name>{}</name>\n{placemarks}</Document>\n</kml>\n",
+        escape_xml(name)
+    )
+}
+
+#[cfg(feature = "kml")]
+fn station_placemarks(stations: &[Station]) -> String {
+    stations
+        .iter()
+        .map(|station| {
+            format!(
+                "<Placemark>\n<name>{}</name>\n<description>{}</description>\n\
+                 <Point><coordinates>{},{}</coordinates></Point>\n</Placemark>\n",
+                escape_xml(&station.human_name),
+                station.id,
+                station.coords.long,
+                station.coords.lat
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "kml")]
+pub fn stations_kml(stations: &[Station]) -> String {
+    kml_document("Stations", &station_placemarks(stations))
+}
+
+/// A KML document with the route as a LineString and a Placemark for each
+/// station.
+#[cfg(feature = "kml")]
+pub fn route_kml(line: &Line, stations: &[Station]) -> String {
+    let coordinates: Vec<String> = stations
+        .iter()
+        .map(|station| format!("{},{}", station.coords.long, station.coords.lat))
+        .collect();
+    let route = format!(
+        "<Placemark>\n<name>{}</name>\n<LineString><coordinates>{}</coordinates></LineString>\n\
+         </Placemark>\n",
+        escape_xml(&line.human_name),
+        coordinates.join(" ")
+    );
+    kml_document(&line.human_name, &(route + &station_placemarks(stations)))
+}
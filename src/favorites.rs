@@ -0,0 +1,261 @@
+//! Favorite stations and lines under labels chosen by the user, saved as
+//! JSON so that every frontend can share one file.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::io::write_atomic;
+
+pub const FAVORITES_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", content = "id", rename_all = "lowercase")]
+pub enum Target {
+    Station(i32),
+    Line(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Favorite {
+    pub label: String,
+    pub target: Target,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("there's already a favorite labelled {label:?}")]
+pub struct DuplicateLabel {
+    pub label: String,
+}
+
+/// Favorites in the order they were added, with unique labels.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Favorites {
+    version: u32,
+    favorites: Vec<Favorite>,
+}
+
+impl Default for Favorites {
+    fn default() -> Favorites {
+        Favorites {
+            version: FAVORITES_VERSION,
+            favorites: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Version {
+    #[serde(default)]
+    version: u32,
+}
+
+/// The first format, from before files had a version, which kept stations
+/// and lines apart like the CLI's config file.
+#[derive(Deserialize)]
+struct V0 {
+    #[serde(default)]
+    stations: BTreeMap<String, i32>,
+    #[serde(default)]
+    lines: BTreeMap<String, String>,
+}
+
+impl V0 {
+    fn migrate(self) -> Result<Favorites, DuplicateLabel> {
+        let mut favorites = Favorites::new();
+        for (label, id) in self.stations {
+            favorites.add_station(id, label)?;
+        }
+        for (label, id) in self.lines {
+            favorites.add_line(id, label)?;
+        }
+        Ok(favorites)
+    }
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, error)
+}
+
+impl Favorites {
+    pub fn new() -> Favorites {
+        Favorites::default()
+    }
+
+    /// Loads favorites saved with [`Favorites::save`], migrating older
+    /// formats. Fails with [`IoErrorKind::InvalidData`] if the file is
+    /// corrupt, from a newer version or has duplicate labels.
+    pub async fn load(path: &Path) -> std::io::Result<Favorites> {
+        let json = tokio::fs::read(path).await?;
+        Favorites::from_json(&json)
+    }
+
+    pub fn from_json(json: &[u8]) -> std::io::Result<Favorites> {
+        let Version { version } = serde_json::from_slice(json)?;
+        match version {
+            0 => {
+                let v0: V0 = serde_json::from_slice(json)?;
+                v0.migrate().map_err(invalid_data)
+            }
+            FAVORITES_VERSION => {
+                let favorites: Favorites = serde_json::from_slice(json)?;
+                let mut checked = Favorites::new();
+                for favorite in favorites.favorites {
+                    checked.add(favorite).map_err(invalid_data)?;
+                }
+                Ok(checked)
+            }
+            version => Err(invalid_data(format!(
+                "unsupported favorites version {version}, expected {FAVORITES_VERSION}"
+            ))),
+        }
+    }
+
+    /// Saves the favorites in the current format, replacing the file at
+    /// `path` at once so that readers never see it half written.
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        write_atomic(path, serde_json::to_vec_pretty(self)?).await
+    }
+
+    pub fn add(&mut self, favorite: Favorite) -> Result<(), DuplicateLabel> {
+        if self.get(&favorite.label).is_some() {
+            return Err(DuplicateLabel {
+                label: favorite.label,
+            });
+        }
+        self.favorites.push(favorite);
+        Ok(())
+    }
+
+    pub fn add_station(&mut self, id: i32, label: impl Into<String>) -> Result<(), DuplicateLabel> {
+        self.add(Favorite {
+            label: label.into(),
+            target: Target::Station(id),
+        })
+    }
+
+    pub fn add_line(
+        &mut self,
+        id: impl Into<String>,
+        label: impl Into<String>,
+    ) -> Result<(), DuplicateLabel> {
+        self.add(Favorite {
+            label: label.into(),
+            target: Target::Line(id.into()),
+        })
+    }
+
+    /// Removes the favorite labelled `label`, returning it if there was one.
+    pub fn remove(&mut self, label: &str) -> Option<Favorite> {
+        let index = self
+            .favorites
+            .iter()
+            .position(|favorite| favorite.label == label)?;
+        Some(self.favorites.remove(index))
+    }
+
+    pub fn list(&self) -> &[Favorite] {
+        &self.favorites
+    }
+
+    fn get(&self, label: &str) -> Option<&Favorite> {
+        self.favorites
+            .iter()
+            .find(|favorite| favorite.label == label)
+    }
+
+    pub fn resolve(&self, label: &str) -> Option<&Target> {
+        self.get(label).map(|favorite| &favorite.target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn favorites() -> Favorites {
+        let mut favorites = Favorites::new();
+        favorites.add_station(10, "home").unwrap();
+        favorites.add_line("7A", "work").unwrap();
+        favorites
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("favorites.json");
+        let saved = favorites();
+
+        block_on(saved.save(&path)).unwrap();
+        let loaded = block_on(Favorites::load(&path)).unwrap();
+        assert_eq!(loaded, saved);
+        assert_eq!(loaded.resolve("home"), Some(&Target::Station(10)));
+        assert_eq!(loaded.resolve("work"), Some(&Target::Line("7A".into())));
+        assert_eq!(loaded.resolve("gym"), None);
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(json["version"], FAVORITES_VERSION);
+        assert_eq!(json["favorites"][1]["target"]["kind"], "line");
+    }
+
+    #[test]
+    fn keeps_insertion_order_and_removes() {
+        let mut favorites = favorites();
+        favorites.add_station(12, "bus station").unwrap();
+        let labels: Vec<&str> = favorites.list().iter().map(|f| &*f.label).collect();
+        assert_eq!(labels, ["home", "work", "bus station"]);
+
+        let removed = favorites.remove("work").unwrap();
+        assert_eq!(removed.target, Target::Line("7A".into()));
+        assert_eq!(favorites.remove("work"), None);
+        assert_eq!(favorites.list().len(), 2);
+    }
+
+    #[test]
+    fn rejects_duplicate_labels() {
+        let mut favorites = favorites();
+        let error = favorites.add_line("1", "home").unwrap_err();
+        assert_eq!(error.label, "home");
+        assert_eq!(favorites.resolve("home"), Some(&Target::Station(10)));
+
+        let json = br#"{"version": 1, "favorites": [
+            {"label": "home", "target": {"kind": "station", "id": 10}},
+            {"label": "home", "target": {"kind": "line", "id": "1"}}
+        ]}"#;
+        let error = Favorites::from_json(json).unwrap_err();
+        assert_eq!(error.kind(), IoErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn migrates_files_without_a_version() {
+        let v0 = br#"{"stations": {"home": 10}, "lines": {"work": "7A"}}"#;
+        let favorites = Favorites::from_json(v0).unwrap();
+        assert_eq!(favorites, self::favorites());
+        assert_eq!(favorites.version, FAVORITES_VERSION);
+
+        let clash = br#"{"stations": {"home": 10}, "lines": {"home": "7A"}}"#;
+        let error = Favorites::from_json(clash).unwrap_err();
+        assert_eq!(error.kind(), IoErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let error = Favorites::from_json(br#"{"version": 2, "favorites": []}"#).unwrap_err();
+        assert_eq!(error.kind(), IoErrorKind::InvalidData);
+        assert!(
+            error
+                .to_string()
+                .contains("unsupported favorites version 2")
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! UniFFI bindings for Kotlin and Swift.
+//!
+//! Build the library and generate bindings with the bundled bindgen:
+//!
+//! ```sh
+//! cargo rustc --release --features uniffi --lib --crate-type cdylib
+//! cargo run --features uniffi --bin uniffi-bindgen -- generate \
+//!     --library target/release/libsivasbus.so --language kotlin --out-dir bindings
+//! ```
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tokio::runtime::Runtime;
+
+use crate::ErrorKind;
+
+#[derive(uniffi::Record, Debug, Clone, PartialEq)]
+pub struct Coords {
+    pub lat: f64,
+    pub long: f64,
+}
+
+#[derive(uniffi::Record, Debug, Clone, PartialEq)]
+pub struct Station {
+    pub id: i32,
+    pub human_name: String,
+    pub coords: Coords,
+}
+
+#[derive(uniffi::Record, Debug, Clone, PartialEq)]
+pub struct Line {
+    pub id: String,
+    pub human_name: String,
+}
+
+#[derive(uniffi::Record, Debug, Clone, PartialEq)]
+pub struct LineBus {
+    pub license_plate: String,
+    pub coords: Coords,
+    pub last_update: Option<SystemTime>,
+}
+
+#[derive(uniffi::Record, Debug, Clone, PartialEq)]
+pub struct StationBus {
+    pub license_plate: String,
+    pub arrive_time: Duration,
+}
+
+impl From<crate::Coords> for Coords {
+    fn from(coords: crate::Coords) -> Coords {
+        Coords {
+            lat: coords.lat,
+            long: coords.long,
+        }
+    }
+}
+
+impl From<Coords> for crate::Coords {
+    fn from(coords: Coords) -> crate::Coords {
+        crate::Coords {
+            lat: coords.lat,
+            long: coords.long,
+        }
+    }
+}
+
+impl From<crate::Station> for Station {
+    fn from(station: crate::Station) -> Station {
+        Station {
+            id: station.id,
+            human_name: station.human_name,
+            coords: station.coords.into(),
+        }
+    }
+}
+
+impl From<Station> for crate::Station {
+    fn from(station: Station) -> crate::Station {
+        crate::Station {
+            id: station.id,
+            human_name: station.human_name,
+            coords: station.coords.into(),
+        }
+    }
+}
+
+impl From<crate::Line> for Line {
+    fn from(line: crate::Line) -> Line {
+        Line {
+            id: line.id,
+            human_name: line.human_name,
+        }
+    }
+}
+
+impl From<crate::LineBus> for LineBus {
+    fn from(bus: crate::LineBus) -> LineBus {
+        LineBus {
+            license_plate: bus.license_plate,
+            coords: bus.coords.into(),
+            last_update: bus.last_update.map(Into::into),
+        }
+    }
+}
+
+impl From<crate::StationBus> for StationBus {
+    fn from(bus: crate::StationBus) -> StationBus {
+        StationBus {
+            license_plate: bus.license_plate,
+            arrive_time: bus.arrive_time,
+        }
+    }
+}
+
+#[derive(uniffi::Error, thiserror::Error, Debug)]
+#[uniffi(flat_error)]
+pub enum Error {
+    #[error("{0}")]
+    Network(String),
+    #[error("{0}")]
+    Parse(String),
+    #[error("{0}")]
+    TokenMissing(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("{0}")]
+    Runtime(String),
+}
+
+impl From<crate::Error> for Error {
+    fn from(error: crate::Error) -> Error {
+        let message = error.to_string();
+        match error.kind() {
+            ErrorKind::Network => Error::Network(message),
+            ErrorKind::Parse => Error::Parse(message),
+            ErrorKind::TokenMissing => Error::TokenMissing(message),
+            ErrorKind::NotFound => Error::NotFound(message),
+            ErrorKind::Unavailable => Error::Unavailable(message),
+        }
+    }
+}
+
+fn map_into<T: Into<U>, U>(v: Vec<T>) -> Vec<U> {
+    v.into_iter().map(Into::into).collect()
+}
+
+#[derive(uniffi::Object)]
+pub struct BlockingClient {
+    client: crate::Client,
+    runtime: Runtime,
+}
+
+#[uniffi::export]
+impl BlockingClient {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<BlockingClient>, Error> {
+        Ok(Arc::new(BlockingClient {
+            client: crate::Client::builder().build()?,
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| Error::Runtime(e.to_string()))?,
+        }))
+    }
+
+    pub fn get_lines(&self) -> Result<Vec<Line>, Error> {
+        Ok(map_into(self.runtime.block_on(self.client.get_lines())?))
+    }
+
+    pub fn get_all_stations(&self) -> Result<Vec<Station>, Error> {
+        Ok(map_into(
+            self.runtime.block_on(self.client.get_all_stations())?,
+        ))
+    }
+
+    pub fn get_stations(&self, line: String) -> Result<Vec<Station>, Error> {
+        Ok(map_into(
+            self.runtime.block_on(self.client.get_stations(&line))?,
+        ))
+    }
+
+    pub fn get_line_buses(&self, line: String) -> Result<Vec<LineBus>, Error> {
+        Ok(map_into(
+            self.runtime.block_on(self.client.get_line_buses(&line))?,
+        ))
+    }
+
+    pub fn get_station_buses(&self, station: i32) -> Result<Vec<StationBus>, Error> {
+        Ok(map_into(self.runtime.block_on(
+            self.client.get_station_buses(crate::StationId(station)),
+        )?))
+    }
+}
+
+#[uniffi::export]
+pub fn nearest_station(stations: Vec<Station>, coords: Coords) -> Option<Station> {
+    let stations: Vec<crate::Station> = map_into(stations);
+    crate::geo::nearest_station(&stations, &coords.into())
+        .cloned()
+        .map(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uniffi::{Lift, Lower};
+
+    use crate::UniFfiTag;
+
+    fn round_trip<T: Lower<UniFfiTag> + Lift<UniFfiTag>>(value: T) -> T {
+        T::try_lift_from_rust_buffer(T::lower_into_rust_buffer(value)).unwrap()
+    }
+
+    fn station(id: i32, lat: f64, long: f64) -> crate::Station {
+        crate::Station {
+            id,
+            human_name: format!("Durak {id}"),
+            coords: crate::Coords { lat, long },
+        }
+    }
+
+    #[test]
+    fn station_round_trips() {
+        let original = station(10, 39.7505, 37.015);
+        let lifted = round_trip(Station::from(original.clone()));
+        assert_eq!(crate::Station::from(lifted), original);
+    }
+
+    #[test]
+    fn line_round_trips() {
+        let line = Line::from(crate::Line {
+            id: "7A".to_string(),
+            human_name: "7A - Kampüs".to_string(),
+        });
+        assert_eq!(round_trip(line.clone()), line);
+    }
+
+    #[test]
+    fn line_bus_round_trips_with_and_without_last_update() {
+        for last_update in [None, Some("2026-10-01T08:30:00Z".parse().unwrap())] {
+            let bus = LineBus::from(crate::LineBus {
+                license_plate: "58 AC 123".to_string(),
+                coords: crate::Coords {
+                    lat: 39.751,
+                    long: 37.016,
+                },
+                last_update,
+            });
+            assert_eq!(bus.last_update, last_update.map(SystemTime::from));
+            assert_eq!(round_trip(bus.clone()), bus);
+        }
+    }
+
+    #[test]
+    fn station_bus_round_trips() {
+        let bus = StationBus::from(crate::StationBus {
+            license_plate: "58 AC 123".to_string(),
+            arrive_time: Duration::from_secs(240),
+        });
+        assert_eq!(round_trip(bus.clone()), bus);
+    }
+
+    #[test]
+    fn errors_flatten_by_kind() {
+        assert!(matches!(
+            Error::from(crate::Error::NoStations),
+            Error::NotFound(_)
+        ));
+        assert!(matches!(
+            Error::from(crate::Error::NoToken),
+            Error::TokenMissing(_)
+        ));
+        assert!(matches!(
+            Error::from(crate::Error::Timeout),
+            Error::Network(_)
+        ));
+    }
+
+    #[test]
+    fn nearest_station_converts_both_ways() {
+        let stations = vec![
+            station(10, 39.7505, 37.015).into(),
+            station(11, 39.77, 37.04).into(),
+        ];
+        let nearest = nearest_station(
+            stations,
+            Coords {
+                lat: 39.769,
+                long: 37.039,
+            },
+        );
+        assert_eq!(nearest.map(|station| station.id), Some(11));
+    }
+}
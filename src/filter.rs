@@ -0,0 +1,115 @@
+//! Predicates over buses and stations that can be combined, for picking out
+//! items by several criteria at once, e.g. stations near a point whose name
+//! doesn't mention "meydan":
+//! `StationFilter::by_radius(origin, 500.0).and(StationFilter::by_name_contains("meydan").negate())`.
+
+use crate::geo::BoundingBox;
+use crate::{Coords, LineBus, Station, normalize_plate};
+
+/// A predicate over `T`.
+pub struct Filter<T>(Box<dyn Fn(&T) -> bool + Send + Sync>);
+
+pub type LineBusFilter = Filter<LineBus>;
+pub type StationFilter = Filter<Station>;
+
+impl<T: 'static> Filter<T> {
+    pub fn new(predicate: impl Fn(&T) -> bool + Send + Sync + 'static) -> Filter<T> {
+        Filter(Box::new(predicate))
+    }
+
+    pub fn matches(&self, item: &T) -> bool {
+        (self.0)(item)
+    }
+
+    /// Matches the items both filters match.
+    pub fn and(self, other: Filter<T>) -> Filter<T> {
+        Filter::new(move |item| self.matches(item) && other.matches(item))
+    }
+
+    /// Matches the items either filter matches.
+    pub fn or(self, other: Filter<T>) -> Filter<T> {
+        Filter::new(move |item| self.matches(item) || other.matches(item))
+    }
+
+    /// Matches the items this filter doesn't.
+    pub fn negate(self) -> Filter<T> {
+        Filter::new(move |item| !self.matches(item))
+    }
+
+    /// The items the filter matches, in order.
+    pub fn apply<'a>(&self, items: &'a [T]) -> Vec<&'a T> {
+        items.iter().filter(|item| self.matches(item)).collect()
+    }
+}
+
+impl Filter<LineBus> {
+    /// Buses whose plate starts with `prefix`, ignoring case, spaces and
+    /// dashes, so "58 ac" matches "58 AC 123".
+    pub fn by_plate_prefix(prefix: &str) -> LineBusFilter {
+        let prefix = normalize_plate(prefix);
+        Filter::new(move |bus: &LineBus| normalize_plate(&bus.license_plate).starts_with(&prefix))
+    }
+
+    pub fn by_bbox(bbox: BoundingBox) -> LineBusFilter {
+        Filter::new(move |bus: &LineBus| bbox.contains(&bus.coords))
+    }
+}
+
+impl Filter<Station> {
+    /// Stations whose name contains `text`, ignoring case the Turkish way
+    /// too, so "istasyon" finds "İSTASYON" and "meydani" finds "Meydanı".
+    pub fn by_name_contains(text: &str) -> StationFilter {
+        let text = fold_case(text);
+        Filter::new(move |station: &Station| fold_case(&station.human_name).contains(&text))
+    }
+
+    /// Stations at most `meters` from `origin`.
+    pub fn by_radius(origin: Coords, meters: f64) -> StationFilter {
+        Filter::new(move |station: &Station| station.coords.distance_m(&origin) <= meters)
+    }
+}
+
+/// Lowercases `text` with İ, I, ı and i all folded to i, since Turkish
+/// and ASCII casing disagree on them.
+fn fold_case(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            'İ' | 'I' | 'ı' => vec!['i'],
+            c => c.to_lowercase().collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn station(name: &str) -> Station {
+        Station {
+            id: 1,
+            human_name: name.to_string(),
+            coords: Coords {
+                lat: 39.75,
+                long: 37.02,
+            },
+        }
+    }
+
+    #[test]
+    fn name_contains_folds_turkish_i() {
+        let stations = [
+            station("İSTASYON CADDESİ"),
+            station("Cumhuriyet Meydanı"),
+            station("Otogar"),
+        ];
+
+        let found = StationFilter::by_name_contains("istasyon").apply(&stations);
+        assert_eq!(found, [&stations[0]]);
+
+        let found = StationFilter::by_name_contains("MEYDANI").apply(&stations);
+        assert_eq!(found, [&stations[1]]);
+
+        let found = StationFilter::by_name_contains("caddesi").apply(&stations);
+        assert_eq!(found, [&stations[0]]);
+    }
+}
@@ -0,0 +1,839 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::ops::RangeInclusive;
+use thiserror::Error;
+
+use crate::{Coords, LineBus, Station, normalize_plate};
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+// A rough bounding box around Turkey. Anything outside it is a bad GPS fix
+// or a placeholder like 0,0.
+const VALID_LAT: RangeInclusive<f64> = 36.0..=42.5;
+const VALID_LONG: RangeInclusive<f64> = 26.0..=45.5;
+
+/// Consecutive stops of a city bus route further apart than this are
+/// likely out of order or misplaced.
+const MAX_STATION_GAP_KM: f64 = 5.0;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum GeohashError {
+    #[error("geohash is empty")]
+    Empty,
+    #[error("invalid geohash character {0:?}")]
+    InvalidChar(char),
+}
+
+impl LineBus {
+    /// Whether the bus's coordinates are plausible, i.e. inside Turkey.
+    pub fn is_valid_position(&self) -> bool {
+        self.coords.is_valid()
+    }
+}
+
+impl Station {
+    /// Whether the station's coordinates are plausible, i.e. inside Turkey.
+    pub fn is_valid_position(&self) -> bool {
+        self.coords.is_valid()
+    }
+}
+
+impl Coords {
+    pub(crate) fn is_valid(&self) -> bool {
+        VALID_LAT.contains(&self.lat) && VALID_LONG.contains(&self.long)
+    }
+
+    pub fn distance_m(&self, other: &Coords) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlong = (other.long - self.long).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlong / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+    }
+
+    /// The geohash of the cell of `precision` characters holding the
+    /// coordinates. Each character narrows the cell down by 32 times, from
+    /// about 5000 km across at 1 to a few centimetres at 12.
+    pub fn geohash(&self, precision: usize) -> String {
+        let mut lat = (-90.0, 90.0);
+        let mut long = (-180.0, 180.0);
+        let mut hash = String::with_capacity(precision);
+        let mut even = true;
+        for _ in 0..precision {
+            let mut index = 0;
+            for _ in 0..5 {
+                let (range, value) = match even {
+                    true => (&mut long, self.long),
+                    false => (&mut lat, self.lat),
+                };
+                let mid = (range.0 + range.1) / 2.0;
+                index <<= 1;
+                if value >= mid {
+                    index |= 1;
+                    range.0 = mid;
+                } else {
+                    range.1 = mid;
+                }
+                even = !even;
+            }
+            hash.push(GEOHASH_ALPHABET[index] as char);
+        }
+        hash
+    }
+
+    /// The centre and bounds of a geohash's cell. Upper case is accepted.
+    pub fn from_geohash(hash: &str) -> Result<(Coords, BoundingBox), GeohashError> {
+        if hash.is_empty() {
+            return Err(GeohashError::Empty);
+        }
+        let mut lat = (-90.0, 90.0);
+        let mut long = (-180.0, 180.0);
+        let mut even = true;
+        for c in hash.chars() {
+            let index = GEOHASH_ALPHABET
+                .iter()
+                .position(|&known| known as char == c.to_ascii_lowercase())
+                .ok_or(GeohashError::InvalidChar(c))?;
+            for bit in (0..5).rev() {
+                let range = match even {
+                    true => &mut long,
+                    false => &mut lat,
+                };
+                let mid = (range.0 + range.1) / 2.0;
+                match index >> bit & 1 {
+                    1 => range.0 = mid,
+                    _ => range.1 = mid,
+                }
+                even = !even;
+            }
+        }
+        let centre = Coords {
+            lat: (lat.0 + lat.1) / 2.0,
+            long: (long.0 + long.1) / 2.0,
+        };
+        let bounds = BoundingBox {
+            min: Coords {
+                lat: lat.0,
+                long: long.0,
+            },
+            max: Coords {
+                lat: lat.1,
+                long: long.1,
+            },
+        };
+        Ok((centre, bounds))
+    }
+
+    /// Initial bearing towards `other` in degrees clockwise from north.
+    pub fn bearing_deg(&self, other: &Coords) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlong = (other.long - self.long).to_radians();
+
+        let y = dlong.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlong.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+}
+
+// geo-types points are (x, y), i.e. (longitude, latitude).
+#[cfg(feature = "geo-types")]
+impl From<Coords> for geo_types::Point<f64> {
+    fn from(coords: Coords) -> geo_types::Point<f64> {
+        geo_types::Point::new(coords.long, coords.lat)
+    }
+}
+
+#[cfg(feature = "geo-types")]
+impl From<geo_types::Point<f64>> for Coords {
+    fn from(point: geo_types::Point<f64>) -> Coords {
+        Coords {
+            lat: point.y(),
+            long: point.x(),
+        }
+    }
+}
+
+/// An area between two latitudes and two longitudes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BoundingBox {
+    /// The south-west corner.
+    pub min: Coords,
+    /// The north-east corner.
+    pub max: Coords,
+}
+
+impl BoundingBox {
+    /// The box with `a` and `b` as opposite corners, in either order.
+    pub fn new(a: Coords, b: Coords) -> BoundingBox {
+        BoundingBox {
+            min: Coords {
+                lat: a.lat.min(b.lat),
+                long: a.long.min(b.long),
+            },
+            max: Coords {
+                lat: a.lat.max(b.lat),
+                long: a.long.max(b.long),
+            },
+        }
+    }
+
+    /// Whether `coords` is inside the box or on its edge.
+    pub fn contains(&self, coords: &Coords) -> bool {
+        (self.min.lat..=self.max.lat).contains(&coords.lat)
+            && (self.min.long..=self.max.long).contains(&coords.long)
+    }
+
+    /// The geohashes of `precision` characters whose cells cover the box,
+    /// sorted. Anything inside the box has one of them as a prefix of its
+    /// own geohash.
+    ///
+    /// The count grows 32 times with each character, so the precision
+    /// should suit the size of the box: 5 for a city, 6 or 7 for a
+    /// neighbourhood.
+    pub fn geohashes(&self, precision: usize) -> Vec<String> {
+        if precision == 0 {
+            return vec![String::new()];
+        }
+        let long_bits = (5 * precision).div_ceil(2) as i32;
+        let lat_bits = (5 * precision / 2) as i32;
+        let cell_height = 180.0 / 2f64.powi(lat_bits);
+        let cell_width = 360.0 / 2f64.powi(long_bits);
+        let cells = |min: f64, max: f64, origin: f64, size: f64, bits: i32| {
+            let last = 2f64.powi(bits) - 1.0;
+            let first = ((min - origin) / size).floor().clamp(0.0, last);
+            let last = ((max - origin) / size).floor().clamp(0.0, last);
+            (first as u64..=last as u64).map(move |i| origin + (i as f64 + 0.5) * size)
+        };
+
+        let mut hashes = BTreeSet::new();
+        for lat in cells(self.min.lat, self.max.lat, -90.0, cell_height, lat_bits) {
+            for long in cells(self.min.long, self.max.long, -180.0, cell_width, long_bits) {
+                hashes.insert(Coords { lat, long }.geohash(precision));
+            }
+        }
+        hashes.into_iter().collect()
+    }
+}
+
+/// The south-west and north-east corners of the smallest box holding every
+/// station, or `None` if there are none. Stations with implausible
+/// positions are left out, so a placeholder 0,0 doesn't stretch the box
+/// across the globe.
+pub fn stations_bounding_box(stations: &[Station]) -> Option<(Coords, Coords)> {
+    stations
+        .iter()
+        .filter(|station| station.is_valid_position())
+        .fold(None, |bounds: Option<(Coords, Coords)>, station| {
+            let coords = &station.coords;
+            let (min, max) = bounds.unwrap_or((coords.clone(), coords.clone()));
+            Some((
+                Coords {
+                    lat: min.lat.min(coords.lat),
+                    long: min.long.min(coords.long),
+                },
+                Coords {
+                    lat: max.lat.max(coords.lat),
+                    long: max.long.max(coords.long),
+                },
+            ))
+        })
+}
+
+pub fn nearest_station<'a>(stations: &'a [Station], coords: &Coords) -> Option<&'a Station> {
+    stations.iter().min_by(|a, b| {
+        a.coords
+            .distance_m(coords)
+            .total_cmp(&b.coords.distance_m(coords))
+    })
+}
+
+pub fn stations_within<'a>(
+    stations: &'a [Station],
+    coords: &Coords,
+    radius_m: f64,
+) -> Vec<(&'a Station, f64)> {
+    let mut found: Vec<(&Station, f64)> = stations
+        .iter()
+        .map(|station| (station, station.coords.distance_m(coords)))
+        .filter(|(_, distance)| *distance <= radius_m)
+        .collect();
+    found.sort_by(|a, b| a.1.total_cmp(&b.1));
+    found
+}
+
+pub fn snap_to_station<'a>(
+    stations: &'a [Station],
+    coords: &Coords,
+    max_dist_m: f64,
+) -> Option<&'a Station> {
+    nearest_station(stations, coords)
+        .filter(|station| station.coords.distance_m(coords) <= max_dist_m)
+}
+
+/// Distance from `coords` to the segment between `a` and `b`, on a local
+/// flat projection that is accurate enough at city scale.
+pub fn distance_to_segment_m(coords: &Coords, a: &Coords, b: &Coords) -> f64 {
+    project_onto_segment(coords, a, b).1
+}
+
+/// How far along the segment from `a` to `b` the point closest to `coords`
+/// is, from 0 to 1, and how far away that point is in metres.
+pub(crate) fn project_onto_segment(coords: &Coords, a: &Coords, b: &Coords) -> (f64, f64) {
+    let scale = coords.lat.to_radians().cos();
+    let project = |point: &Coords| {
+        (
+            (point.long - coords.long).to_radians() * scale * EARTH_RADIUS_M,
+            (point.lat - coords.lat).to_radians() * EARTH_RADIUS_M,
+        )
+    };
+    let (ax, ay) = project(a);
+    let (bx, by) = project(b);
+    let (dx, dy) = (bx - ax, by - ay);
+
+    let length2 = dx * dx + dy * dy;
+    let t = if length2 > 0.0 {
+        (-(ax * dx + ay * dy) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (t, (ax + t * dx).hypot(ay + t * dy))
+}
+
+/// Index of the leg, i.e. the segment from `stations[i]` to
+/// `stations[i + 1]`, closest to `coords`.
+pub fn nearest_leg(stations: &[Station], coords: &Coords) -> Option<usize> {
+    stations
+        .windows(2)
+        .map(|leg| distance_to_segment_m(coords, &leg[0].coords, &leg[1].coords))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Length of a route through `stations` in order, in kilometres.
+///
+/// This is the straight-line distance from stop to stop, not the distance
+/// along the roads, so it underestimates how far the buses actually go.
+pub fn route_length_km(stations: &[Station]) -> f64 {
+    stations
+        .windows(2)
+        .map(|leg| leg[0].coords.distance_m(&leg[1].coords))
+        .sum::<f64>()
+        / 1000.0
+}
+
+/// A suspiciously long gap between consecutive stations of a line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StationOrderWarning {
+    /// Index of the station after the gap.
+    pub idx: usize,
+    pub distance_km: f64,
+}
+
+/// Flags the consecutive stations more than 5 km apart, which on a city
+/// bus route means stops are out of order or have wrong coordinates.
+pub fn validate_station_order(stations: &[Station]) -> Vec<StationOrderWarning> {
+    stations
+        .windows(2)
+        .enumerate()
+        .map(|(i, leg)| StationOrderWarning {
+            idx: i + 1,
+            distance_km: leg[0].coords.distance_m(&leg[1].coords) / 1000.0,
+        })
+        .filter(|warning| warning.distance_km > MAX_STATION_GAP_KM)
+        .collect()
+}
+
+/// Where a bus likely was `fraction` of the way from its `older` to its
+/// `newer` position, moving in a straight line. `fraction` is clamped to
+/// 0..=1. `None` if the two aren't the same bus or `fraction` is NaN.
+pub fn interpolate_position(older: &LineBus, newer: &LineBus, fraction: f64) -> Option<Coords> {
+    if normalize_plate(&older.license_plate) != normalize_plate(&newer.license_plate)
+        || fraction.is_nan()
+    {
+        return None;
+    }
+    let fraction = fraction.clamp(0.0, 1.0);
+    let (a, b) = (&older.coords, &newer.coords);
+    Some(Coords {
+        lat: a.lat + (b.lat - a.lat) * fraction,
+        long: a.long + (b.long - a.long) * fraction,
+    })
+}
+
+/// The buses of the newest poll in `history`, oldest poll first, with
+/// their positions averaged over the last `window` polls to smooth out GPS
+/// jitter. Newer positions weigh more: the newest counts `window` times,
+/// the one before it `window - 1` times and so on. Polls where a bus is
+/// missing or has an implausible position are skipped.
+///
+/// A bus that really moved lags behind, so keep `window` small.
+pub fn smooth_positions(history: &VecDeque<Vec<LineBus>>, window: usize) -> Vec<LineBus> {
+    let Some(newest) = history.back() else {
+        return Vec::new();
+    };
+    let mut sums: HashMap<String, (f64, f64, f64)> = HashMap::new();
+    for (age, poll) in history.iter().rev().take(window).enumerate() {
+        let weight = (window - age) as f64;
+        for bus in poll.iter().filter(|bus| bus.coords.is_valid()) {
+            let sum = sums.entry(normalize_plate(&bus.license_plate)).or_default();
+            sum.0 += bus.coords.lat * weight;
+            sum.1 += bus.coords.long * weight;
+            sum.2 += weight;
+        }
+    }
+
+    newest
+        .iter()
+        .map(|bus| {
+            let mut bus = bus.clone();
+            if let Some((lat, long, weight)) = sums.get(&normalize_plate(&bus.license_plate))
+                && bus.coords.is_valid()
+            {
+                bus.coords = Coords {
+                    lat: lat / weight,
+                    long: long / weight,
+                };
+            }
+            bus
+        })
+        .collect()
+}
+
+/// Whether `coords` is inside `polygon`, by ray casting on plain latitude
+/// and longitude, which is fine at city scale. Points exactly on an edge
+/// may land either way. Nothing is inside a polygon of fewer than three
+/// points.
+pub(crate) fn in_polygon(coords: &Coords, polygon: &[Coords]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut previous = &polygon[polygon.len() - 1];
+    for vertex in polygon {
+        if (vertex.lat > coords.lat) != (previous.lat > coords.lat) {
+            let crossing = vertex.long
+                + (coords.lat - vertex.lat) / (previous.lat - vertex.lat)
+                    * (previous.long - vertex.long);
+            if coords.long < crossing {
+                inside = !inside;
+            }
+        }
+        previous = vertex;
+    }
+    inside
+}
+
+/// The buses inside `polygon`, whose vertices may or may not repeat the
+/// first one at the end. Empty if the polygon has fewer than three points.
+pub fn buses_in_polygon<'a>(buses: &'a [LineBus], polygon: &[Coords]) -> Vec<&'a LineBus> {
+    buses
+        .iter()
+        .filter(|bus| in_polygon(&bus.coords, polygon))
+        .collect()
+}
+
+/// Groups stations that are within `radius_m` of each other, such as the
+/// stops on either side of a street or around a square, largest group
+/// first.
+///
+/// Each group starts from the first station not yet in one and takes in
+/// every station within `radius_m` of a station already in it, so a chain
+/// of close stations ends up in one group. Stations with no neighbours are
+/// groups of their own.
+pub fn cluster_stations(stations: &[Station], radius_m: f64) -> Vec<Vec<&Station>> {
+    let mut assigned = vec![false; stations.len()];
+    let mut clusters = Vec::new();
+    for seed in 0..stations.len() {
+        if assigned[seed] {
+            continue;
+        }
+        assigned[seed] = true;
+        let mut cluster = vec![seed];
+        let mut next = 0;
+        while let Some(&member) = cluster.get(next) {
+            next += 1;
+            for (i, station) in stations.iter().enumerate() {
+                if !assigned[i] && station.coords.distance_m(&stations[member].coords) <= radius_m {
+                    assigned[i] = true;
+                    cluster.push(i);
+                }
+            }
+        }
+        clusters.push(
+            cluster
+                .into_iter()
+                .map(|i| &stations[i])
+                .collect::<Vec<_>>(),
+        );
+    }
+    clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.len()));
+    clusters
+}
+
+/// Metres per pixel at the equator at zoom 0 on web maps like Leaflet.
+const EQUATOR_M_PER_PX: f64 = 156_543.033_92;
+/// How wide a cluster cell is on screen, whatever the zoom.
+const CLUSTER_CELL_PX: f64 = 60.0;
+const M_PER_DEG_LAT: f64 = 111_320.0;
+
+/// A marker on a clustered map of stations.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StationCluster {
+    /// A station alone in its cell, to show like any other.
+    Station(Station),
+    /// Several stations sharing a cell.
+    Cluster {
+        /// The average position of the stations.
+        centroid: Coords,
+        station_ids: Vec<i32>,
+        count: usize,
+    },
+}
+
+fn mean_lat(stations: &[Station]) -> Option<f64> {
+    (!stations.is_empty()).then(|| {
+        stations
+            .iter()
+            .map(|station| station.coords.lat)
+            .sum::<f64>()
+            / stations.len() as f64
+    })
+}
+
+/// Groups stations into square cells of `cell_size_m`, for showing one
+/// marker per cell on a zoomed out map. Unlike [`cluster_stations`] this
+/// doesn't look at the distances between stations, so two close stations
+/// on either side of a cell edge end up apart, but it takes a single pass.
+///
+/// Cells are laid out from 0,0 with their width fixed at the stations'
+/// average latitude, so the same stations always cluster the same way.
+pub fn grid_cluster_stations(stations: &[Station], cell_size_m: f64) -> Vec<StationCluster> {
+    let Some(mean_lat) = mean_lat(stations).filter(|_| cell_size_m > 0.0) else {
+        return stations
+            .iter()
+            .cloned()
+            .map(StationCluster::Station)
+            .collect();
+    };
+    let cell_lat = cell_size_m / M_PER_DEG_LAT;
+    let cell_long = cell_size_m / (M_PER_DEG_LAT * mean_lat.to_radians().cos().max(0.01));
+
+    let mut cells: BTreeMap<(i64, i64), Vec<&Station>> = BTreeMap::new();
+    for station in stations {
+        let cell = (
+            (station.coords.lat / cell_lat).floor() as i64,
+            (station.coords.long / cell_long).floor() as i64,
+        );
+        cells.entry(cell).or_default().push(station);
+    }
+
+    cells
+        .into_values()
+        .map(|members| match members[..] {
+            [station] => StationCluster::Station(station.clone()),
+            _ => {
+                let count = members.len();
+                StationCluster::Cluster {
+                    centroid: Coords {
+                        lat: members.iter().map(|s| s.coords.lat).sum::<f64>() / count as f64,
+                        long: members.iter().map(|s| s.coords.long).sum::<f64>() / count as f64,
+                    },
+                    station_ids: members.iter().map(|station| station.id).collect(),
+                    count,
+                }
+            }
+        })
+        .collect()
+}
+
+/// The cell size for [`grid_cluster_stations`] that gives cells 60 pixels
+/// wide at a Leaflet zoom level and latitude: about 7 km at zoom 10 in
+/// Sivas, halving with each level.
+pub fn cell_size_for_zoom(zoom: u8, lat: f64) -> f64 {
+    CLUSTER_CELL_PX * EQUATOR_M_PER_PX * lat.to_radians().cos() / 2f64.powi(zoom.into())
+}
+
+/// Clusters stations for a map at a Leaflet zoom level, with the cell size
+/// from [`cell_size_for_zoom`] at the stations' average latitude.
+pub fn cluster_stations_for_zoom(stations: &[Station], zoom: u8) -> Vec<StationCluster> {
+    let Some(mean_lat) = mean_lat(stations) else {
+        return Vec::new();
+    };
+    grid_cluster_stations(stations, cell_size_for_zoom(zoom, mean_lat))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bus(plate: &str, lat: f64, long: f64) -> LineBus {
+        LineBus {
+            license_plate: plate.to_string(),
+            coords: Coords { lat, long },
+            last_update: None,
+        }
+    }
+
+    /// The midpoint of the great circle from `a` to `b`.
+    fn great_circle_midpoint(a: &Coords, b: &Coords) -> Coords {
+        let (lat1, long1) = (a.lat.to_radians(), a.long.to_radians());
+        let (lat2, dlong) = (b.lat.to_radians(), (b.long - a.long).to_radians());
+        let bx = lat2.cos() * dlong.cos();
+        let by = lat2.cos() * dlong.sin();
+        let lat = (lat1.sin() + lat2.sin()).atan2(((lat1.cos() + bx).powi(2) + by.powi(2)).sqrt());
+        let long = long1 + by.atan2(lat1.cos() + bx);
+        Coords {
+            lat: lat.to_degrees(),
+            long: long.to_degrees(),
+        }
+    }
+
+    #[test]
+    fn interpolated_midpoint_is_the_great_circle_midpoint() {
+        let older = bus("58 AC 123", 39.7505, 37.0150);
+        let newer = bus("58AC123", 39.7700, 37.0400);
+        let midpoint = interpolate_position(&older, &newer, 0.5).unwrap();
+        let expected = great_circle_midpoint(&older.coords, &newer.coords);
+        assert!(midpoint.distance_m(&expected) < 1.0, "{midpoint:?}");
+    }
+
+    #[test]
+    fn interpolation_ends_at_the_observations() {
+        let older = bus("58 AC 123", 39.7505, 37.0150);
+        let newer = bus("58 AC 123", 39.7700, 37.0400);
+        assert_eq!(
+            interpolate_position(&older, &newer, 0.0),
+            Some(older.coords.clone())
+        );
+        assert_eq!(
+            interpolate_position(&older, &newer, 1.0),
+            Some(newer.coords.clone())
+        );
+        assert_eq!(
+            interpolate_position(&older, &newer, -0.5),
+            Some(older.coords.clone())
+        );
+        assert_eq!(
+            interpolate_position(&older, &newer, 1.5),
+            Some(newer.coords.clone())
+        );
+    }
+
+    #[test]
+    fn interpolation_needs_the_same_bus() {
+        let older = bus("58 AC 123", 39.7505, 37.0150);
+        let other = bus("58 AB 999", 39.7700, 37.0400);
+        assert_eq!(interpolate_position(&older, &other, 0.5), None);
+        assert_eq!(interpolate_position(&older, &older, f64::NAN), None);
+    }
+
+    #[test]
+    fn geohash_matches_the_reference_vectors() {
+        let jutland = Coords {
+            lat: 57.64911,
+            long: 10.40744,
+        };
+        assert_eq!(jutland.geohash(11), "u4pruydqqvj");
+        assert_eq!(jutland.geohash(0), "");
+
+        let (centre, bounds) = Coords::from_geohash("ezs42").unwrap();
+        assert!((centre.lat - 42.605).abs() < 0.001, "{centre:?}");
+        assert!((centre.long - -5.603).abs() < 0.001, "{centre:?}");
+        assert!(bounds.contains(&Coords {
+            lat: 42.6,
+            long: -5.6
+        }));
+        assert_eq!(Coords::from_geohash("EZS42").unwrap().0, centre);
+    }
+
+    #[test]
+    fn geohash_round_trips_at_any_precision() {
+        for i in 0..50 {
+            // Points spread over the globe, including its edges.
+            let coords = Coords {
+                lat: -90.0 + 180.0 * (i as f64 * 0.618_034).fract(),
+                long: -180.0 + 360.0 * (i as f64 * 0.414_214).fract(),
+            };
+            for precision in [1, 3, 5, 7, 9, 12] {
+                let hash = coords.geohash(precision);
+                assert_eq!(hash.len(), precision);
+                let (centre, bounds) = Coords::from_geohash(&hash).unwrap();
+                assert!(bounds.contains(&coords), "{coords:?} not in {hash}");
+                assert_eq!(centre.geohash(precision), hash);
+                assert!(coords.geohash(precision + 1).starts_with(&hash));
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_geohashes_are_rejected() {
+        assert_eq!(Coords::from_geohash(""), Err(GeohashError::Empty));
+        // a, i, l and o aren't in the alphabet.
+        assert_eq!(
+            Coords::from_geohash("sxka"),
+            Err(GeohashError::InvalidChar('a'))
+        );
+        assert_eq!(
+            Coords::from_geohash("sx k"),
+            Err(GeohashError::InvalidChar(' '))
+        );
+    }
+
+    #[test]
+    fn box_geohashes_cover_everything_inside() {
+        let sivas = BoundingBox::new(
+            Coords {
+                lat: 39.70,
+                long: 36.95,
+            },
+            Coords {
+                lat: 39.80,
+                long: 37.10,
+            },
+        );
+        let hashes = sivas.geohashes(5);
+        assert!(hashes.is_sorted());
+        // Cells at 5 are about 0.044° square: 3 rows of 5 here.
+        assert_eq!(hashes.len(), 15, "{hashes:?}");
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let coords = Coords {
+                    lat: 39.70 + 0.01 * i as f64,
+                    long: 36.95 + 0.015 * j as f64,
+                };
+                let hash = coords.geohash(7);
+                assert!(hashes.iter().any(|cell| hash.starts_with(cell)), "{hash}");
+            }
+        }
+        // Every cell listed touches the box.
+        for hash in &hashes {
+            let (_, cell) = Coords::from_geohash(hash).unwrap();
+            assert!(cell.min.lat <= sivas.max.lat && cell.max.lat >= sivas.min.lat);
+            assert!(cell.min.long <= sivas.max.long && cell.max.long >= sivas.min.long);
+        }
+        assert_eq!(sivas.geohashes(0), [""]);
+    }
+
+    fn station(id: i32, lat: f64, long: f64) -> Station {
+        Station {
+            id,
+            human_name: format!("Durak {id}"),
+            coords: Coords { lat, long },
+        }
+    }
+
+    #[test]
+    fn grid_clusters_count_stations_per_cell() {
+        const CELL_M: f64 = 1000.0;
+        let cell_lat = CELL_M / M_PER_DEG_LAT;
+        // Rows of cells from a cell edge near Sivas, placed symmetrically so
+        // the mean latitude, and so the cell width, is known up front.
+        let row = (39.75 / cell_lat).floor();
+        let lat = |cell: f64| (row + cell) * cell_lat;
+        let lats = [0.2, 0.4, 0.6, 0.8, 1.3, 1.7, 2.5];
+        let mean_lat = lats.iter().map(|&cell| lat(cell)).sum::<f64>() / lats.len() as f64;
+        let cell_long = CELL_M / (M_PER_DEG_LAT * mean_lat.to_radians().cos());
+        let column = (37.0 / cell_long).floor();
+        let long = |cell: f64| (column + cell) * cell_long;
+
+        // Four stations in the first cell, two in the one north-east of it
+        // and one two rows up.
+        let stations = [
+            station(1, lat(0.2), long(0.2)),
+            station(2, lat(0.4), long(0.8)),
+            station(3, lat(0.6), long(0.4)),
+            station(4, lat(0.8), long(0.6)),
+            station(5, lat(1.3), long(1.5)),
+            station(6, lat(1.7), long(1.5)),
+            station(7, lat(2.5), long(0.5)),
+        ];
+        let clusters = grid_cluster_stations(&stations, CELL_M);
+
+        assert_eq!(clusters.len(), 3, "{clusters:#?}");
+        let StationCluster::Cluster {
+            centroid,
+            station_ids,
+            count,
+        } = &clusters[0]
+        else {
+            panic!("{clusters:#?}");
+        };
+        assert_eq!(
+            (station_ids.as_slice(), *count),
+            ([1, 2, 3, 4].as_slice(), 4)
+        );
+        assert!(
+            centroid.distance_m(&Coords {
+                lat: lat(0.5),
+                long: long(0.5)
+            }) < 0.01
+        );
+        let StationCluster::Cluster {
+            centroid,
+            station_ids,
+            count,
+        } = &clusters[1]
+        else {
+            panic!("{clusters:#?}");
+        };
+        assert_eq!((station_ids.as_slice(), *count), ([5, 6].as_slice(), 2));
+        assert!(
+            centroid.distance_m(&Coords {
+                lat: lat(1.5),
+                long: long(1.5)
+            }) < 0.01
+        );
+        assert_eq!(clusters[2], StationCluster::Station(stations[6].clone()));
+
+        // A cell a tenth the size separates every station.
+        let apart = grid_cluster_stations(&stations, CELL_M / 10.0);
+        assert_eq!(apart.len(), stations.len());
+        assert!(
+            apart
+                .iter()
+                .all(|cluster| matches!(cluster, StationCluster::Station(_)))
+        );
+    }
+
+    #[test]
+    fn zoom_levels_halve_the_cell_size() {
+        let at_10 = cell_size_for_zoom(10, 39.75);
+        assert!((at_10 - 7052.2).abs() < 0.1, "{at_10}");
+        assert!((cell_size_for_zoom(11, 39.75) - at_10 / 2.0).abs() < 1e-9);
+        assert!((cell_size_for_zoom(0, 0.0) - 60.0 * EQUATOR_M_PER_PX).abs() < 1e-6);
+
+        let stations = [
+            station(10, 39.7505, 37.0150),
+            station(11, 39.7520, 37.0180),
+            station(12, 39.7700, 37.0400),
+            station(13, 39.7000, 37.0300),
+        ];
+        let markers = |zoom| cluster_stations_for_zoom(&stations, zoom).len();
+        assert_eq!(markers(8), 1);
+        assert_eq!(markers(11), 3);
+        assert_eq!(markers(16), 4);
+        assert!(cluster_stations_for_zoom(&[], 11).is_empty());
+    }
+
+    #[test]
+    fn clusters_serialize_tagged() {
+        let clusters = grid_cluster_stations(
+            &[station(10, 39.7505, 37.0150), station(11, 39.7505, 37.0150)],
+            500.0,
+        );
+        assert_eq!(
+            serde_json::to_value(&clusters).unwrap(),
+            serde_json::json!([{
+                "type": "cluster",
+                "centroid": {"lat": 39.7505, "long": 37.015},
+                "station_ids": [10, 11],
+                "count": 2,
+            }])
+        );
+    }
+}
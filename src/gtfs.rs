@@ -0,0 +1,210 @@
+//! Export of the static network as a GTFS feed, see
+//! [`NetworkSnapshot::to_gtfs`].
+
+use chrono::{Months, NaiveTime, TimeDelta};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::{Coords, NetworkSnapshot, SITE_ROOT};
+
+const AGENCY_ID: &str = "sivas";
+const SERVICE_ID: &str = "daily";
+// GTFS route_type for buses.
+const ROUTE_TYPE_BUS: &str = "3";
+/// When every trip is made to leave its first stop, as a placeholder.
+const FIRST_DEPARTURE: NaiveTime = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
+/// Average bus speed for the placeholder time of a trip's last stop.
+const AVERAGE_SPEED_KMH: f64 = 20.0;
+
+/// The placeholder time a trip through `stops` reaches its last one, from
+/// the straight-line length of the route at [`AVERAGE_SPEED_KMH`], in whole
+/// minutes and at least one.
+fn last_arrival(stops: &[&Coords]) -> NaiveTime {
+    let length_m: f64 = stops
+        .windows(2)
+        .map(|pair| pair[0].distance_m(pair[1]))
+        .sum();
+    let minutes = (length_m / 1000.0 / AVERAGE_SPEED_KMH * 60.0)
+        .ceil()
+        .max(1.0);
+    FIRST_DEPARTURE + TimeDelta::minutes(minutes as i64)
+}
+
+fn writer(dir: &Path, name: &str) -> io::Result<csv::Writer<std::fs::File>> {
+    Ok(csv::Writer::from_path(dir.join(name))?)
+}
+
+impl NetworkSnapshot {
+    /// Writes the static network as a GTFS feed into `dir`.
+    ///
+    /// The site only publishes stops, lines and the station order of each
+    /// line, so the feed is incomplete:
+    ///
+    /// - `agency.txt` has a single agency for the municipality.
+    /// - `stops.txt` has every station. `stop_code`, `stop_desc` and
+    ///   `zone_id` are blank.
+    /// - `routes.txt` has one bus route per line. `route_desc`, `route_url`,
+    ///   `route_color` and `route_text_color` are blank.
+    /// - `calendar.txt` has a single `daily` service running every day for a
+    ///   year from [`NetworkSnapshot::taken_at`], since service days aren't
+    ///   published.
+    /// - `trips.txt` has one trip per line with at least two known stations,
+    ///   using the line id as the trip id. `trip_headsign` and
+    ///   `direction_id` are blank.
+    /// - `stop_times.txt` lists the stations of each trip in route order.
+    ///   Stations missing from [`NetworkSnapshot::stations`] are left out.
+    ///   The timetables don't say when a bus reaches each stop, but GTFS
+    ///   requires times at the first and last stop, so those get
+    ///   placeholders: 06:00 at the first, and at the last the time the
+    ///   straight-line route takes at 20 km/h. The stops in between have
+    ///   blank times. Every row has `timepoint` 0, marking its times as
+    ///   approximate.
+    ///
+    /// Lines without station order, e.g. those that failed while the
+    /// snapshot was taken, get a route but no trip.
+    pub fn to_gtfs(&self, dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+
+        let mut agency = writer(dir, "agency.txt")?;
+        agency.write_record(["agency_id", "agency_name", "agency_url", "agency_timezone"])?;
+        agency.write_record([AGENCY_ID, "Sivas Belediyesi", SITE_ROOT, "Europe/Istanbul"])?;
+        agency.flush()?;
+
+        let mut stops = writer(dir, "stops.txt")?;
+        stops.write_record([
+            "stop_id",
+            "stop_code",
+            "stop_name",
+            "stop_desc",
+            "stop_lat",
+            "stop_lon",
+            "zone_id",
+        ])?;
+        for station in &self.stations {
+            stops.write_record([
+                station.id.to_string(),
+                String::new(),
+                station.human_name.clone(),
+                String::new(),
+                station.coords.lat.to_string(),
+                station.coords.long.to_string(),
+                String::new(),
+            ])?;
+        }
+        stops.flush()?;
+
+        let mut routes = writer(dir, "routes.txt")?;
+        routes.write_record([
+            "route_id",
+            "agency_id",
+            "route_short_name",
+            "route_long_name",
+            "route_desc",
+            "route_type",
+            "route_url",
+            "route_color",
+            "route_text_color",
+        ])?;
+        for line in &self.lines {
+            routes.write_record([
+                &line.id,
+                AGENCY_ID,
+                &line.id,
+                &line.human_name,
+                "",
+                ROUTE_TYPE_BUS,
+                "",
+                "",
+                "",
+            ])?;
+        }
+        routes.flush()?;
+
+        let start = self.taken_at.date_naive();
+        let end = start + Months::new(12);
+        let mut calendar = writer(dir, "calendar.txt")?;
+        calendar.write_record([
+            "service_id",
+            "monday",
+            "tuesday",
+            "wednesday",
+            "thursday",
+            "friday",
+            "saturday",
+            "sunday",
+            "start_date",
+            "end_date",
+        ])?;
+        calendar.write_record([
+            SERVICE_ID,
+            "1",
+            "1",
+            "1",
+            "1",
+            "1",
+            "1",
+            "1",
+            &start.format("%Y%m%d").to_string(),
+            &end.format("%Y%m%d").to_string(),
+        ])?;
+        calendar.flush()?;
+
+        let known: HashMap<i32, &Coords> = self
+            .stations
+            .iter()
+            .map(|station| (station.id, &station.coords))
+            .collect();
+        let mut trips = writer(dir, "trips.txt")?;
+        trips.write_record([
+            "route_id",
+            "service_id",
+            "trip_id",
+            "trip_headsign",
+            "direction_id",
+        ])?;
+        let mut stop_times = writer(dir, "stop_times.txt")?;
+        stop_times.write_record([
+            "trip_id",
+            "arrival_time",
+            "departure_time",
+            "stop_id",
+            "stop_sequence",
+            "timepoint",
+        ])?;
+        for line in &self.lines {
+            let Some(stations) = self.line_stations.get(&line.id) else {
+                continue;
+            };
+            let stops: Vec<(i32, &Coords)> = stations
+                .iter()
+                .filter_map(|id| Some((*id, *known.get(id)?)))
+                .collect();
+            if stops.len() < 2 {
+                continue;
+            }
+            trips.write_record([&line.id, SERVICE_ID, &line.id, "", ""])?;
+
+            let coords: Vec<&Coords> = stops.iter().map(|(_, coords)| *coords).collect();
+            let first = FIRST_DEPARTURE.format("%H:%M:%S").to_string();
+            let last = last_arrival(&coords).format("%H:%M:%S").to_string();
+            for (sequence, (id, _)) in stops.iter().enumerate() {
+                let time = match sequence {
+                    0 => first.clone(),
+                    _ if sequence == stops.len() - 1 => last.clone(),
+                    _ => String::new(),
+                };
+                stop_times.write_record([
+                    line.id.clone(),
+                    time.clone(),
+                    time,
+                    id.to_string(),
+                    (sequence + 1).to_string(),
+                    "0".to_string(),
+                ])?;
+            }
+        }
+        trips.flush()?;
+        stop_times.flush()
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Deserialize;
+
+use crate::{NetworkSnapshot, SNAPSHOT_VERSION, default_snapshot_version};
+
+#[derive(Deserialize)]
+struct Version {
+    #[serde(default = "default_snapshot_version")]
+    version: u32,
+}
+
+pub async fn save_snapshot(snapshot: &NetworkSnapshot, path: &Path) -> std::io::Result<()> {
+    write_atomic(path, serde_json::to_vec(snapshot)?).await
+}
+
+/// Tells apart the temporary files of writes running at the same time.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Writes `contents` to a temporary file next to `path` and renames it over
+/// `path`, so that readers never see a partly written file.
+///
+/// The temporary name has the process id and a counter in it, so concurrent
+/// writes to the same path, from this process or another, don't clobber each
+/// other's halves.
+pub(crate) async fn write_atomic(path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = match tokio::fs::write(&temp_path, contents).await {
+        Ok(()) => tokio::fs::rename(&temp_path, path).await,
+        Err(error) => Err(error),
+    };
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    result
+}
+
+/// Loads a snapshot saved with [`save_snapshot`], failing with
+/// [`std::io::ErrorKind::InvalidData`] if it's corrupt or from an
+/// unsupported version.
+pub async fn load_snapshot(path: &Path) -> std::io::Result<NetworkSnapshot> {
+    let json = tokio::fs::read(path).await?;
+    let Version { version } = serde_json::from_slice(&json)?;
+    if version != SNAPSHOT_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unsupported snapshot version {version}, expected {SNAPSHOT_VERSION}"),
+        ));
+    }
+    Ok(serde_json::from_slice(&json)?)
+}
@@ -1,10 +1,80 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use futures::{Stream, StreamExt, future, stream};
 use regex::Regex;
+use reqwest::{StatusCode, header};
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use thiserror::Error;
+use tokio::sync::OwnedSemaphorePermit;
+
+use crate::metrics::Metrics;
+use crate::rate_limit::{HostLimits, TokenBucket};
+use crate::session::CookieLog;
+
+pub mod alerts;
+pub mod analytics;
+#[doc(hidden)]
+pub mod benchmark;
+pub mod bunching;
+pub mod config;
+#[cfg(feature = "csv")]
+pub mod csv_log;
+pub mod diff;
+pub mod display;
+#[cfg(feature = "envelope")]
+pub mod envelope;
+pub mod eta;
+pub mod event;
+#[cfg(any(feature = "geojson", feature = "kml"))]
+pub mod export;
+pub mod favorites;
+#[cfg(feature = "uniffi")]
+pub mod ffi;
+pub mod filter;
+pub mod geo;
+#[cfg(feature = "gtfs-static")]
+pub mod gtfs;
+pub mod io;
+pub mod media;
+pub mod metrics;
+#[cfg(feature = "sqlite")]
+pub mod persistence;
+#[cfg(feature = "qr")]
+pub mod qr;
+pub mod rate_limit;
+pub mod recommend;
+pub mod report;
+pub mod serialise;
+pub mod service;
+pub mod session;
+pub mod timetable;
+pub mod tracker;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod watch;
+#[cfg(feature = "server")]
+pub mod web;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
 
 const SITE_ROOT: &str = "https://ulasim.sivas.bel.tr";
+const DEFAULT_HOST_CONNECTIONS: usize = 2;
+/// Lines whose buses are fetched at once to tell which line a station's
+/// arrivals are on.
+const LINE_LOOKUP_CONCURRENCY: usize = 4;
+/// Wait before the first retry of a 429 without a `Retry-After` header,
+/// doubled for each retry after it.
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(2);
+
+const DRY_RUN_DOCUMENT: &str = r#"<input name="__RequestVerificationToken" value="dry-run">
+<script>var duraks = []; var data = { hgID: 0 };</script>"#;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -20,22 +90,123 @@ pub enum Error {
     NoToken,
     #[error("stations not found")]
     NoStations,
-    #[error("line id not found")]
-    NoLineId,
+    #[error(
+        "line id not found{}",
+        redirect.as_ref().map(|url| format!(", redirected to {url}")).unwrap_or_default()
+    )]
+    NoLineId { redirect: Option<String> },
+    #[error("circuit breaker opened after {failures} consecutive line failures")]
+    CircuitOpen { failures: usize },
+    #[error("request timed out")]
+    Timeout,
+    #[error("schedule not found")]
+    NoSchedule,
+    #[error("retry budget exhausted")]
+    RetryBudgetExhausted(#[source] Box<Error>),
+    #[error("invalid DNS override for {host}: {reason}")]
+    InvalidResolve { host: String, reason: &'static str },
+    #[error("{failed} of {total} entries failed to parse")]
+    TooManyParseFailures { failed: usize, total: usize },
+    #[error(
+        "rate limited by the site{}",
+        retry_after.map(|delay| format!(", retry after {}s", delay.as_secs())).unwrap_or_default()
+    )]
+    RateLimited { retry_after: Option<Duration> },
+    #[error(
+        "unexpected content type {}",
+        content_type.as_deref().unwrap_or("(none)")
+    )]
+    UnexpectedContentType { content_type: Option<String> },
+    #[error("download is larger than {limit} bytes")]
+    MediaTooLarge { limit: usize },
+    #[error("invalid proxy URL: {reason}")]
+    InvalidProxy { reason: &'static str },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Network,
+    Parse,
+    TokenMissing,
+    NotFound,
+    Unavailable,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Network => "network",
+            ErrorKind::Parse => "parse",
+            ErrorKind::TokenMissing => "token_missing",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Unavailable => "unavailable",
+        }
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Request(_)
+            | Error::Timeout
+            | Error::InvalidResolve { .. }
+            | Error::InvalidProxy { .. } => ErrorKind::Network,
+            Error::Json(_)
+            | Error::StationError(_)
+            | Error::LineBusError(_)
+            | Error::TooManyParseFailures { .. }
+            | Error::UnexpectedContentType { .. }
+            | Error::MediaTooLarge { .. } => ErrorKind::Parse,
+            Error::NoToken => ErrorKind::TokenMissing,
+            Error::NoStations | Error::NoLineId { .. } | Error::NoSchedule => ErrorKind::NotFound,
+            Error::CircuitOpen { .. }
+            | Error::RetryBudgetExhausted(_)
+            | Error::RateLimited { .. } => ErrorKind::Unavailable,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Request(_) => "request",
+            Error::Json(_) => "json",
+            Error::StationError(_) => "station",
+            Error::LineBusError(_) => "line_bus",
+            Error::NoToken => "no_token",
+            Error::NoStations => "no_stations",
+            Error::NoLineId { .. } => "no_line_id",
+            Error::CircuitOpen { .. } => "circuit_open",
+            Error::Timeout => "timeout",
+            Error::NoSchedule => "no_schedule",
+            Error::RetryBudgetExhausted(_) => "retry_budget_exhausted",
+            Error::InvalidResolve { .. } => "invalid_resolve",
+            Error::TooManyParseFailures { .. } => "too_many_parse_failures",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::UnexpectedContentType { .. } => "unexpected_content_type",
+            Error::MediaTooLarge { .. } => "media_too_large",
+            Error::InvalidProxy { .. } => "invalid_proxy",
+        }
+    }
 }
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Coords {
     pub lat: f64,
     pub long: f64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LineBus {
     pub license_plate: String,
     pub coords: Coords,
+    /// Time of the vehicle's last GPS report.
+    ///
+    /// The `aractekrar` feed has not been seen carrying a timestamp so far,
+    /// in which case this stays `None`.
+    #[serde(default)]
+    pub last_update: Option<DateTime<Utc>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -46,6 +217,8 @@ struct LineBusDto {
     lat: String,
     #[serde(rename = "mevcutlng")]
     long: String,
+    #[serde(rename = "sonGuncelleme", default)]
+    last_update: Option<serde_json::Value>,
 }
 
 #[derive(Error, Debug)]
@@ -62,22 +235,170 @@ impl TryFrom<LineBusDto> for LineBus {
                 lat: dto.lat.parse()?,
                 long: dto.long.parse()?,
             },
+            last_update: dto.last_update.as_ref().and_then(parse_timestamp),
         })
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+fn parse_timestamp(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+    let turkey = FixedOffset::east_opt(3 * 3600)?;
+
+    if let Some(millis) = value.as_i64() {
+        return DateTime::from_timestamp_millis(millis);
+    }
+
+    let text = value.as_str()?.trim();
+    if let Some(millis) = text
+        .strip_prefix("/Date(")
+        .and_then(|rest| rest.strip_suffix(")/"))
+    {
+        return DateTime::from_timestamp_millis(millis.parse().ok()?);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.to_utc());
+    }
+
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%d.%m.%Y %H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .and_then(|time| time.and_local_timezone(turkey).single())
+    .map(|time| time.to_utc())
+}
+
+/// Checks a SOCKS5 proxy URL and adds the credentials to it. The URL isn't
+/// part of the errors as it may hold a password.
+#[cfg(feature = "socks")]
+fn socks5_proxy(url: &str, auth: Option<&(String, String)>) -> Result<reqwest::Proxy> {
+    let invalid = |reason| Error::InvalidProxy { reason };
+    let mut url = reqwest::Url::parse(url).map_err(|_| invalid("not a URL"))?;
+    if !matches!(url.scheme(), "socks5" | "socks5h") {
+        return Err(invalid("scheme isn't socks5 or socks5h"));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(invalid("missing host"));
+    }
+    if url.port().is_none() {
+        return Err(invalid("missing port"));
+    }
+    if let Some((username, password)) = auth {
+        url.set_username(username)
+            .and_then(|()| url.set_password(Some(password)))
+            .map_err(|()| invalid("can't hold credentials"))?;
+    }
+    Ok(reqwest::Proxy::all(url)?)
+}
+
+/// How long a `Retry-After` header asks to wait, given in seconds or as a
+/// date. Dates in the past mean no wait.
+fn parse_retry_after(headers: &header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.to_utc() - Utc::now()).to_std().unwrap_or_default())
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct StationBus {
     pub license_plate: String,
     pub arrive_time: Duration,
 }
 
+/// Orders by arrival time, then by license plate.
+impl Ord for StationBus {
+    fn cmp(&self, other: &StationBus) -> Ordering {
+        self.arrive_time
+            .cmp(&other.arrive_time)
+            .then_with(|| self.license_plate.cmp(&other.license_plate))
+    }
+}
+
+impl PartialOrd for StationBus {
+    fn partial_cmp(&self, other: &StationBus) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub fn sort_arrivals(buses: &mut [StationBus]) {
+    buses.sort();
+}
+
+pub fn filter_arrivals_within(buses: Vec<StationBus>, window: Duration) -> Vec<StationBus> {
+    buses
+        .into_iter()
+        .filter(|bus| bus.arrive_time <= window)
+        .collect()
+}
+
+/// A station's arrival with the line the bus is running on, if known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnrichedArrival<'a> {
+    pub bus: &'a StationBus,
+    pub line: Option<&'a Line>,
+}
+
+/// Pairs arrivals with their lines from `line_map`, keyed by license plate,
+/// e.g. as gathered from [`Client::get_line_buses`] for the lines stopping
+/// at the station. Plates are compared with [`normalize_plate`].
+pub fn enrich_with_line_name<'a>(
+    buses: &'a [StationBus],
+    line_map: &'a HashMap<String, Line>,
+) -> Vec<EnrichedArrival<'a>> {
+    let by_plate: HashMap<String, &Line> = line_map
+        .iter()
+        .map(|(plate, line)| (normalize_plate(plate), line))
+        .collect();
+    buses
+        .iter()
+        .map(|bus| EnrichedArrival {
+            bus,
+            line: by_plate.get(&normalize_plate(&bus.license_plate)).copied(),
+        })
+        .collect()
+}
+
+/// Uppercases a license plate and drops spaces and dashes, so "58 ac-123"
+/// and "58AC123" compare equal.
+pub fn normalize_plate(plate: &str) -> String {
+    plate
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct StationBusDto {
     #[serde(rename = "plaka")]
     license_plate: String,
     #[serde(rename = "sure")]
     arrive_time_mins: u64,
+    /// The line the bus is on. The board doesn't always send this.
+    #[serde(
+        default,
+        rename = "hatNo",
+        alias = "hat",
+        deserialize_with = "deserialize_line_code"
+    )]
+    line: Option<String>,
+}
+
+/// A line code sent as a string or a number, with blanks as missing.
+fn deserialize_line_code<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let code = match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(serde_json::Value::String(code)) => code.trim().to_string(),
+        Some(serde_json::Value::Number(code)) => code.to_string(),
+        _ => return Ok(None),
+    };
+    Ok((!code.is_empty()).then_some(code))
 }
 
 impl From<StationBusDto> for StationBus {
@@ -89,13 +410,47 @@ impl From<StationBusDto> for StationBus {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A station's id, as in its `/Akilli-Durak/{id}` page.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct StationId(pub i32);
+
+impl From<i32> for StationId {
+    fn from(id: i32) -> StationId {
+        StationId(id)
+    }
+}
+
+impl From<StationId> for i32 {
+    fn from(id: StationId) -> i32 {
+        id.0
+    }
+}
+
+impl std::fmt::Display for StationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Station {
     pub id: i32,
     pub human_name: String,
     pub coords: Coords,
 }
 
+impl Station {
+    /// The station's live arrivals page on the site at `base_url`.
+    pub fn page_url(&self, base_url: &str) -> String {
+        format!(
+            "{}/Akilli-Durak/{}",
+            base_url.trim_end_matches('/'),
+            self.id
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct StationDto {
     #[serde(rename = "linko")]
@@ -138,55 +493,659 @@ impl TryFrom<StationDto> for Station {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Line {
     pub id: String,
     pub human_name: String,
 }
 
-pub struct Client(reqwest::Client);
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DryRunRequest {
+    pub method: String,
+    pub path: String,
+    pub params: Vec<(String, String)>,
+}
+
+/// The [`NetworkSnapshot::version`] this crate writes and reads.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+fn default_snapshot_version() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NetworkSnapshot {
+    /// Format version, snapshots from before it was added count as 1.
+    #[serde(default = "default_snapshot_version")]
+    pub version: u32,
+    pub taken_at: DateTime<Utc>,
+    pub lines: Vec<Line>,
+    pub stations: Vec<Station>,
+    pub buses: HashMap<String, Vec<LineBus>>,
+    /// Station ids of each line, in route order.
+    #[serde(default)]
+    pub line_stations: HashMap<String, Vec<i32>>,
+    pub failed_lines: Vec<String>,
+}
+
+/// How [`Client::get_station_buses_for_lines`] told which line each
+/// arrival is on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineFilterStrategy {
+    /// The board sent the line of every arrival.
+    BoardLines,
+    /// The board didn't, so the plates were looked up among the lines'
+    /// buses.
+    PlateLookup,
+}
+
+/// A station's arrivals on some lines, see
+/// [`Client::get_station_buses_for_lines`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LineArrivals {
+    pub buses: Vec<StationBus>,
+    pub strategy: LineFilterStrategy,
+    /// Lines whose buses couldn't be fetched for a
+    /// [`LineFilterStrategy::PlateLookup`], so arrivals on them are missing.
+    pub failed_lines: Vec<String>,
+}
+
+/// The stations of several lines, see [`Client::get_stations_for_lines`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LinesStations {
+    /// Every station on any of the lines, once, in the order they first
+    /// appear.
+    pub stations: Vec<Station>,
+    /// The lines that stop at each station, in the order they were given.
+    pub lines_by_station: HashMap<i32, Vec<String>>,
+    pub failed_lines: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FoundBus {
+    pub line: String,
+    pub bus: LineBus,
+}
+
+/// Progress through a fetch that goes line by line, reported after each line.
+#[derive(Debug)]
+pub struct LineProgress<'a> {
+    /// Lines done so far, this one included.
+    pub done: usize,
+    pub total: usize,
+    pub line: &'a str,
+    /// How many buses are on the line, `None` if buses weren't fetched, or
+    /// why the line failed.
+    pub result: std::result::Result<Option<usize>, &'a Error>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionalResult<T> {
+    /// The server answered 304 Not Modified, the previous value is current.
+    NotModified,
+    /// The new value and its ETag, if the server sent one.
+    Updated(T, Option<String>),
+}
+
+pub struct ClientBuilder {
+    base_url: String,
+    request_delay: Duration,
+    timeout: Option<Duration>,
+    circuit_breaker_threshold: Option<usize>,
+    retry_budget: Option<(u32, Duration)>,
+    rate_limit_retries: u32,
+    rate_limiter: Option<TokenBucket>,
+    host_connections: usize,
+    max_parse_failure_ratio: Option<f64>,
+    resolve: Vec<(String, SocketAddr)>,
+    connect_to: Option<SocketAddr>,
+    dry_run: bool,
+    token_ttl: Duration,
+    token_refresh_lead: Duration,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+    #[cfg(feature = "brotli")]
+    brotli: bool,
+    #[cfg(feature = "socks")]
+    socks5_proxy: Option<String>,
+    #[cfg(feature = "socks")]
+    socks5_auth: Option<(String, String)>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> ClientBuilder {
+        ClientBuilder {
+            base_url: SITE_ROOT.to_string(),
+            request_delay: Duration::from_millis(200),
+            timeout: None,
+            circuit_breaker_threshold: None,
+            retry_budget: None,
+            rate_limit_retries: 0,
+            rate_limiter: None,
+            host_connections: DEFAULT_HOST_CONNECTIONS,
+            max_parse_failure_ratio: None,
+            resolve: Vec::new(),
+            connect_to: None,
+            dry_run: false,
+            token_ttl: Duration::from_secs(20 * 60),
+            token_refresh_lead: Duration::from_secs(30),
+            #[cfg(feature = "gzip")]
+            gzip: true,
+            #[cfg(feature = "brotli")]
+            brotli: true,
+            #[cfg(feature = "socks")]
+            socks5_proxy: None,
+            #[cfg(feature = "socks")]
+            socks5_auth: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> ClientBuilder {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn request_delay(mut self, delay: Duration) -> ClientBuilder {
+        self.request_delay = delay;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn circuit_breaker_threshold(mut self, threshold: usize) -> ClientBuilder {
+        self.circuit_breaker_threshold = Some(threshold);
+        self
+    }
+
+    /// Asks for gzip compressed responses and decompresses them, which makes
+    /// the page with all stations several times smaller. On by default.
+    #[cfg(feature = "gzip")]
+    pub fn gzip(mut self, enable: bool) -> ClientBuilder {
+        self.gzip = enable;
+        self
+    }
+
+    /// Like [`ClientBuilder::gzip`], for brotli. On by default.
+    #[cfg(feature = "brotli")]
+    pub fn brotli(mut self, enable: bool) -> ClientBuilder {
+        self.brotli = enable;
+        self
+    }
+
+    /// Sends every request through a SOCKS5 proxy, given as a URL like
+    /// `socks5://127.0.0.1:1080`. With `socks5h://` host names are resolved
+    /// by the proxy, so [`ClientBuilder::resolve`] doesn't apply. A username
+    /// and password can be part of the URL or set with
+    /// [`ClientBuilder::socks5_proxy_auth`].
+    ///
+    /// [`ClientBuilder::build`] fails with [`Error::InvalidProxy`] if the URL
+    /// isn't a `socks5` or `socks5h` URL with a host and port.
+    ///
+    /// Needs the `socks` cargo feature.
+    ///
+    /// ```
+    /// use sivasbus::{Client, Error};
+    ///
+    /// let client = Client::builder()
+    ///     .socks5_proxy("socks5h://127.0.0.1:1080")
+    ///     .socks5_proxy_auth("user", "secret")
+    ///     .build()?;
+    ///
+    /// let missing_port = Client::builder()
+    ///     .socks5_proxy("socks5://127.0.0.1")
+    ///     .build();
+    /// assert!(matches!(missing_port, Err(Error::InvalidProxy { .. })));
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[cfg(feature = "socks")]
+    pub fn socks5_proxy(mut self, url: impl Into<String>) -> ClientBuilder {
+        self.socks5_proxy = Some(url.into());
+        self
+    }
+
+    /// Authenticates to the [`ClientBuilder::socks5_proxy`] with a username
+    /// and password, overriding any in its URL.
+    #[cfg(feature = "socks")]
+    pub fn socks5_proxy_auth(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> ClientBuilder {
+        self.socks5_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Caps retries across the client and all its clones to `max_retries`
+    /// every `per`. Once the budget is spent, calls that would retry fail
+    /// with [`Error::RetryBudgetExhausted`] instead. Unlimited by default.
+    pub fn retry_budget(mut self, max_retries: u32, per: Duration) -> ClientBuilder {
+        self.retry_budget = Some((max_retries, per));
+        self
+    }
+
+    /// Retries requests the site answers with 429 Too Many Requests up to
+    /// `retries` times, waiting as long as its `Retry-After` header says, or
+    /// 2 seconds doubling with each retry without one. Retries come out of
+    /// the [`ClientBuilder::retry_budget`]. Off by default, so that a 429
+    /// fails with [`Error::RateLimited`].
+    pub fn retry_rate_limited(mut self, retries: u32) -> ClientBuilder {
+        self.rate_limit_retries = retries;
+        self
+    }
+
+    /// How long a page's verification token is expected to stay valid.
+    pub fn token_ttl(mut self, ttl: Duration) -> ClientBuilder {
+        self.token_ttl = ttl;
+        self
+    }
+
+    /// How long before the expected expiry watch streams refresh a token in
+    /// the background.
+    pub fn token_refresh_lead(mut self, lead: Duration) -> ClientBuilder {
+        self.token_refresh_lead = lead;
+        self
+    }
+
+    /// Takes a token from `token_bucket` before every request. The bucket
+    /// can be shared with other clients to limit them together.
+    pub fn rate_limiter(mut self, token_bucket: TokenBucket) -> ClientBuilder {
+        self.rate_limiter = Some(token_bucket);
+        self
+    }
+
+    /// How many requests the client and its clones may have in flight to
+    /// one host at a time, however many calls run concurrently. Defaults to
+    /// 2 to go easy on the municipality's server; raise it with care, and
+    /// values below 1 count as 1.
+    pub fn max_host_connections(mut self, connections: usize) -> ClientBuilder {
+        self.host_connections = connections;
+        self
+    }
+
+    /// Skips stations and buses that fail to parse as long as they're at
+    /// most `ratio` of the entries, say 0.05 for 5%, and fails with
+    /// [`Error::TooManyParseFailures`] above that, as the site has likely
+    /// changed. By default a single bad entry fails the whole call.
+    pub fn max_parse_failure_ratio(mut self, ratio: f64) -> ClientBuilder {
+        self.max_parse_failure_ratio = Some(ratio);
+        self
+    }
+
+    /// Connects to `addr` instead of resolving `host`, which must be the
+    /// host of the base URL. Other hosts, such as redirect targets, are
+    /// still resolved normally. A port of 0 means the URL's port. Calling it
+    /// again for the same host adds addresses to try in order.
+    ///
+    /// [`ClientBuilder::build`] fails with [`Error::InvalidResolve`] if the
+    /// host isn't the base URL's or the address is unspecified.
+    pub fn resolve(mut self, host: impl Into<String>, addr: SocketAddr) -> ClientBuilder {
+        self.resolve.push((host.into(), addr));
+        self
+    }
+
+    /// Connects to `addr` for every host, e.g. a local stub serving
+    /// recorded pages, while still sending the real host name in the `Host`
+    /// header and for TLS. Addresses set with [`ClientBuilder::resolve`]
+    /// take precedence.
+    ///
+    /// Whoever runs `addr` sees all the client's traffic, including its
+    /// cookies, and redirects to other sites end up there too. Over HTTPS
+    /// the server still needs a certificate for the real host, so don't
+    /// work around certificate errors to make this work against a stub;
+    /// give the stub an `http://` base URL instead.
+    ///
+    /// [`ClientBuilder::build`] fails with [`Error::InvalidResolve`] if the
+    /// address is unspecified.
+    pub fn danger_connect_to(mut self, addr: SocketAddr) -> ClientBuilder {
+        self.connect_to = Some(addr);
+        self
+    }
+
+    /// Records requests instead of sending them, see [`Client::dry_run_log`].
+    ///
+    /// Every fetch method succeeds with empty results in this mode, so parsed
+    /// outputs carry no data.
+    pub fn dry_run(mut self, dry_run: bool) -> ClientBuilder {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let cookies = Arc::new(CookieLog::default());
+        let mut http = reqwest::Client::builder().cookie_provider(cookies.clone());
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        #[cfg(feature = "gzip")]
+        {
+            http = http.gzip(self.gzip);
+        }
+        #[cfg(feature = "brotli")]
+        {
+            http = http.brotli(self.brotli);
+        }
+        #[cfg(feature = "socks")]
+        if let Some(proxy) = &self.socks5_proxy {
+            http = http.proxy(socks5_proxy(proxy, self.socks5_auth.as_ref())?);
+        }
+        let base_host = reqwest::Url::parse(&self.base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        let mut resolve: Vec<(&str, Vec<SocketAddr>)> = Vec::new();
+        for (host, addr) in &self.resolve {
+            let invalid = |reason| Error::InvalidResolve {
+                host: host.clone(),
+                reason,
+            };
+            if !base_host
+                .as_ref()
+                .is_some_and(|base| base.eq_ignore_ascii_case(host))
+            {
+                return Err(invalid("not the host of the base URL"));
+            }
+            if addr.ip().is_unspecified() {
+                return Err(invalid("unspecified address"));
+            }
+            match resolve
+                .iter_mut()
+                .find(|(known, _)| known.eq_ignore_ascii_case(host))
+            {
+                Some((_, addrs)) => addrs.push(*addr),
+                None => resolve.push((host, vec![*addr])),
+            }
+        }
+        for (host, addrs) in resolve {
+            http = http.resolve_to_addrs(host, &addrs);
+        }
+        if let Some(addr) = self.connect_to {
+            if addr.ip().is_unspecified() {
+                return Err(Error::InvalidResolve {
+                    host: "every host".to_string(),
+                    reason: "unspecified address",
+                });
+            }
+            http = http.dns_resolver(Arc::new(ConnectTo(addr)));
+        }
+
+        Ok(Client {
+            http: http.build()?,
+            cookies,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            request_delay: self.request_delay,
+            timeout: self.timeout,
+            circuit_breaker_threshold: self.circuit_breaker_threshold,
+            retry_budget: self.retry_budget.map(|(max_retries, per)| {
+                let refill = if per.is_zero() {
+                    0.0
+                } else {
+                    max_retries as f32 / per.as_secs_f32()
+                };
+                TokenBucket::with_capacity(max_retries, refill)
+            }),
+            rate_limit_retries: self.rate_limit_retries,
+            rate_limiter: self.rate_limiter,
+            host_limits: Arc::new(HostLimits::new(self.host_connections)),
+            max_parse_failure_ratio: self.max_parse_failure_ratio,
+            dry_run_log: self.dry_run.then(Default::default),
+            token_ttl: self.token_ttl,
+            token_refresh_lead: self.token_refresh_lead,
+            metrics: Default::default(),
+        })
+    }
+}
+
+/// Resolves every host to one address, see
+/// [`ClientBuilder::danger_connect_to`].
+struct ConnectTo(SocketAddr);
+
+impl reqwest::dns::Resolve for ConnectTo {
+    fn resolve(&self, _: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let addrs: reqwest::dns::Addrs = Box::new(std::iter::once(self.0));
+        Box::pin(std::future::ready(Ok(addrs)))
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    cookies: Arc<CookieLog>,
+    base_url: String,
+    request_delay: Duration,
+    timeout: Option<Duration>,
+    circuit_breaker_threshold: Option<usize>,
+    retry_budget: Option<TokenBucket>,
+    rate_limit_retries: u32,
+    rate_limiter: Option<TokenBucket>,
+    host_limits: Arc<HostLimits>,
+    max_parse_failure_ratio: Option<f64>,
+    dry_run_log: Option<Arc<Mutex<Vec<DryRunRequest>>>>,
+    token_ttl: Duration,
+    token_refresh_lead: Duration,
+    metrics: Arc<Metrics>,
+}
+
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
 
 impl Client {
+    /// A client with the default settings.
+    ///
+    /// Panics if the HTTP client can't be set up, which only happens when
+    /// the platform's TLS backend fails to initialize, e.g. with missing or
+    /// broken system certificates. Use [`ClientBuilder::build`] to get an
+    /// error instead.
     pub fn new() -> Client {
-        Client(
-            reqwest::Client::builder()
-                .cookie_store(true)
-                .build()
-                .unwrap(),
-        )
+        ClientBuilder::new()
+            .build()
+            .expect("Failed to build reqwest client; check your TLS configuration")
     }
 
-    async fn get_document(&self, path: String) -> Result<String, reqwest::Error> {
-        let result = self
-            .0
-            .get(format!("{SITE_ROOT}{path}"))
-            .send()
-            .await?
-            .text()
-            .await?;
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Counts a parse error in the metrics. [`Error::TooManyParseFailures`]
+    /// isn't counted, [`Client::convert`] already counted each failed entry.
+    fn track<T>(&self, result: Result<T>) -> Result<T> {
+        if let Err(error) = &result
+            && error.kind() == ErrorKind::Parse
+            && !matches!(error, Error::TooManyParseFailures { .. })
+        {
+            self.metrics.record_parse_failure();
+        }
+        result
+    }
+
+    /// Converts every entry, or with a [`ClientBuilder::max_parse_failure_ratio`]
+    /// the ones that convert as long as few enough fail.
+    fn convert<T, U>(&self, dtos: Vec<T>) -> Result<Vec<U>>
+    where
+        T: TryInto<U>,
+        Error: From<T::Error>,
+    {
+        let Some(ratio) = self.max_parse_failure_ratio else {
+            return map_try_into(dtos);
+        };
+
+        let total = dtos.len();
+        let converted: Vec<U> = dtos
+            .into_iter()
+            .filter_map(|dto| dto.try_into().ok())
+            .collect();
+        let failed = total - converted.len();
+        for _ in 0..failed {
+            self.metrics.record_parse_failure();
+        }
+        if failed as f64 > ratio * total as f64 {
+            return Err(Error::TooManyParseFailures { failed, total });
+        }
+        Ok(converted)
+    }
+
+    fn parse_stations(&self, doc: &str) -> Result<Vec<Station>> {
+        self.convert(extract_station_dtos(doc)?)
+    }
+
+    /// Takes a retry from the budget, or returns `error` wrapped in
+    /// [`Error::RetryBudgetExhausted`] if there's none left.
+    fn retry_after(&self, error: Error) -> Result<()> {
+        if self
+            .retry_budget
+            .as_ref()
+            .is_some_and(|budget| !budget.try_acquire())
+        {
+            return Err(Error::RetryBudgetExhausted(Box::new(error)));
+        }
+        self.metrics.record_retry();
+        Ok(())
+    }
+
+    /// Waits for the rate limiter and for a free connection to the host of
+    /// `url`, which is held until the permit is dropped.
+    async fn start_request(&self, url: &str) -> OwnedSemaphorePermit {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        self.host_limits.acquire(url).await
+    }
+
+    /// Sends the request built by `request` once a connection is free,
+    /// retrying if the site rate limits it and
+    /// [`ClientBuilder::retry_rate_limited`] allows. Returns the response
+    /// along with the permit for the connection, to hold until the body has
+    /// been read.
+    async fn send(
+        &self,
+        url: &str,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<(reqwest::Response, OwnedSemaphorePermit)> {
+        let mut retries = 0;
+        loop {
+            let permit = self.start_request(url).await;
+            let response = request().send().await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok((response, permit));
+            }
+            drop(permit);
+
+            let retry_after = parse_retry_after(response.headers());
+            let error = Error::RateLimited { retry_after };
+            if retries >= self.rate_limit_retries {
+                return Err(error);
+            }
+            self.retry_after(error)?;
+            tokio::time::sleep(retry_after.unwrap_or(RATE_LIMIT_BACKOFF * 2u32.pow(retries))).await;
+            retries += 1;
+        }
+    }
+
+    async fn throttle(&self) {
+        tokio::time::sleep(self.request_delay).await;
+        self.metrics.record_throttle_wait(self.request_delay);
+    }
 
-        tokio::time::sleep(Duration::from_millis(200)).await;
+    pub fn dry_run_log(&self) -> Vec<DryRunRequest> {
+        self.dry_run_log
+            .as_ref()
+            .map(|log| log.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    fn record_dry_run(&self, method: &str, path: &str, params: &[(&str, &str)]) -> bool {
+        let Some(log) = &self.dry_run_log else {
+            return false;
+        };
 
-        Ok(result)
+        log.lock().unwrap().push(DryRunRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            params: params
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        });
+        true
     }
 
-    async fn post_json<T: for<'de> Deserialize<'de>>(
+    async fn get_document(&self, path: String) -> Result<String> {
+        Ok(self.get_page(path).await?.1)
+    }
+
+    async fn get_page(&self, path: String) -> Result<(String, String)> {
+        let url = format!("{}{path}", self.base_url);
+        if self.record_dry_run("GET", &path, &[]) {
+            return Ok((url, DRY_RUN_DOCUMENT.to_string()));
+        }
+
+        let (response, permit) = self.send(&url, || self.http.get(&url)).await?;
+        let final_url = response.url().to_string();
+        let result = response.text().await?;
+        drop(permit);
+
+        self.metrics.record_get(result.len());
+        self.throttle().await;
+
+        Ok((final_url, result))
+    }
+
+    async fn post_json<T: for<'de> Deserialize<'de> + Default>(
         &self,
         path: &str,
         params: Vec<(&str, &str)>,
-    ) -> Result<T, reqwest::Error> {
-        let result = self
-            .0
-            .post(format!("{SITE_ROOT}{path}"))
-            .form(&params)
-            .send()
-            .await?
-            .json()
-            .await?;
+    ) -> Result<T> {
+        self.post_json_within(path, params, None).await
+    }
 
-        tokio::time::sleep(Duration::from_millis(200)).await;
+    /// Like [`Client::post_json`], failing with [`Error::Timeout`] if
+    /// sending the request and reading the body takes over `timeout`. The
+    /// request delay after it doesn't count.
+    async fn post_json_within<T: for<'de> Deserialize<'de> + Default>(
+        &self,
+        path: &str,
+        params: Vec<(&str, &str)>,
+        timeout: Option<Duration>,
+    ) -> Result<T> {
+        if self.record_dry_run("POST", path, &params) {
+            return Ok(T::default());
+        }
+
+        let url = format!("{}{path}", self.base_url);
+        let fetch = async {
+            let (response, permit) = self
+                .send(&url, || self.http.post(&url).form(&params))
+                .await?;
+            let body = response.bytes().await?;
+            drop(permit);
+            Ok::<_, Error>(body)
+        };
+        let body = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fetch)
+                .await
+                .map_err(|_| Error::Timeout)??,
+            None => fetch.await?,
+        };
+
+        self.metrics.record_post(body.len());
+        self.throttle().await;
 
-        Ok(result)
+        self.track(serde_json::from_slice(&body).map_err(Into::into))
     }
 
     pub async fn get_lines(&self) -> Result<Vec<Line>> {
@@ -198,44 +1157,579 @@ impl Client {
         let doc = self
             .get_document("/Akilli-Duraklar-Harita".to_string())
             .await?;
-        extract_stations(&doc)
+        self.track(self.parse_stations(&doc))
+    }
+
+    /// Like [`Client::get_all_stations`], but sends `previous_etag` in
+    /// `If-None-Match` and skips downloading and parsing the page if the
+    /// server says it hasn't changed.
+    pub async fn get_all_stations_conditional(
+        &self,
+        previous_etag: Option<&str>,
+    ) -> Result<ConditionalResult<Vec<Station>>> {
+        let path = "/Akilli-Duraklar-Harita";
+        if self.record_dry_run("GET", path, &[]) {
+            let stations = self.track(self.parse_stations(DRY_RUN_DOCUMENT))?;
+            return Ok(ConditionalResult::Updated(stations, None));
+        }
+
+        let url = format!("{}{path}", self.base_url);
+        let (response, permit) = self
+            .send(&url, || {
+                let request = self.http.get(&url);
+                match previous_etag {
+                    Some(etag) => request.header(header::IF_NONE_MATCH, etag),
+                    None => request,
+                }
+            })
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            drop(permit);
+            self.metrics.record_get(0);
+            self.throttle().await;
+            return Ok(ConditionalResult::NotModified);
+        }
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let doc = response.text().await?;
+        drop(permit);
+        self.metrics.record_get(doc.len());
+        self.throttle().await;
+
+        let stations = self.track(self.parse_stations(&doc))?;
+        Ok(ConditionalResult::Updated(stations, etag))
+    }
+
+    /// Like [`Client::get_all_stations`], but parses the station array one
+    /// element at a time and yields each station as soon as it's parsed.
+    ///
+    /// The stream ends after the first error.
+    pub fn get_all_stations_stream(&self) -> impl Stream<Item = Result<Station>> + '_ {
+        stream::once(async move {
+            let doc = self
+                .get_document("/Akilli-Duraklar-Harita".to_string())
+                .await?;
+            let range = station_json_range(&doc).ok_or(Error::NoStations)?;
+            Ok(StationArray::new(doc, range))
+        })
+        .flat_map(|result| match result {
+            Ok(array) => stream::iter(array).left_stream(),
+            Err(error) => stream::iter([Err(error)]).right_stream(),
+        })
+        .map(|result| self.track(result))
     }
 
     pub async fn get_stations(&self, line: &str) -> Result<Vec<Station>> {
         let doc = self.get_document(format!("/hat/{line}")).await?;
-        extract_stations(&doc)
+        let stations = self.track(self.parse_stations(&doc))?;
+
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            for warning in geo::validate_station_order(&stations) {
+                tracing::debug!(
+                    line,
+                    idx = warning.idx,
+                    distance_km = warning.distance_km,
+                    "consecutive stations are implausibly far apart"
+                );
+            }
+        }
+        Ok(stations)
+    }
+
+    /// The coordinates of a line's stations in route order, a rough sketch of
+    /// the route with straight segments between stops rather than the roads
+    /// the buses take.
+    pub async fn get_line_stop_polyline(&self, line: &str) -> Result<Vec<Coords>> {
+        let stations = self.get_stations(line).await?;
+        Ok(stations.into_iter().map(|station| station.coords).collect())
+    }
+
+    /// The line's buses inside `polygon`, see [`geo::buses_in_polygon`].
+    pub async fn buses_in_polygon(&self, line: &str, polygon: &[Coords]) -> Result<Vec<LineBus>> {
+        let mut buses = self.get_line_buses(line).await?;
+        buses.retain(|bus| geo::in_polygon(&bus.coords, polygon));
+        Ok(buses)
+    }
+
+    /// Fetches the stations of all `lines` concurrently and merges them.
+    /// Lines that fail are listed in [`LinesStations::failed_lines`], unless
+    /// all of them do, in which case the first line's error is returned.
+    pub async fn get_stations_for_lines(&self, lines: &[&str]) -> Result<LinesStations> {
+        let results = future::join_all(lines.iter().map(|line| self.get_stations(line))).await;
+
+        let mut merged = LinesStations::default();
+        let mut first_error = None;
+        for (line, result) in lines.iter().zip(results) {
+            let stations = match result {
+                Ok(stations) => stations,
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                    merged.failed_lines.push(line.to_string());
+                    continue;
+                }
+            };
+            for station in stations {
+                let served_by = merged.lines_by_station.entry(station.id).or_default();
+                if served_by.is_empty() {
+                    merged.stations.push(station);
+                }
+                if !served_by.iter().any(|served| served == line) {
+                    served_by.push(line.to_string());
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) if merged.failed_lines.len() == lines.len() => Err(error),
+            _ => Ok(merged),
+        }
+    }
+
+    /// The corners of the area covered by the network's stations, see
+    /// [`geo::stations_bounding_box`].
+    pub async fn network_bounds(&self) -> Result<Option<(Coords, Coords)>> {
+        Ok(geo::stations_bounding_box(&self.get_all_stations().await?))
+    }
+
+    /// A line's straight-line length from stop to stop, see
+    /// [`geo::route_length_km`].
+    pub async fn get_line_route_length_km(&self, line: &str) -> Result<f64> {
+        Ok(geo::route_length_km(&self.get_stations(line).await?))
+    }
+
+    /// A line's length in metres. The site publishes no route geometry, so
+    /// this always follows the stops in order, like
+    /// [`Client::get_line_stop_polyline`], and comes out shorter than the
+    /// roads the buses take. Lines with fewer than two stops are 0 long.
+    pub async fn get_line_length(&self, line: &str) -> Result<f64> {
+        Ok(self.get_line_route_length_km(line).await? * 1000.0)
+    }
+
+    /// Looks for a bus on the given lines, fetching up to `concurrency` lines
+    /// at a time and stopping at the first line the bus is on.
+    ///
+    /// Lines that fail to load are skipped.
+    pub async fn find_bus(
+        &self,
+        plate: &str,
+        lines: &[String],
+        concurrency: usize,
+    ) -> Option<FoundBus> {
+        self.find_bus_with_progress(plate, lines, concurrency, |_| {})
+            .await
+    }
+
+    /// Like [`Client::find_bus`], calling `progress` after each line searched.
+    pub async fn find_bus_with_progress(
+        &self,
+        plate: &str,
+        lines: &[String],
+        concurrency: usize,
+        mut progress: impl FnMut(LineProgress),
+    ) -> Option<FoundBus> {
+        let plate = normalize_plate(plate);
+        let mut results = stream::iter(lines)
+            .map(|line| async move { (line, self.get_line_buses(line).await) })
+            .buffered(concurrency.max(1));
+
+        let mut done = 0;
+        while let Some((line, result)) = results.next().await {
+            done += 1;
+            progress(LineProgress {
+                done,
+                total: lines.len(),
+                line,
+                result: result.as_ref().map(|buses| Some(buses.len())),
+            });
+            let Ok(buses) = result else {
+                continue;
+            };
+            if let Some(bus) = buses
+                .into_iter()
+                .find(|bus| normalize_plate(&bus.license_plate) == plate)
+            {
+                return Some(FoundBus {
+                    line: line.clone(),
+                    bus,
+                });
+            }
+        }
+        None
+    }
+
+    /// Groups lines by the name of their first station.
+    ///
+    /// Lines whose stations can't be fetched are left out.
+    pub async fn lines_by_origin(&self) -> Result<HashMap<String, Vec<Line>>> {
+        let mut groups: HashMap<String, Vec<Line>> = HashMap::new();
+        for line in self.get_lines().await? {
+            let Ok(stations) = self.get_stations(&line.id).await else {
+                continue;
+            };
+            if let Some(origin) = stations.into_iter().next() {
+                groups.entry(origin.human_name).or_default().push(line);
+            }
+        }
+
+        Ok(groups)
+    }
+
+    async fn get_line_page(&self, line: &str) -> Result<String> {
+        let path = format!("/hat/{line}");
+        let expected = reqwest::Url::parse(&format!("{}{path}", self.base_url)).ok();
+        let (final_url, doc) = self.get_page(path).await?;
+        if expected.is_some_and(|expected| expected.as_str() != final_url) {
+            return Err(Error::NoLineId {
+                redirect: Some(final_url),
+            });
+        }
+        Ok(doc)
     }
 
     pub async fn get_line_buses(&self, line: &str) -> Result<Vec<LineBus>> {
-        let doc = self.get_document(format!("/hat/{line}")).await?;
+        let doc = self.get_line_page(line).await?;
+        self.post_line_page_buses(&doc).await
+    }
+
+    /// The bus on `line` with the license plate `plate`, compared like
+    /// [`normalize_plate`] does, or `None` if it isn't on the line right now.
+    pub async fn get_line_buses_for_plate(
+        &self,
+        line: &str,
+        plate: &str,
+    ) -> Result<Option<LineBus>> {
+        let plate = normalize_plate(plate);
+        Ok(self
+            .get_line_buses(line)
+            .await?
+            .into_iter()
+            .find(|bus| normalize_plate(&bus.license_plate) == plate))
+    }
+
+    /// Like [`Client::get_line_buses`], along with the line itself. Its name
+    /// is taken from the links to lines on the line's page when there are
+    /// any, costing a request for the line list otherwise.
+    pub async fn get_line_buses_with_line(&self, line: &str) -> Result<(Line, Vec<LineBus>)> {
+        let doc = self.get_line_page(line).await?;
+        let buses = self.post_line_page_buses(&doc).await?;
+        let on_page = extract_lines(&doc)
+            .into_iter()
+            .find(|found| found.id == line);
+        let found = match on_page {
+            Some(found) => Some(found),
+            None => self
+                .get_lines()
+                .await?
+                .into_iter()
+                .find(|found| found.id == line),
+        };
+        let line = found.unwrap_or_else(|| Line {
+            id: line.to_string(),
+            human_name: line.to_string(),
+        });
+        Ok((line, buses))
+    }
+
+    async fn post_line_page_buses(&self, doc: &str) -> Result<Vec<LineBus>> {
+        let token = extract_token(doc).ok_or(Error::NoToken)?;
+        let id = extract_line_id(doc).ok_or(Error::NoLineId { redirect: None })?;
+
+        self.post_line_buses(id, &token).await
+    }
+
+    pub async fn line_data_age(&self, line: &str) -> Result<Option<Duration>> {
+        let buses = self.get_line_buses(line).await?;
+        let freshest = buses.iter().filter_map(|bus| bus.last_update).max();
+
+        Ok(freshest.map(|time| (Utc::now() - time).to_std().unwrap_or_default()))
+    }
+
+    pub async fn get_station_buses(&self, station: StationId) -> Result<Vec<StationBus>> {
+        let doc = self
+            .get_document(format!("/Akilli-Durak/{station}"))
+            .await?;
         let token = extract_token(&doc).ok_or(Error::NoToken)?;
-        let id = extract_line_id(&doc).ok_or(Error::NoLineId)?;
-        let dtos: Vec<LineBusDto> = self
-            .post_json(
-                "/aractekrar",
-                vec![("hgID", id), ("__RequestVerificationToken", &token)],
-            )
+
+        self.post_station_buses(station, &token).await
+    }
+
+    /// [`Client::get_station_buses`] taking a bare id, for code written
+    /// before [`StationId`].
+    pub async fn get_station_buses_by_id(&self, id: i32) -> Result<Vec<StationBus>> {
+        self.get_station_buses(StationId(id)).await
+    }
+
+    /// A station's arrivals on the given lines only.
+    ///
+    /// When the board says which line each bus is on, that's used. When it
+    /// doesn't, the buses of each of `lines` are fetched, 4 lines at a
+    /// time, and arrivals are kept if their plate is among them. Lines that
+    /// fail then are listed in [`LineArrivals::failed_lines`], unless all of
+    /// them do, in which case the first line's error is returned.
+    pub async fn get_station_buses_for_lines(
+        &self,
+        station: StationId,
+        lines: &[&str],
+    ) -> Result<LineArrivals> {
+        let doc = self
+            .get_document(format!("/Akilli-Durak/{station}"))
             .await?;
+        let token = extract_token(&doc).ok_or(Error::NoToken)?;
+        let board = self.post_station_board(station, &token).await?;
+
+        if board.iter().all(|arrival| arrival.line.is_some()) {
+            let buses = board
+                .into_iter()
+                .filter(|arrival| {
+                    let line = arrival.line.as_deref().unwrap_or_default();
+                    lines
+                        .iter()
+                        .any(|wanted| wanted.trim().eq_ignore_ascii_case(line))
+                })
+                .map(Into::into)
+                .collect();
+            return Ok(LineArrivals {
+                buses,
+                strategy: LineFilterStrategy::BoardLines,
+                failed_lines: Vec::new(),
+            });
+        }
+
+        let results: Vec<_> = stream::iter(lines)
+            .map(|line| async move { (line, self.get_line_buses(line).await) })
+            .buffered(LINE_LOOKUP_CONCURRENCY)
+            .collect()
+            .await;
+        let mut plates = HashSet::new();
+        let mut failed_lines = Vec::new();
+        let mut first_error = None;
+        for (line, result) in results {
+            match result {
+                Ok(buses) => {
+                    plates.extend(buses.iter().map(|bus| normalize_plate(&bus.license_plate)))
+                }
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                    failed_lines.push(line.to_string());
+                }
+            }
+        }
+        if let Some(error) = first_error.filter(|_| failed_lines.len() == lines.len()) {
+            return Err(error);
+        }
+
+        let buses = board
+            .into_iter()
+            .map(StationBus::from)
+            .filter(|bus| plates.contains(&normalize_plate(&bus.license_plate)))
+            .collect();
+        Ok(LineArrivals {
+            buses,
+            strategy: LineFilterStrategy::PlateLookup,
+            failed_lines,
+        })
+    }
+
+    /// Fetches the arrivals of several stations, in the order given.
+    ///
+    /// The token from the first station page that works is tried for the
+    /// others too.
+    /// If the site rejects it, that station falls back to its own page and
+    /// the rest of the batch fetches a token per station.
+    pub async fn get_station_buses_many(
+        &self,
+        stations: &[StationId],
+    ) -> Vec<Result<Vec<StationBus>>> {
+        let mut results = Vec::with_capacity(stations.len());
+        let mut shared_token: Option<String> = None;
+        let mut rejected = false;
+
+        for &station in stations {
+            let Some(token) = &shared_token else {
+                let doc = match self.get_document(format!("/Akilli-Durak/{station}")).await {
+                    Ok(doc) => doc,
+                    Err(error) => {
+                        results.push(Err(error));
+                        continue;
+                    }
+                };
+                let Some(token) = extract_token(&doc) else {
+                    results.push(Err(Error::NoToken));
+                    continue;
+                };
+                let result = self.post_station_buses(station, &token).await;
+                if !rejected && result.is_ok() {
+                    shared_token = Some(token);
+                }
+                results.push(result);
+                continue;
+            };
+
+            let result = match self.post_station_buses(station, token).await {
+                Err(error @ (Error::Request(_) | Error::Json(_))) => {
+                    shared_token = None;
+                    rejected = true;
+                    match self.retry_after(error) {
+                        Ok(()) => self.get_station_buses(station).await,
+                        Err(error) => Err(error),
+                    }
+                }
+                result => result,
+            };
+            results.push(result);
+        }
 
-        map_try_into(dtos)
+        results
     }
 
-    pub async fn get_station_buses(&self, station: i32) -> Result<Vec<StationBus>> {
+    /// Like [`Client::get_station_buses`], failing with [`Error::Timeout`]
+    /// if fetching the board takes over `bus_fetch_timeout`. Only the
+    /// request itself is timed, not the station page before it or the
+    /// request delay after it.
+    pub async fn get_station_buses_with_bus_timeout(
+        &self,
+        station: StationId,
+        bus_fetch_timeout: Duration,
+    ) -> Result<Vec<StationBus>> {
         let doc = self
             .get_document(format!("/Akilli-Durak/{station}"))
             .await?;
         let token = extract_token(&doc).ok_or(Error::NoToken)?;
-        let json: Vec<StationBusDto> = self
-            .post_json(
+
+        let board: Vec<StationBusDto> = self
+            .post_json_within(
                 "/durakTekrar",
                 vec![
                     ("drkID", &station.to_string()),
                     ("__RequestVerificationToken", &token),
                 ],
+                Some(bus_fetch_timeout),
             )
             .await?;
+        Ok(board.into_iter().map(Into::into).collect())
+    }
 
-        Ok(json.into_iter().map(Into::into).collect())
+    async fn post_line_buses(&self, id: &str, token: &str) -> Result<Vec<LineBus>> {
+        let dtos: Vec<LineBusDto> = self
+            .post_json(
+                "/aractekrar",
+                vec![("hgID", id), ("__RequestVerificationToken", token)],
+            )
+            .await?;
+
+        self.track(self.convert(dtos))
+    }
+
+    async fn post_station_buses(&self, station: StationId, token: &str) -> Result<Vec<StationBus>> {
+        let board = self.post_station_board(station, token).await?;
+        Ok(board.into_iter().map(Into::into).collect())
+    }
+
+    async fn post_station_board(
+        &self,
+        station: StationId,
+        token: &str,
+    ) -> Result<Vec<StationBusDto>> {
+        self.post_json(
+            "/durakTekrar",
+            vec![
+                ("drkID", &station.to_string()),
+                ("__RequestVerificationToken", token),
+            ],
+        )
+        .await
+    }
+
+    pub async fn get_all_data(&self) -> Result<NetworkSnapshot> {
+        self.get_all_data_with_progress(|_| {}).await
+    }
+
+    /// Like [`Client::get_all_data`], calling `progress` after each line.
+    pub async fn get_all_data_with_progress(
+        &self,
+        progress: impl FnMut(LineProgress),
+    ) -> Result<NetworkSnapshot> {
+        self.snapshot(true, progress).await
+    }
+
+    /// Like [`Client::get_all_data_with_progress`], but only takes the
+    /// static network, leaving [`NetworkSnapshot::buses`] empty. This makes
+    /// one request per line instead of two.
+    pub async fn get_network_with_progress(
+        &self,
+        progress: impl FnMut(LineProgress),
+    ) -> Result<NetworkSnapshot> {
+        self.snapshot(false, progress).await
+    }
+
+    async fn snapshot(
+        &self,
+        with_buses: bool,
+        mut progress: impl FnMut(LineProgress),
+    ) -> Result<NetworkSnapshot> {
+        let taken_at = Utc::now();
+        let lines = self.get_lines().await?;
+        let stations = self.get_all_stations().await?;
+        let mut buses = HashMap::new();
+        let mut line_stations = HashMap::new();
+        let mut failed_lines = Vec::new();
+        let mut failures = 0;
+
+        for (done, line) in lines.iter().enumerate() {
+            let result = match self.get_line_page(&line.id).await {
+                Ok(doc) => {
+                    if let Ok(stations) = self.parse_stations(&doc) {
+                        let ids = stations.iter().map(|station| station.id).collect();
+                        line_stations.insert(line.id.clone(), ids);
+                    }
+                    if with_buses {
+                        self.post_line_page_buses(&doc).await.map(Some)
+                    } else {
+                        Ok(None)
+                    }
+                }
+                Err(error) => Err(error),
+            };
+
+            progress(LineProgress {
+                done: done + 1,
+                total: lines.len(),
+                line: &line.id,
+                result: result.as_ref().map(|buses| buses.as_ref().map(Vec::len)),
+            });
+            match result {
+                Ok(line_buses) => {
+                    failures = 0;
+                    if let Some(line_buses) = line_buses {
+                        buses.insert(line.id.clone(), line_buses);
+                    }
+                }
+                Err(_) => {
+                    failures += 1;
+                    failed_lines.push(line.id.clone());
+                    if self
+                        .circuit_breaker_threshold
+                        .is_some_and(|threshold| failures >= threshold)
+                    {
+                        return Err(Error::CircuitOpen { failures });
+                    }
+                }
+            }
+        }
+
+        Ok(NetworkSnapshot {
+            version: SNAPSHOT_VERSION,
+            taken_at,
+            lines,
+            stations,
+            buses,
+            line_stations,
+            failed_lines,
+        })
     }
 }
 
@@ -252,7 +1746,7 @@ fn extract_line_id(doc: &str) -> Option<&str> {
     Some(
         Regex::new(r"hgID\s*:\s*(\d+)")
             .unwrap()
-            .captures(&doc)?
+            .captures(doc)?
             .get(1)?
             .as_str(),
     )
@@ -263,27 +1757,112 @@ fn extract_lines(doc: &str) -> Vec<Line> {
         .select(&Selector::parse(r#"a[href^="/hat/"]"#).unwrap())
         .filter_map(|elem| {
             let id = elem.attr("href")?.split("/").last()?.to_string();
+            if !is_valid_line_id(&id) {
+                tracing::warn!(id, "skipping line with an invalid id");
+                return None;
+            }
             let human_name = elem.text().next()?.trim().to_string();
             Some(Line { id, human_name })
         })
         .collect()
 }
 
-fn extract_station_json(doc: &str) -> Option<&str> {
+// Sivas line ids are short codes like "1", "7A" or "16B".
+fn is_valid_line_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 8 && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+// The stations are assigned with `var`, `let` or `const`, or to
+// `window.duraks`.
+fn station_json_range(doc: &str) -> Option<Range<usize>> {
     Some(
-        Regex::new(r"var\s+duraks\s*=\s*(\[.*\])")
+        Regex::new(r"(?:\b(?:var|let|const)\s+|\bwindow\.)duraks\s*=\s*(\[.*\])")
             .unwrap()
             .captures(doc)?
             .get(1)?
-            .as_str(),
+            .range(),
     )
 }
 
-fn extract_stations(doc: &str) -> Result<Vec<Station>> {
+fn extract_station_json(doc: &str) -> Option<&str> {
+    Some(&doc[station_json_range(doc)?])
+}
+
+struct StationArray {
+    doc: String,
+    pos: usize,
+    end: usize,
+    done: bool,
+}
+
+impl StationArray {
+    fn new(doc: String, range: Range<usize>) -> StationArray {
+        StationArray {
+            doc,
+            // skip the opening bracket
+            pos: range.start + 1,
+            end: range.end,
+            done: false,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.doc[self.pos..self.end];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn next_dto(&mut self) -> Option<Result<StationDto>> {
+        self.skip_whitespace();
+        if self.doc[self.pos..self.end].starts_with(']') {
+            return None;
+        }
+
+        let mut values = serde_json::Deserializer::from_str(&self.doc[self.pos..self.end])
+            .into_iter::<StationDto>();
+        let dto = match values.next()? {
+            Ok(dto) => dto,
+            Err(error) => return Some(Err(error.into())),
+        };
+        self.pos += values.byte_offset();
+
+        self.skip_whitespace();
+        match self.doc[self.pos..self.end].chars().next() {
+            Some(',') => self.pos += 1,
+            Some(']') => {}
+            _ => {
+                let error = <serde_json::Error as serde::de::Error>::custom(
+                    "expected `,` or `]` after a station",
+                );
+                return Some(Err(error.into()));
+            }
+        }
+        Some(Ok(dto))
+    }
+}
+
+impl Iterator for StationArray {
+    type Item = Result<Station>;
+
+    fn next(&mut self) -> Option<Result<Station>> {
+        if self.done {
+            return None;
+        }
+
+        let result = self
+            .next_dto()?
+            .and_then(|dto| dto.try_into().map_err(Error::from));
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+fn extract_station_dtos(doc: &str) -> Result<Vec<StationDto>> {
     let json = extract_station_json(doc).ok_or(Error::NoStations)?;
-    let dtos = serde_json::from_str::<Vec<StationDto>>(json)?;
+    Ok(serde_json::from_str(json)?)
+}
 
-    map_try_into(dtos)
+fn extract_stations(doc: &str) -> Result<Vec<Station>> {
+    map_try_into(extract_station_dtos(doc)?)
 }
 
 fn map_try_into<T, E, U>(v: Vec<T>) -> Result<Vec<U>, E>
@@ -1,10 +1,8 @@
-use sivasbus::Client;
+mod cli;
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let client = Client::new();
-    let results = client.get_station_buses(10).await?;
-    println!("{results:#?}");
+use std::process::ExitCode;
 
-    Ok(())
+#[tokio::main]
+async fn main() -> ExitCode {
+    cli::main().await
 }
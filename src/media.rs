@@ -0,0 +1,184 @@
+//! Route map images and timetable documents linked from line pages.
+//!
+//! Few line pages have these and there's no markup to tell them apart, so
+//! parsing is best effort:
+//!
+//! - Images count if their `src`, `alt` or `title` mentions a route or map
+//!   ("güzergah", "harita", "route", "map"). Logos and icons don't.
+//! - Documents are links to PDF, Word or Excel files.
+//! - Relative links are resolved against the client's base URL, and each
+//!   URL is listed once.
+
+use bytes::{Bytes, BytesMut};
+use reqwest::{Url, header};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Error, Result};
+
+/// Downloads larger than this fail with [`Error::MediaTooLarge`].
+pub const MAX_MEDIA_BYTES: usize = 20 * 1024 * 1024;
+
+const ROUTE_IMAGE_WORDS: [&str; 5] = ["güzergah", "guzergah", "harita", "route", "map"];
+const DOCUMENT_EXTENSIONS: [&str; 5] = ["pdf", "doc", "docx", "xls", "xlsx"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Image,
+    Document,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MediaRef {
+    pub kind: MediaKind,
+    /// Absolute URL of the file.
+    pub url: String,
+    /// The image's alt text or the link's text, if any.
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LineMedia {
+    pub images: Vec<MediaRef>,
+    pub documents: Vec<MediaRef>,
+}
+
+fn title<'a>(text: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let text = text
+        .into_iter()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn push_unique(list: &mut Vec<MediaRef>, media: MediaRef) {
+    if !list.iter().any(|known| known.url == media.url) {
+        list.push(media);
+    }
+}
+
+fn is_route_image(img: ElementRef) -> bool {
+    ["src", "alt", "title"]
+        .iter()
+        .filter_map(|name| img.attr(name))
+        .map(str::to_lowercase)
+        .any(|text| ROUTE_IMAGE_WORDS.iter().any(|word| text.contains(word)))
+}
+
+fn is_document(url: &Url) -> bool {
+    url.path().rsplit_once('.').is_some_and(|(_, extension)| {
+        DOCUMENT_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+    })
+}
+
+/// Finds the route images and documents on a line page, resolving their
+/// links against `base_url`.
+pub fn extract_line_media(doc: &str, base_url: &str) -> LineMedia {
+    let Ok(base) = Url::parse(&format!("{}/", base_url.trim_end_matches('/'))) else {
+        return LineMedia::default();
+    };
+    let html = Html::parse_document(doc);
+    let mut media = LineMedia::default();
+
+    for img in html.select(&Selector::parse("img[src]").unwrap()) {
+        let Some(url) = img.attr("src").and_then(|src| base.join(src.trim()).ok()) else {
+            continue;
+        };
+        if is_route_image(img) {
+            let image = MediaRef {
+                kind: MediaKind::Image,
+                url: url.to_string(),
+                title: title(img.attr("alt").or(img.attr("title"))),
+            };
+            push_unique(&mut media.images, image);
+        }
+    }
+    for link in html.select(&Selector::parse("a[href]").unwrap()) {
+        let Some(url) = link
+            .attr("href")
+            .and_then(|href| base.join(href.trim()).ok())
+        else {
+            continue;
+        };
+        if is_document(&url) {
+            let document = MediaRef {
+                kind: MediaKind::Document,
+                url: url.to_string(),
+                title: title(link.text()),
+            };
+            push_unique(&mut media.documents, document);
+        }
+    }
+    media
+}
+
+/// Whether `content_type` is what a file of `kind` should be served as.
+/// Documents are also accepted as `application/octet-stream`, which some
+/// servers send for anything that isn't a web page.
+fn expected_content_type(kind: MediaKind, content_type: &str) -> bool {
+    let content_type = content_type.to_lowercase();
+    match kind {
+        MediaKind::Image => content_type.starts_with("image/"),
+        MediaKind::Document => {
+            content_type.starts_with("application/") && !content_type.contains("html")
+        }
+    }
+}
+
+impl Client {
+    /// Fetches the route images and documents linked from a line's page,
+    /// see the [module docs](self) for what's picked up.
+    pub async fn get_line_media(&self, line: &str) -> Result<LineMedia> {
+        let doc = self.get_line_page(line).await?;
+        Ok(extract_line_media(&doc, &self.base_url))
+    }
+
+    /// Downloads a file found by [`Client::get_line_media`]. Fails with
+    /// [`Error::UnexpectedContentType`] if the server doesn't send an image
+    /// for an image or a document for a document, and with
+    /// [`Error::MediaTooLarge`] past [`MAX_MEDIA_BYTES`].
+    pub async fn download_media(&self, media: &MediaRef) -> Result<Bytes> {
+        if self.record_dry_run("GET", &media.url, &[]) {
+            return Ok(Bytes::new());
+        }
+
+        let (response, permit) = self.send(&media.url, || self.http.get(&media.url)).await?;
+        let mut response = response.error_for_status()?;
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        if !content_type.is_some_and(|content_type| expected_content_type(media.kind, content_type))
+        {
+            return Err(Error::UnexpectedContentType {
+                content_type: content_type.map(str::to_string),
+            });
+        }
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_MEDIA_BYTES as u64)
+        {
+            return Err(Error::MediaTooLarge {
+                limit: MAX_MEDIA_BYTES,
+            });
+        }
+
+        let mut body = BytesMut::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > MAX_MEDIA_BYTES {
+                return Err(Error::MediaTooLarge {
+                    limit: MAX_MEDIA_BYTES,
+                });
+            }
+            body.extend_from_slice(&chunk);
+        }
+        drop(permit);
+
+        self.metrics.record_get(body.len());
+        self.throttle().await;
+
+        Ok(body.freeze())
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    get_requests: AtomicU64,
+    post_requests: AtomicU64,
+    response_bytes: AtomicU64,
+    parse_failures: AtomicU64,
+    retries: AtomicU64,
+    throttle_waits: AtomicU64,
+    throttle_wait_micros: AtomicU64,
+}
+
+impl Metrics {
+    pub fn get_requests(&self) -> u64 {
+        self.get_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn post_requests(&self) -> u64 {
+        self.post_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.get_requests() + self.post_requests()
+    }
+
+    pub fn response_bytes(&self) -> u64 {
+        self.response_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn parse_failures(&self) -> u64 {
+        self.parse_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    pub fn throttle_waits(&self) -> u64 {
+        self.throttle_waits.load(Ordering::Relaxed)
+    }
+
+    pub fn throttle_wait_time(&self) -> Duration {
+        Duration::from_micros(self.throttle_wait_micros.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn record_get(&self, bytes: usize) {
+        self.get_requests.fetch_add(1, Ordering::Relaxed);
+        self.response_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_post(&self, bytes: usize) {
+        self.post_requests.fetch_add(1, Ordering::Relaxed);
+        self.response_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_parse_failure(&self) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_throttle_wait(&self, wait: Duration) {
+        self.throttle_waits.fetch_add(1, Ordering::Relaxed);
+        self.throttle_wait_micros
+            .fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "prometheus")]
+    pub fn render_prometheus(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let mut metric = |name: &str, help: &str, samples: &[(&str, String)]| {
+            writeln!(out, "# HELP {name} {help}").unwrap();
+            writeln!(out, "# TYPE {name} counter").unwrap();
+            for (labels, value) in samples {
+                writeln!(out, "{name}{labels} {value}").unwrap();
+            }
+        };
+
+        metric(
+            "sivasbus_requests_total",
+            "Requests sent to the bus site.",
+            &[
+                (r#"{method="GET"}"#, self.get_requests().to_string()),
+                (r#"{method="POST"}"#, self.post_requests().to_string()),
+            ],
+        );
+        metric(
+            "sivasbus_response_bytes_total",
+            "Response body bytes received from the bus site.",
+            &[("", self.response_bytes().to_string())],
+        );
+        metric(
+            "sivasbus_parse_failures_total",
+            "Responses that could not be parsed.",
+            &[("", self.parse_failures().to_string())],
+        );
+        metric(
+            "sivasbus_retries_total",
+            "Requests sent again after a failure.",
+            &[("", self.retries().to_string())],
+        );
+        metric(
+            "sivasbus_throttle_waits_total",
+            "Pauses made between requests to stay polite.",
+            &[("", self.throttle_waits().to_string())],
+        );
+        metric(
+            "sivasbus_throttle_wait_seconds_total",
+            "Time spent pausing between requests.",
+            &[("", self.throttle_wait_time().as_secs_f64().to_string())],
+        );
+
+        out
+    }
+}
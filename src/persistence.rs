@@ -0,0 +1,3 @@
+//! Storage backends for network snapshots.
+
+pub mod sqlite;
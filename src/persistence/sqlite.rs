@@ -0,0 +1,163 @@
+//! Snapshots stored in SQLite, one row per snapshot with its lines,
+//! stations and bus positions in child tables.
+//!
+//! Times are stored as milliseconds since the Unix epoch.
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Coords, LineBus, NetworkSnapshot, normalize_plate};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    taken_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS snapshots_taken_at ON snapshots (taken_at);
+
+CREATE TABLE IF NOT EXISTS lines (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+    id TEXT NOT NULL,
+    human_name TEXT NOT NULL,
+    failed INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (snapshot_id, id)
+);
+
+CREATE TABLE IF NOT EXISTS stations (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+    id INTEGER NOT NULL,
+    human_name TEXT NOT NULL,
+    lat REAL NOT NULL,
+    long REAL NOT NULL,
+    PRIMARY KEY (snapshot_id, id)
+);
+
+CREATE TABLE IF NOT EXISTS line_stations (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+    line_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    station_id INTEGER NOT NULL,
+    PRIMARY KEY (snapshot_id, line_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS bus_positions (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshots (id) ON DELETE CASCADE,
+    line_id TEXT NOT NULL,
+    license_plate TEXT NOT NULL,
+    plate_key TEXT NOT NULL,
+    lat REAL NOT NULL,
+    long REAL NOT NULL,
+    last_update INTEGER
+);
+CREATE INDEX IF NOT EXISTS bus_positions_plate ON bus_positions (plate_key, snapshot_id);
+";
+
+fn millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis().try_into().unwrap_or(i64::MAX))
+}
+
+/// Creates the tables if they don't exist yet.
+pub fn create_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(SCHEMA)
+}
+
+/// Inserts a snapshot in a single transaction.
+pub fn insert_snapshot(conn: &Connection, snapshot: &NetworkSnapshot) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO snapshots (taken_at) VALUES (?1)",
+        [snapshot.taken_at.timestamp_millis()],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR IGNORE INTO lines (snapshot_id, id, human_name, failed)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for line in &snapshot.lines {
+            let failed = snapshot.failed_lines.contains(&line.id);
+            insert.execute(params![id, line.id, line.human_name, failed])?;
+        }
+
+        let mut insert = tx.prepare(
+            "INSERT OR IGNORE INTO stations (snapshot_id, id, human_name, lat, long)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for station in &snapshot.stations {
+            insert.execute(params![
+                id,
+                station.id,
+                station.human_name,
+                station.coords.lat,
+                station.coords.long
+            ])?;
+        }
+
+        let mut insert = tx.prepare(
+            "INSERT INTO line_stations (snapshot_id, line_id, position, station_id)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (line, stations) in &snapshot.line_stations {
+            for (position, station) in stations.iter().enumerate() {
+                insert.execute(params![id, line, position, station])?;
+            }
+        }
+
+        let mut insert = tx.prepare(
+            "INSERT INTO bus_positions
+             (snapshot_id, line_id, license_plate, plate_key, lat, long, last_update)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for (line, buses) in &snapshot.buses {
+            for bus in buses {
+                insert.execute(params![
+                    id,
+                    line,
+                    bus.license_plate,
+                    normalize_plate(&bus.license_plate),
+                    bus.coords.lat,
+                    bus.coords.long,
+                    bus.last_update.map(|time| time.timestamp_millis()),
+                ])?;
+            }
+        }
+    }
+
+    tx.commit()
+}
+
+/// Positions of a bus in snapshots taken between `from` and `to`, oldest
+/// first. Plates are compared like [`normalize_plate`] does.
+///
+/// Positions without a GPS timestamp get the snapshot's time as
+/// [`LineBus::last_update`].
+pub fn query_bus_history(
+    conn: &Connection,
+    plate: &str,
+    from: SystemTime,
+    to: SystemTime,
+) -> rusqlite::Result<Vec<LineBus>> {
+    let mut query = conn.prepare(
+        "SELECT b.license_plate, b.lat, b.long, COALESCE(b.last_update, s.taken_at)
+         FROM bus_positions b JOIN snapshots s ON s.id = b.snapshot_id
+         WHERE b.plate_key = ?1 AND s.taken_at BETWEEN ?2 AND ?3
+         ORDER BY s.taken_at",
+    )?;
+    let rows = query.query_map(
+        params![normalize_plate(plate), millis(from), millis(to)],
+        |row| {
+            Ok(LineBus {
+                license_plate: row.get(0)?,
+                coords: Coords {
+                    lat: row.get(1)?,
+                    long: row.get(2)?,
+                },
+                last_update: DateTime::<Utc>::from_timestamp_millis(row.get(3)?),
+            })
+        },
+    )?;
+    rows.collect()
+}
@@ -0,0 +1,513 @@
+//! QR codes linking to stations' live arrivals pages, for printing on
+//! signs.
+
+use qrcode::{Color, EcLevel, QrCode};
+use std::path::{Path, PathBuf};
+
+use crate::Station;
+
+/// Height of the caption under the code, in modules.
+const CAPTION_MODULES: u32 = 3;
+
+/// How a QR code is drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrOptions {
+    module_size: u32,
+    quiet_zone: u32,
+    caption: bool,
+}
+
+impl Default for QrOptions {
+    fn default() -> QrOptions {
+        QrOptions {
+            module_size: 8,
+            quiet_zone: 4,
+            caption: false,
+        }
+    }
+}
+
+impl QrOptions {
+    pub fn new() -> QrOptions {
+        QrOptions::default()
+    }
+
+    /// Size of each module, the code's squares, in pixels. 8 by default,
+    /// and at least 1.
+    pub fn module_size(mut self, pixels: u32) -> QrOptions {
+        self.module_size = pixels.max(1);
+        self
+    }
+
+    /// Blank border around the code, in modules. Readers expect 4, the
+    /// default.
+    pub fn quiet_zone(mut self, modules: u32) -> QrOptions {
+        self.quiet_zone = modules;
+        self
+    }
+
+    /// Writes the station's name under the code. SVG only.
+    pub fn caption(mut self, caption: bool) -> QrOptions {
+        self.caption = caption;
+        self
+    }
+}
+
+fn station_code(station: &Station, base_url: &str) -> QrCode {
+    QrCode::with_error_correction_level(station.page_url(base_url), EcLevel::M)
+        .expect("station URL too long for a QR code")
+}
+
+/// Whether each pixel is dark, row by row, with the quiet zone.
+fn pixels(code: &QrCode, opts: &QrOptions) -> (u32, Vec<bool>) {
+    let width = code.width() as u32;
+    let size = (width + 2 * opts.quiet_zone) * opts.module_size;
+    let colors = code.to_colors();
+    let module = |pixel: u32| (pixel / opts.module_size).checked_sub(opts.quiet_zone);
+    let dark = (0..size * size)
+        .map(|i| match (module(i % size), module(i / size)) {
+            (Some(x), Some(y)) if x < width && y < width => {
+                colors[(y * width + x) as usize] == Color::Dark
+            }
+            _ => false,
+        })
+        .collect();
+    (size, dark)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Lowercase ASCII letters, digits and dashes, with Turkish letters
+/// transliterated, for file names.
+fn slugify(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| match c {
+            'İ' | 'I' | 'ı' => 'i',
+            'Ş' | 'ş' => 's',
+            'Ğ' | 'ğ' => 'g',
+            'Ü' | 'ü' => 'u',
+            'Ö' | 'ö' => 'o',
+            'Ç' | 'ç' => 'c',
+            c if c.is_ascii_alphanumeric() => c.to_ascii_lowercase(),
+            _ => ' ',
+        })
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+impl Station {
+    /// An SVG QR code for the station's [page](Station::page_url) on the
+    /// site at `base_url`.
+    ///
+    /// # Panics
+    ///
+    /// If the URL is too long for a QR code, which takes a base URL of over
+    /// 2000 bytes.
+    pub fn qr_svg(&self, base_url: &str, opts: &QrOptions) -> String {
+        let code = station_code(self, base_url);
+        let width = code.width() as u32;
+        let size = (width + 2 * opts.quiet_zone) * opts.module_size;
+        let caption_height = match opts.caption {
+            true => CAPTION_MODULES * opts.module_size,
+            false => 0,
+        };
+
+        let mut path = String::new();
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            if color == Color::Dark {
+                let x = (i as u32 % width + opts.quiet_zone) * opts.module_size;
+                let y = (i as u32 / width + opts.quiet_zone) * opts.module_size;
+                path.push_str(&format!("M{x} {y}h{0}v{0}h-{0}z", opts.module_size));
+            }
+        }
+
+        let height = size + caption_height;
+        let mut svg = format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{height}" viewBox="0 0 {size} {height}" shape-rendering="crispEdges"><rect width="{size}" height="{height}" fill="#fff"/><path d="{path}" fill="#000"/>"##
+        );
+        if opts.caption {
+            svg.push_str(&format!(
+                r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" text-anchor="middle">{}</text>"#,
+                size / 2,
+                size + caption_height / 2,
+                2 * opts.module_size,
+                escape_xml(&self.human_name),
+            ));
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// Like [`Station::qr_svg`], as a grayscale PNG. The caption option is
+    /// ignored.
+    pub fn qr_png(&self, base_url: &str, opts: &QrOptions) -> Vec<u8> {
+        let (size, dark) = pixels(&station_code(self, base_url), opts);
+        let data: Vec<u8> = dark
+            .into_iter()
+            .map(|dark| if dark { 0 } else { 255 })
+            .collect();
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, size, size);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .expect("writing a PNG to memory can't fail");
+        png
+    }
+}
+
+/// Writes an SVG QR code for each of `stations` into `dir`, named after the
+/// station's id and name like `10-cumhuriyet-meydani.svg`. Returns the
+/// paths written, in order.
+pub async fn generate_station_qrs(
+    stations: &[Station],
+    base_url: &str,
+    dir: &Path,
+    opts: &QrOptions,
+) -> std::io::Result<Vec<PathBuf>> {
+    tokio::fs::create_dir_all(dir).await?;
+    let mut paths = Vec::with_capacity(stations.len());
+    for station in stations {
+        let slug = slugify(&station.human_name);
+        let name = match slug.is_empty() {
+            true => format!("{}.svg", station.id),
+            false => format!("{}-{slug}.svg", station.id),
+        };
+        let path = dir.join(name);
+        tokio::fs::write(&path, station.qr_svg(base_url, opts)).await?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coords;
+
+    const BASE_URL: &str = "https://www.sivas.bel.tr";
+
+    fn station(id: i32, name: &str) -> Station {
+        Station {
+            id,
+            human_name: name.to_string(),
+            coords: Coords {
+                lat: 39.75,
+                long: 37.02,
+            },
+        }
+    }
+
+    /// A minimal QR decoder for the codes drawn here: versions 1 to 6 at
+    /// error correction level M, read without errors. Each mask is tried
+    /// and the one whose blocks pass the Reed-Solomon check is kept.
+    mod decode {
+        /// (error correction codewords per block, blocks, data codewords per
+        /// block) of versions 1 to 6 at level M.
+        const BLOCKS: [(usize, usize, usize); 6] = [
+            (10, 1, 16),
+            (16, 1, 28),
+            (26, 1, 44),
+            (18, 2, 32),
+            (24, 2, 43),
+            (16, 4, 27),
+        ];
+
+        /// Multiplication in GF(256) modulo x⁸ + x⁴ + x³ + x² + 1.
+        fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+            let mut product = 0;
+            while b != 0 {
+                if b & 1 != 0 {
+                    product ^= a;
+                }
+                let carry = a & 0x80 != 0;
+                a <<= 1;
+                if carry {
+                    a ^= 0x1d;
+                }
+                b >>= 1;
+            }
+            product
+        }
+
+        /// Whether `block` is divisible by the generator polynomial, whose
+        /// roots are α⁰ to α^(`ec` - 1).
+        fn is_codeword(block: &[u8], ec: usize) -> bool {
+            let mut root = 1;
+            (0..ec).all(|_| {
+                let syndrome = block.iter().fold(0, |s, &c| gf_mul(s, root) ^ c);
+                root = gf_mul(root, 2);
+                syndrome == 0
+            })
+        }
+
+        fn is_function(n: usize, y: usize, x: usize) -> bool {
+            let finder = (y < 9 && (x < 9 || x >= n - 8)) || (y >= n - 8 && x < 9);
+            let alignment = n > 21 && (n - 9..n - 4).contains(&y) && (n - 9..n - 4).contains(&x);
+            finder || alignment || y == 6 || x == 6
+        }
+
+        fn mask(mask: u8, y: usize, x: usize) -> bool {
+            match mask {
+                0 => (y + x).is_multiple_of(2),
+                1 => y.is_multiple_of(2),
+                2 => x.is_multiple_of(3),
+                3 => (y + x).is_multiple_of(3),
+                4 => (y / 2 + x / 3).is_multiple_of(2),
+                5 => (y * x) % 2 + (y * x) % 3 == 0,
+                6 => ((y * x) % 2 + (y * x) % 3).is_multiple_of(2),
+                _ => ((y + x) % 2 + (y * x) % 3).is_multiple_of(2),
+            }
+        }
+
+        /// The codewords in placement order, unmasked with `mask_id`.
+        fn codewords(modules: &[Vec<bool>], mask_id: u8, total: usize) -> Vec<u8> {
+            let n = modules.len();
+            let mut bits = Vec::new();
+            let mut x = n - 1;
+            let mut upward = true;
+            loop {
+                for i in 0..n {
+                    let y = if upward { n - 1 - i } else { i };
+                    for x in [x, x - 1] {
+                        if !is_function(n, y, x) {
+                            bits.push(modules[y][x] ^ mask(mask_id, y, x));
+                        }
+                    }
+                }
+                upward = !upward;
+                match x {
+                    1 => break,
+                    8 => x = 5,
+                    _ => x -= 2,
+                }
+            }
+            bits.chunks(8)
+                .take(total)
+                .map(|byte| byte.iter().fold(0, |b, &bit| b << 1 | bit as u8))
+                .collect()
+        }
+
+        struct Bits<'a> {
+            data: &'a [u8],
+            at: usize,
+        }
+
+        impl Bits<'_> {
+            fn take(&mut self, count: usize) -> Option<usize> {
+                (self.at + count <= self.data.len() * 8).then(|| {
+                    let value = (self.at..self.at + count).fold(0, |value, i| {
+                        value << 1 | (self.data[i / 8] >> (7 - i % 8) & 1) as usize
+                    });
+                    self.at += count;
+                    value
+                })
+            }
+        }
+
+        fn parse(data: &[u8]) -> Option<String> {
+            const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+            let mut bits = Bits { data, at: 0 };
+            let mut text = Vec::new();
+            loop {
+                match bits.take(4).unwrap_or(0) {
+                    0 => return String::from_utf8(text).ok(),
+                    0b0001 => {
+                        let mut count = bits.take(10)?;
+                        while count > 0 {
+                            let digits = count.min(3);
+                            let value = bits.take([4, 7, 10][digits - 1])?;
+                            text.extend(format!("{value:0digits$}").bytes());
+                            count -= digits;
+                        }
+                    }
+                    0b0010 => {
+                        let mut count = bits.take(9)?;
+                        while count > 0 {
+                            if count == 1 {
+                                text.push(ALPHANUMERIC[bits.take(6)?]);
+                                break;
+                            }
+                            let pair = bits.take(11)?;
+                            text.extend([ALPHANUMERIC[pair / 45], ALPHANUMERIC[pair % 45]]);
+                            count -= 2;
+                        }
+                    }
+                    0b0100 => {
+                        let count = bits.take(8)?;
+                        for _ in 0..count {
+                            text.push(bits.take(8)? as u8);
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+        }
+
+        /// The text encoded in `modules`, dark modules being `true`.
+        pub fn decode(modules: &[Vec<bool>]) -> Option<String> {
+            let version = (modules.len() - 17) / 4;
+            let (ec, blocks, data) = *BLOCKS.get(version.checked_sub(1)?)?;
+            (0..8).find_map(|mask_id| {
+                let codewords = codewords(modules, mask_id, blocks * (data + ec));
+                let (data_words, ec_words) = codewords.split_at(blocks * data);
+                let blocks: Vec<Vec<u8>> = (0..blocks)
+                    .map(|b| {
+                        let data = data_words.iter().skip(b).step_by(blocks);
+                        let ec = ec_words.iter().skip(b).step_by(blocks);
+                        data.chain(ec).copied().collect()
+                    })
+                    .collect();
+                if !blocks.iter().all(|block| is_codeword(block, ec)) {
+                    return None;
+                }
+                let data: Vec<u8> = blocks
+                    .iter()
+                    .flat_map(|block| &block[..data])
+                    .copied()
+                    .collect();
+                parse(&data)
+            })
+        }
+    }
+
+    /// The modules of a code drawn as `size` pixels square, sampled at the
+    /// center of each.
+    fn modules(size: u32, opts: &QrOptions, dark: impl Fn(u32, u32) -> bool) -> Vec<Vec<bool>> {
+        let n = size / opts.module_size - 2 * opts.quiet_zone;
+        let center =
+            |module: u32| (module + opts.quiet_zone) * opts.module_size + opts.module_size / 2;
+        (0..n)
+            .map(|y| (0..n).map(|x| dark(center(x), center(y))).collect())
+            .collect()
+    }
+
+    fn read_png(png: &[u8], opts: &QrOptions) -> Option<String> {
+        let mut reader = png::Decoder::new(std::io::Cursor::new(png))
+            .read_info()
+            .unwrap();
+        let mut data = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Grayscale);
+        let size = info.width;
+        decode::decode(&modules(size, opts, |x, y| {
+            data[(y * size + x) as usize] < 128
+        }))
+    }
+
+    /// The decoded code and the caption, if any, of an SVG.
+    fn read_svg(svg: &str, opts: &QrOptions) -> (Option<String>, Option<String>) {
+        let doc = roxmltree::Document::parse(svg).unwrap();
+        let size: u32 = doc
+            .root_element()
+            .attribute("width")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let path = doc
+            .descendants()
+            .find(|node| node.has_tag_name("path"))
+            .unwrap()
+            .attribute("d")
+            .unwrap();
+        let squares: Vec<(u32, u32)> = path
+            .split('M')
+            .skip(1)
+            .map(|square| {
+                let (x, rest) = square.split_once(' ').unwrap();
+                let y = rest.split_once('h').unwrap().0;
+                (x.parse().unwrap(), y.parse().unwrap())
+            })
+            .collect();
+        let dark = |x: u32, y: u32| {
+            squares.iter().any(|&(left, top)| {
+                (left..left + opts.module_size).contains(&x)
+                    && (top..top + opts.module_size).contains(&y)
+            })
+        };
+        let caption = doc
+            .descendants()
+            .find(|node| node.has_tag_name("text"))
+            .and_then(|text| text.text())
+            .map(str::to_string);
+        (decode::decode(&modules(size, opts, dark)), caption)
+    }
+
+    #[test]
+    fn svg_links_to_the_station_page() {
+        let opts = QrOptions::new();
+        let svg = station(10, "Cumhuriyet Meydanı").qr_svg(BASE_URL, &opts);
+        assert_eq!(
+            read_svg(&svg, &opts),
+            (Some(format!("{BASE_URL}/Akilli-Durak/10")), None)
+        );
+    }
+
+    #[test]
+    fn svg_caption_is_the_escaped_station_name() {
+        let opts = QrOptions::new().module_size(3).quiet_zone(2).caption(true);
+        let svg = station(12, "Otogar & <Terminal>").qr_svg(&format!("{BASE_URL}/"), &opts);
+        assert_eq!(
+            read_svg(&svg, &opts),
+            (
+                Some(format!("{BASE_URL}/Akilli-Durak/12")),
+                Some("Otogar & <Terminal>".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn png_links_to_the_station_page() {
+        for opts in [
+            QrOptions::new(),
+            QrOptions::new().module_size(1).quiet_zone(0),
+        ] {
+            let png = station(1234, "Hükümet Konağı").qr_png("http://127.0.0.1:8765", &opts);
+            assert_eq!(
+                read_png(&png, &opts).as_deref(),
+                Some("http://127.0.0.1:8765/Akilli-Durak/1234"),
+                "{opts:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn station_names_are_slugified() {
+        assert_eq!(slugify("Cumhuriyet Meydanı"), "cumhuriyet-meydani");
+        assert_eq!(slugify("İŞÇİ Bloklari / Çarşı"), "isci-bloklari-carsi");
+        assert_eq!(slugify("  ~~ "), "");
+    }
+
+    #[test]
+    fn batch_writes_one_code_per_station() {
+        let dir = tempfile::tempdir().unwrap();
+        let stations = [station(10, "Cumhuriyet Meydanı"), station(99, "?")];
+        let opts = QrOptions::new();
+        let paths = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(generate_station_qrs(
+                &stations,
+                BASE_URL,
+                &dir.path().join("qr"),
+                &opts,
+            ))
+            .unwrap();
+
+        let names: Vec<_> = paths.iter().map(|path| path.file_name().unwrap()).collect();
+        assert_eq!(names, ["10-cumhuriyet-meydani.svg", "99.svg"]);
+        for (path, station) in paths.iter().zip(&stations) {
+            let svg = std::fs::read_to_string(path).unwrap();
+            assert_eq!(read_svg(&svg, &opts).0, Some(station.page_url(BASE_URL)));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// A token bucket rate limiter, shareable between clients and threads.
+///
+/// Clones share the same bucket. It starts full with `capacity` tokens and
+/// refills `refill_per_second` tokens every second, up to `capacity`.
+#[derive(Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl TokenBucket {
+    /// A capacity of 0 is taken as 1, as a bucket that can't hold a token
+    /// would make [`TokenBucket::acquire`] wait forever.
+    pub fn new(capacity: u32, refill_per_second: f32) -> TokenBucket {
+        TokenBucket::with_capacity(capacity.max(1), refill_per_second)
+    }
+
+    /// Like [`TokenBucket::new`], but a capacity of 0 stays empty for good.
+    /// Only for budgets checked with [`TokenBucket::try_acquire`], where 0
+    /// means nothing is allowed.
+    pub(crate) fn with_capacity(capacity: u32, refill_per_second: f32) -> TokenBucket {
+        TokenBucket {
+            capacity: capacity.into(),
+            refill_per_second: refill_per_second.max(0.0).into(),
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: capacity.into(),
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Takes a token if there's one, otherwise returns how long until the
+    /// next one, or `None` if the bucket never refills.
+    fn take(&self) -> Result<(), Option<Duration>> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if self.refill_per_second > 0.0 {
+            let missing = 1.0 - bucket.tokens;
+            Err(Some(Duration::from_secs_f64(
+                missing / self.refill_per_second,
+            )))
+        } else {
+            Err(None)
+        }
+    }
+
+    pub(crate) fn refill_per_second(&self) -> f64 {
+        self.refill_per_second
+    }
+
+    /// Takes a token without waiting, returning whether there was one.
+    pub fn try_acquire(&self) -> bool {
+        self.take().is_ok()
+    }
+
+    /// Waits until a token is available and takes it.
+    pub async fn acquire(&self) {
+        loop {
+            match self.take() {
+                Ok(()) => return,
+                Err(Some(wait)) => tokio::time::sleep(wait).await,
+                Err(None) => std::future::pending().await,
+            }
+        }
+    }
+}
+
+/// Caps the requests in flight to each host, shared between clones of a
+/// client.
+pub(crate) struct HostLimits {
+    permits: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimits {
+    pub(crate) fn new(permits: usize) -> HostLimits {
+        HostLimits {
+            permits: permits.max(1),
+            hosts: Mutex::default(),
+        }
+    }
+
+    /// Waits until a request to the host of `url` may start. The request
+    /// counts as in flight until the permit is dropped.
+    pub(crate) async fn acquire(&self, url: &str) -> OwnedSemaphorePermit {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_default();
+        let semaphore = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.permits)))
+            .clone();
+        semaphore.acquire_owned().await.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn zero_capacity_holds_one_token() {
+        let bucket = TokenBucket::new(0, 100.0);
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+
+        // Refilled after 10 ms rather than never.
+        let acquired = block_on(async {
+            tokio::time::timeout(Duration::from_secs(1), bucket.acquire()).await
+        });
+        assert!(acquired.is_ok());
+    }
+
+    #[test]
+    fn zero_budget_allows_nothing() {
+        let budget = TokenBucket::with_capacity(0, 0.0);
+        assert!(!budget.try_acquire());
+    }
+}
@@ -0,0 +1,184 @@
+//! Which stop to walk to for the soonest bus.
+//!
+//! The nearest stop isn't always the best one: a stop a few minutes further
+//! away may have a bus coming much sooner. [`Client::recommend_stop`] ranks
+//! the stops around a point by the time to walk there plus the wait for the
+//! first bus that can still be caught.
+
+use futures::{StreamExt, stream};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::{Client, Coords, ErrorKind, Result, Station, StationBus, StationId, geo};
+
+/// Options for [`Client::recommend_stop`].
+#[derive(Debug, Clone)]
+pub struct RecommendOptions {
+    candidates: usize,
+    walking_speed_kmh: f64,
+    lines: Vec<String>,
+    concurrency: usize,
+    no_bus_penalty: Duration,
+}
+
+impl Default for RecommendOptions {
+    fn default() -> RecommendOptions {
+        RecommendOptions {
+            candidates: 3,
+            walking_speed_kmh: 4.8,
+            lines: Vec::new(),
+            concurrency: 2,
+            no_bus_penalty: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl RecommendOptions {
+    pub fn new() -> RecommendOptions {
+        RecommendOptions::default()
+    }
+
+    /// How many of the nearest stops to consider, 3 by default.
+    pub fn candidates(mut self, candidates: usize) -> RecommendOptions {
+        self.candidates = candidates;
+        self
+    }
+
+    /// Walking speed in km/h, 4.8 by default. Distances are straight
+    /// lines, so a slower speed makes up for the detours of real streets.
+    pub fn walking_speed_kmh(mut self, speed: f64) -> RecommendOptions {
+        self.walking_speed_kmh = speed;
+        self
+    }
+
+    /// Only count buses on these lines, see
+    /// [`Client::get_station_buses_for_lines`] for how they're told apart.
+    /// Any line by default.
+    pub fn lines(mut self, lines: Vec<String>) -> RecommendOptions {
+        self.lines = lines;
+        self
+    }
+
+    /// How many stops' boards to fetch at once, 2 by default.
+    pub fn concurrency(mut self, concurrency: usize) -> RecommendOptions {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// The wait counted for a stop without a bus that can be caught, or
+    /// whose board couldn't be fetched, 30 minutes by default.
+    pub fn no_bus_penalty(mut self, penalty: Duration) -> RecommendOptions {
+        self.no_bus_penalty = penalty;
+        self
+    }
+}
+
+/// What a stop's board had to offer.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "state", content = "kind", rename_all = "snake_case")]
+pub enum BoardState {
+    /// There's a bus to catch.
+    Departure,
+    /// No bus arrives after the walk there, on the wanted lines.
+    NoBus,
+    /// The board couldn't be fetched.
+    Failed(ErrorKind),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    pub station: Station,
+    pub distance_m: f64,
+    pub walk_time: Duration,
+    /// The first bus arriving after the walk, if any.
+    pub departure: Option<StationBus>,
+    /// How long the bus comes after reaching the stop, or the
+    /// [`RecommendOptions::no_bus_penalty`] without one.
+    pub wait_time: Duration,
+    pub total: Duration,
+    pub board: BoardState,
+}
+
+impl Client {
+    /// The nearest stops to `from`, best first, ranked by the walk there
+    /// plus the wait for the first bus that arrives after it.
+    ///
+    /// Stops without such a bus and stops whose board fails are ranked with
+    /// [`RecommendOptions::no_bus_penalty`] as the wait. Ties go to the
+    /// nearer stop, then the lower station id. Only fails if the stations
+    /// can't be fetched.
+    pub async fn recommend_stop(
+        &self,
+        from: Coords,
+        options: RecommendOptions,
+    ) -> Result<Vec<Recommendation>> {
+        let stations = self.get_all_stations().await?;
+        let valid: Vec<Station> = stations
+            .into_iter()
+            .filter(|station| station.is_valid_position())
+            .collect();
+        let mut nearest = geo::stations_within(&valid, &from, f64::INFINITY);
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.id.cmp(&b.0.id)));
+        nearest.truncate(options.candidates);
+
+        let lines: Vec<&str> = options.lines.iter().map(String::as_str).collect();
+        let boards: Vec<Result<Vec<StationBus>>> = stream::iter(&nearest)
+            .map(|(station, _)| async {
+                match lines.is_empty() {
+                    true => self.get_station_buses(StationId(station.id)).await,
+                    false => self
+                        .get_station_buses_for_lines(StationId(station.id), &lines)
+                        .await
+                        .map(|arrivals| arrivals.buses),
+                }
+            })
+            .buffered(options.concurrency.max(1))
+            .collect()
+            .await;
+
+        let speed_ms = options.walking_speed_kmh / 3.6;
+        let mut recommendations: Vec<Recommendation> = nearest
+            .into_iter()
+            .zip(boards)
+            .map(|((station, distance_m), board)| {
+                let walk_time = Duration::try_from_secs_f64((distance_m / speed_ms).round())
+                    .unwrap_or(Duration::MAX);
+                let (departure, board) = match board {
+                    Ok(buses) => {
+                        let departure = buses
+                            .into_iter()
+                            .filter(|bus| bus.arrive_time >= walk_time)
+                            .min();
+                        let state = match departure {
+                            Some(_) => BoardState::Departure,
+                            None => BoardState::NoBus,
+                        };
+                        (departure, state)
+                    }
+                    Err(error) => (None, BoardState::Failed(error.kind())),
+                };
+                let wait_time = match &departure {
+                    Some(bus) => bus.arrive_time - walk_time,
+                    None => options.no_bus_penalty,
+                };
+                Recommendation {
+                    station: station.clone(),
+                    distance_m,
+                    walk_time,
+                    departure,
+                    wait_time,
+                    total: walk_time.saturating_add(wait_time),
+                    board,
+                }
+            })
+            .collect();
+
+        recommendations.sort_by(|a, b| {
+            a.total
+                .cmp(&b.total)
+                .then(a.distance_m.total_cmp(&b.distance_m))
+                .then(a.station.id.cmp(&b.station.id))
+        });
+        Ok(recommendations)
+    }
+}
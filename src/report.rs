@@ -0,0 +1,110 @@
+//! Plain text reports for people running the network.
+
+use chrono::{DateTime, TimeDelta, Timelike, Utc};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+
+use crate::analytics::{PositionRecord, turkish_date, vehicle_utilization};
+use crate::{NetworkSnapshot, normalize_plate};
+
+fn turkish_time(time: DateTime<Utc>) -> String {
+    (time + TimeDelta::hours(3))
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn section(report: &mut String, title: &str) {
+    writeln!(report, "\n{title}\n{}", "-".repeat(title.chars().count())).unwrap();
+}
+
+/// A summary of a day's worth of snapshots taken with buses, as from
+/// [`Client::get_all_data`](crate::Client::get_all_data):
+///
+/// - How many different buses were seen and stations covered.
+/// - The average number of buses on a line, over every line of every
+///   snapshot that has buses for it.
+/// - The peak hour, the hour of the snapshot with the most buses out.
+/// - The distance driven, as [`vehicle_utilization`] estimates it from
+///   one snapshot to the next. The more often snapshots were taken, the
+///   closer it gets to the real distance.
+///
+/// Times are in Turkish time. Snapshots may come in any order.
+pub fn daily_summary(snapshots: &[NetworkSnapshot]) -> String {
+    let mut report = String::from("Daily summary\n=============\n");
+    let mut snapshots: Vec<&NetworkSnapshot> = snapshots.iter().collect();
+    snapshots.sort_by_key(|snapshot| snapshot.taken_at);
+    let (Some(first), Some(last)) = (snapshots.first(), snapshots.last()) else {
+        report.push_str("No snapshots.\n");
+        return report;
+    };
+    writeln!(
+        report,
+        "{} snapshots from {} to {}",
+        snapshots.len(),
+        turkish_time(first.taken_at),
+        turkish_time(last.taken_at),
+    )
+    .unwrap();
+
+    let mut plates = HashSet::new();
+    let mut stations = HashSet::new();
+    let mut line_polls = 0;
+    let mut line_buses = 0;
+    let mut peak: Option<(&NetworkSnapshot, usize)> = None;
+    let mut records = Vec::new();
+    for snapshot in &snapshots {
+        stations.extend(snapshot.stations.iter().map(|station| station.id));
+        let mut active = HashSet::new();
+        for (line, buses) in &snapshot.buses {
+            line_polls += 1;
+            line_buses += buses.len();
+            for bus in buses {
+                active.insert(normalize_plate(&bus.license_plate));
+                records.push(PositionRecord::from_bus(snapshot.taken_at, line, bus));
+            }
+        }
+        if peak.is_none_or(|(_, most)| active.len() > most) {
+            peak = Some((snapshot, active.len()));
+        }
+        plates.extend(active);
+    }
+
+    section(&mut report, "Buses");
+    writeln!(report, "Buses seen: {}", plates.len()).unwrap();
+    match line_polls {
+        0 => writeln!(report, "Average buses per line: no bus data"),
+        _ => writeln!(
+            report,
+            "Average buses per line: {:.1}",
+            line_buses as f64 / line_polls as f64
+        ),
+    }
+    .unwrap();
+    if let Some((snapshot, buses)) = peak.filter(|(_, buses)| *buses > 0) {
+        let hour = (snapshot.taken_at + TimeDelta::hours(3)).hour();
+        writeln!(
+            report,
+            "Peak hour: {hour:02}:00-{:02}:00, {buses} buses at {}",
+            (hour + 1) % 24,
+            turkish_time(snapshot.taken_at),
+        )
+        .unwrap();
+    }
+
+    section(&mut report, "Stations");
+    writeln!(report, "Stations covered: {}", stations.len()).unwrap();
+
+    let days: BTreeSet<_> = snapshots
+        .iter()
+        .map(|snapshot| turkish_date(snapshot.taken_at))
+        .collect();
+    let distance_km: f64 = days
+        .into_iter()
+        .flat_map(|day| vehicle_utilization(records.iter().cloned(), day))
+        .map(|vehicle| vehicle.distance_km)
+        .sum();
+    section(&mut report, "Distance");
+    writeln!(report, "Estimated distance driven: {distance_km:.1} km").unwrap();
+
+    report
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// Serialises each item as JSON on its own line, without a trailing newline.
+///
+/// Panics if an item can't be represented as JSON, e.g. a map with
+/// non-string keys. None of this crate's types have that problem.
+pub fn to_ndjson<T: Serialize>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| serde_json::to_string(item).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses one JSON value per line, skipping blank lines.
+pub fn from_ndjson<T: for<'de> Deserialize<'de>>(text: &str) -> Result<Vec<T>, serde_json::Error> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect()
+}
@@ -0,0 +1,391 @@
+//! Alerts for lines that should be running but have no buses out.
+//!
+//! A [`ServiceMonitor`] is fed each poll of the network, as a
+//! [`NetworkSnapshot`] from [`Client::get_all_data`](crate::Client::get_all_data),
+//! and raises an alert once a line in service has shown no buses for a
+//! while. Polls where the line couldn't be fetched count towards a separate
+//! [`ServiceAlertKind::Unreachable`] alert, so an outage of the site doesn't
+//! read as every line stopping.
+//!
+//! The monitor doesn't poll or deliver anything itself: the caller runs the
+//! polling loop and forwards the returned [`ServiceAlert`]s wherever they
+//! should go. They serialize, so they can be sent as is as a webhook or MQTT
+//! payload.
+
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::NetworkSnapshot;
+use crate::timetable::WeeklySchedule;
+
+/// When a line is expected to have buses out, in Turkish time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServiceHours {
+    /// Every day from `start` to `end`, past midnight if `end` is before
+    /// `start`.
+    Daily { start: NaiveTime, end: NaiveTime },
+    /// From the first to the last departure of each day's timetable. Days
+    /// without departures have no service.
+    Timetable(WeeklySchedule),
+}
+
+impl ServiceHours {
+    fn in_service(&self, time: NaiveDateTime) -> bool {
+        match self {
+            ServiceHours::Daily { start, end } if start <= end => {
+                (*start..*end).contains(&time.time())
+            }
+            ServiceHours::Daily { start, end } => time.time() >= *start || time.time() < *end,
+            ServiceHours::Timetable(schedule) => {
+                let times = schedule.on(time.date()).times;
+                match (times.first(), times.last()) {
+                    (Some(first), Some(last)) => (*first..=*last).contains(&time.time()),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceAlertKind {
+    /// The line was fetched and had no buses.
+    NoVehicles,
+    /// The line couldn't be fetched at all.
+    Unreachable,
+    /// The line has buses again, or its service hours ended, after one of
+    /// the other alerts.
+    Restored,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ServiceAlert {
+    pub line: String,
+    /// The first poll without buses, or for [`ServiceAlertKind::Restored`]
+    /// the poll that ended the alert.
+    pub since: DateTime<Utc>,
+    pub kind: ServiceAlertKind,
+}
+
+enum Observation {
+    Running,
+    Empty,
+    Failed,
+}
+
+#[derive(Default)]
+struct LineState {
+    down_since: Option<DateTime<Utc>>,
+    /// Whether any poll since `down_since` reached the line.
+    seen_empty: bool,
+    alerted: Option<ServiceAlertKind>,
+}
+
+/// Watches lines for gaps in service, raising an alert once a line in
+/// service has had no buses for `confirm_after` and restoring it as soon as
+/// buses show up again.
+///
+/// Only the lines given to [`ServiceMonitor::new`] are watched; any other
+/// lines in a snapshot are ignored.
+pub struct ServiceMonitor {
+    hours: HashMap<String, ServiceHours>,
+    confirm_after: TimeDelta,
+    lines: HashMap<String, LineState>,
+}
+
+impl ServiceMonitor {
+    /// Watches the lines in `hours`, by line id.
+    pub fn new(hours: HashMap<String, ServiceHours>, confirm_after: Duration) -> ServiceMonitor {
+        ServiceMonitor {
+            hours,
+            confirm_after: TimeDelta::from_std(confirm_after).unwrap_or(TimeDelta::MAX),
+            lines: HashMap::new(),
+        }
+    }
+
+    /// Takes a poll of the network with buses, as from
+    /// [`Client::get_all_data`](crate::Client::get_all_data), and returns the
+    /// alerts raised or restored by it. Lines in
+    /// [`NetworkSnapshot::failed_lines`] or missing from
+    /// [`NetworkSnapshot::buses`] count as unreachable.
+    pub fn update(&mut self, snapshot: &NetworkSnapshot) -> Vec<ServiceAlert> {
+        let observe = |line: &str| {
+            if snapshot.failed_lines.iter().any(|failed| failed == line) {
+                return Observation::Failed;
+            }
+            match snapshot.buses.get(line) {
+                Some(buses) if buses.is_empty() => Observation::Empty,
+                Some(_) => Observation::Running,
+                None => Observation::Failed,
+            }
+        };
+        self.observe(snapshot.taken_at, observe)
+    }
+
+    /// Records a poll at `at` that failed altogether, e.g. because the site
+    /// was down, which counts as every line being unreachable.
+    pub fn update_failed(&mut self, at: DateTime<Utc>) -> Vec<ServiceAlert> {
+        self.observe(at, |_| Observation::Failed)
+    }
+
+    fn observe(
+        &mut self,
+        at: DateTime<Utc>,
+        observe: impl Fn(&str) -> Observation,
+    ) -> Vec<ServiceAlert> {
+        // Turkey has kept UTC+3 all year since 2016.
+        let local = (at + TimeDelta::hours(3)).naive_utc();
+        let mut alerts = Vec::new();
+
+        for (line, hours) in &self.hours {
+            let state = self.lines.entry(line.clone()).or_default();
+            let observation = match hours.in_service(local) {
+                true => observe(line),
+                false => Observation::Running,
+            };
+            let empty = match observation {
+                Observation::Running => {
+                    if state.alerted.is_some() {
+                        alerts.push(ServiceAlert {
+                            line: line.clone(),
+                            since: at,
+                            kind: ServiceAlertKind::Restored,
+                        });
+                    }
+                    *state = LineState::default();
+                    continue;
+                }
+                Observation::Empty => true,
+                Observation::Failed => false,
+            };
+
+            let since = *state.down_since.get_or_insert(at);
+            state.seen_empty |= empty;
+            let kind = match state.seen_empty {
+                true => ServiceAlertKind::NoVehicles,
+                false => ServiceAlertKind::Unreachable,
+            };
+            if at - since >= self.confirm_after && state.alerted != Some(kind) {
+                state.alerted = Some(kind);
+                alerts.push(ServiceAlert {
+                    line: line.clone(),
+                    since,
+                    kind,
+                });
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Coords, LineBus};
+
+    /// 2025-10-10 at `hh:mm` in Turkey.
+    fn at(hh: i64, mm: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_760_043_600, 0).unwrap()
+            + TimeDelta::hours(hh)
+            + TimeDelta::minutes(mm)
+    }
+
+    fn time(hh: u32, mm: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hh, mm, 0).unwrap()
+    }
+
+    fn monitor(start: NaiveTime, end: NaiveTime) -> ServiceMonitor {
+        let hours = HashMap::from([("1".to_string(), ServiceHours::Daily { start, end })]);
+        ServiceMonitor::new(hours, Duration::from_secs(120))
+    }
+
+    fn snapshot(taken_at: DateTime<Utc>, buses: Option<usize>) -> NetworkSnapshot {
+        let bus = LineBus {
+            license_plate: "58 AC 123".to_string(),
+            coords: Coords {
+                lat: 39.75,
+                long: 37.02,
+            },
+            last_update: None,
+        };
+        NetworkSnapshot {
+            version: crate::SNAPSHOT_VERSION,
+            taken_at,
+            lines: Vec::new(),
+            stations: Vec::new(),
+            buses: buses
+                .map(|count| HashMap::from([("1".to_string(), vec![bus; count])]))
+                .unwrap_or_default(),
+            line_stations: HashMap::new(),
+            failed_lines: match buses {
+                Some(_) => Vec::new(),
+                None => vec!["1".to_string()],
+            },
+        }
+    }
+
+    fn alert(since: DateTime<Utc>, kind: ServiceAlertKind) -> Vec<ServiceAlert> {
+        vec![ServiceAlert {
+            line: "1".to_string(),
+            since,
+            kind,
+        }]
+    }
+
+    /// Polls `monitor` once a minute from `hh:mm` with the line having
+    /// `buses[i]` buses, or unreachable for `None`, and returns the alerts
+    /// of each poll.
+    fn polls(
+        monitor: &mut ServiceMonitor,
+        (hh, mm): (i64, i64),
+        buses: &[Option<usize>],
+    ) -> Vec<Vec<ServiceAlert>> {
+        buses
+            .iter()
+            .enumerate()
+            .map(|(i, buses)| monitor.update(&snapshot(at(hh, mm + i as i64), *buses)))
+            .collect()
+    }
+
+    #[test]
+    fn empty_line_is_alerted_after_the_confirmation_period() {
+        let mut monitor = monitor(time(6, 0), time(23, 0));
+        let alerts = polls(
+            &mut monitor,
+            (12, 0),
+            &[
+                Some(2),
+                Some(0),
+                Some(0),
+                Some(0),
+                Some(0),
+                Some(1),
+                Some(1),
+            ],
+        );
+        assert_eq!(
+            alerts,
+            [
+                vec![],
+                vec![],
+                vec![],
+                alert(at(12, 1), ServiceAlertKind::NoVehicles),
+                vec![],
+                alert(at(12, 5), ServiceAlertKind::Restored),
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn a_single_failed_poll_does_not_alert() {
+        let mut monitor = monitor(time(6, 0), time(23, 0));
+        let alerts = polls(
+            &mut monitor,
+            (12, 0),
+            &[
+                Some(1),
+                None,
+                Some(1),
+                Some(0),
+                Some(1),
+                None,
+                None,
+                Some(1),
+            ],
+        );
+        assert!(alerts.iter().all(Vec::is_empty), "{alerts:?}");
+    }
+
+    #[test]
+    fn site_outage_is_told_apart_from_an_empty_line() {
+        let mut monitor = monitor(time(6, 0), time(23, 0));
+        monitor.update(&snapshot(at(12, 0), Some(1)));
+
+        assert_eq!(monitor.update_failed(at(12, 1)), []);
+        assert_eq!(monitor.update_failed(at(12, 2)), []);
+        assert_eq!(
+            monitor.update_failed(at(12, 3)),
+            alert(at(12, 1), ServiceAlertKind::Unreachable)
+        );
+        assert_eq!(monitor.update_failed(at(12, 4)), []);
+
+        // The site is back but the line is empty, so it wasn't just the site.
+        assert_eq!(
+            monitor.update(&snapshot(at(12, 5), Some(0))),
+            alert(at(12, 1), ServiceAlertKind::NoVehicles)
+        );
+        assert_eq!(
+            monitor.update(&snapshot(at(12, 6), Some(3))),
+            alert(at(12, 6), ServiceAlertKind::Restored)
+        );
+    }
+
+    #[test]
+    fn lines_missing_from_the_snapshot_are_unreachable() {
+        let mut monitor = monitor(time(6, 0), time(23, 0));
+        let mut missing = snapshot(at(12, 0), None);
+        missing.failed_lines.clear();
+        monitor.update(&missing);
+        missing.taken_at = at(12, 2);
+        assert_eq!(
+            monitor.update(&missing),
+            alert(at(12, 0), ServiceAlertKind::Unreachable)
+        );
+    }
+
+    #[test]
+    fn nothing_is_expected_outside_service_hours() {
+        let mut monitor = monitor(time(6, 0), time(23, 0));
+        let alerts = polls(&mut monitor, (23, 0), &[Some(0), Some(0), Some(0), Some(0)]);
+        assert!(alerts.iter().all(Vec::is_empty), "{alerts:?}");
+
+        // An alert still open when service ends is restored.
+        let alerts = polls(
+            &mut monitor,
+            (22, 56),
+            &[Some(0), Some(0), Some(0), Some(0)],
+        );
+        assert_eq!(
+            alerts,
+            [
+                vec![],
+                vec![],
+                alert(at(22, 56), ServiceAlertKind::NoVehicles),
+                vec![],
+            ]
+        );
+        assert_eq!(
+            monitor.update(&snapshot(at(23, 0), Some(0))),
+            alert(at(23, 0), ServiceAlertKind::Restored)
+        );
+    }
+
+    #[test]
+    fn service_hours_can_run_past_midnight() {
+        let mut monitor = monitor(time(22, 0), time(2, 0));
+        let alerts = polls(&mut monitor, (23, 58), &[Some(0), Some(0), Some(0)]);
+        assert_eq!(alerts[2], alert(at(23, 58), ServiceAlertKind::NoVehicles));
+
+        let mut monitor = self::monitor(time(22, 0), time(2, 0));
+        let alerts = polls(&mut monitor, (12, 0), &[Some(0), Some(0), Some(0)]);
+        assert!(alerts.iter().all(Vec::is_empty), "{alerts:?}");
+    }
+
+    #[test]
+    fn alerts_serialize_for_notifiers() {
+        let json =
+            serde_json::to_value(&alert(at(12, 1), ServiceAlertKind::NoVehicles)[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "line": "1",
+                "since": "2025-10-10T09:01:00Z",
+                "kind": "no_vehicles",
+            })
+        );
+    }
+}
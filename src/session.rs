@@ -0,0 +1,237 @@
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{
+    Client, Error, LineBus, Result, StationBus, StationId, extract_line_id, extract_token,
+};
+
+/// A cookie jar that remembers the last `Set-Cookie` header of each cookie,
+/// so sessions can be saved and restored.
+#[derive(Default)]
+pub(crate) struct CookieLog {
+    jar: Jar,
+    headers: Mutex<HashMap<String, SavedCookie>>,
+}
+
+impl CookieLog {
+    fn record(&self, header: &str, url: &Url) {
+        let name = header.split([';', '=']).next().unwrap_or_default().trim();
+        self.headers.lock().unwrap().insert(
+            name.to_string(),
+            SavedCookie {
+                url: url.to_string(),
+                header: header.to_string(),
+            },
+        );
+    }
+}
+
+impl CookieStore for CookieLog {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let headers: Vec<&HeaderValue> = cookie_headers.collect();
+        for header in &headers {
+            if let Ok(header) = header.to_str() {
+                self.record(header, url);
+            }
+        }
+        self.jar.set_cookies(&mut headers.into_iter(), url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.jar.cookies(url)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedCookie {
+    pub url: String,
+    pub header: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedPage {
+    pub token: String,
+    pub line_id: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Everything needed to pick a session up again later, e.g. in another
+/// process. See [`Session::state`] and [`Client::restore_session`].
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct SessionState {
+    pub cookies: Vec<SavedCookie>,
+    pub pages: HashMap<String, SavedPage>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Page {
+    token: String,
+    line_id: Option<String>,
+    fetched_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct Session {
+    client: Client,
+    pages: Arc<Mutex<HashMap<String, Page>>>,
+}
+
+impl Client {
+    pub async fn open_session(&self) -> Result<Session> {
+        self.get_document("/".to_string()).await?;
+
+        Ok(Session {
+            client: self.clone(),
+            pages: Default::default(),
+        })
+    }
+
+    /// Restores a session saved with [`Session::state`] without making any
+    /// requests.
+    ///
+    /// Pages older than [`ClientBuilder::token_ttl`] are dropped. An empty
+    /// state gives a session that fetches pages as they are needed.
+    ///
+    /// [`ClientBuilder::token_ttl`]: crate::ClientBuilder::token_ttl
+    pub fn restore_session(&self, state: SessionState) -> Session {
+        for cookie in &state.cookies {
+            if let Ok(url) = Url::parse(&cookie.url) {
+                self.cookies.jar.add_cookie_str(&cookie.header, &url);
+                self.cookies.record(&cookie.header, &url);
+            }
+        }
+
+        let now = Utc::now();
+        let pages = state
+            .pages
+            .into_iter()
+            .filter_map(|(path, page)| {
+                let age = (now - page.fetched_at).to_std().unwrap_or_default();
+                if age >= self.token_ttl {
+                    return None;
+                }
+                let page = Page {
+                    token: page.token,
+                    line_id: page.line_id,
+                    fetched_at: Instant::now().checked_sub(age)?,
+                };
+                Some((path, page))
+            })
+            .collect();
+
+        Session {
+            client: self.clone(),
+            pages: Arc::new(Mutex::new(pages)),
+        }
+    }
+}
+
+impl Session {
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn clear(&self) {
+        self.pages.lock().unwrap().clear();
+    }
+
+    pub fn state(&self) -> SessionState {
+        let now = Utc::now();
+        let pages = self
+            .pages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, page)| {
+                let age = chrono::Duration::from_std(page.fetched_at.elapsed()).unwrap_or_default();
+                let page = SavedPage {
+                    token: page.token.clone(),
+                    line_id: page.line_id.clone(),
+                    fetched_at: now - age,
+                };
+                (path.clone(), page)
+            })
+            .collect();
+
+        SessionState {
+            cookies: self
+                .client
+                .cookies
+                .headers
+                .lock()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect(),
+            pages,
+        }
+    }
+
+    pub(crate) fn page_age(&self, path: &str) -> Option<Duration> {
+        let pages = self.pages.lock().unwrap();
+        pages.get(path).map(|page| page.fetched_at.elapsed())
+    }
+
+    async fn page(&self, path: &str) -> Result<(Page, bool)> {
+        let cached = self.pages.lock().unwrap().get(path).cloned();
+        match cached {
+            Some(page) => Ok((page, true)),
+            None => Ok((self.refresh_page(path).await?, false)),
+        }
+    }
+
+    pub(crate) async fn refresh_page(&self, path: &str) -> Result<Page> {
+        // Line pages go through the same redirect check as the client, so an
+        // unknown line fails with `NoLineId` instead of `NoToken`.
+        let doc = match path.strip_prefix("/hat/") {
+            Some(line) => self.client.get_line_page(line).await?,
+            None => self.client.get_document(path.to_string()).await?,
+        };
+        let page = Page {
+            token: extract_token(&doc).ok_or(Error::NoToken)?,
+            line_id: extract_line_id(&doc).map(str::to_string),
+            fetched_at: Instant::now(),
+        };
+        self.pages
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), page.clone());
+
+        Ok(page)
+    }
+
+    pub async fn get_line_buses(&self, line: &str) -> Result<Vec<LineBus>> {
+        let path = format!("/hat/{line}");
+        let (page, cached) = self.page(&path).await?;
+        let id = page.line_id.ok_or(Error::NoLineId { redirect: None })?;
+
+        match self.client.post_line_buses(&id, &page.token).await {
+            Err(error @ (Error::Request(_) | Error::Json(_))) if cached => {
+                self.client.retry_after(error)?;
+                let page = self.refresh_page(&path).await?;
+                self.client.post_line_buses(&id, &page.token).await
+            }
+            result => result,
+        }
+    }
+
+    pub async fn get_station_buses(&self, station: StationId) -> Result<Vec<StationBus>> {
+        let path = format!("/Akilli-Durak/{station}");
+        let (page, cached) = self.page(&path).await?;
+
+        match self.client.post_station_buses(station, &page.token).await {
+            Err(error @ (Error::Request(_) | Error::Json(_))) if cached => {
+                self.client.retry_after(error)?;
+                let page = self.refresh_page(&path).await?;
+                self.client.post_station_buses(station, &page.token).await
+            }
+            result => result,
+        }
+    }
+}
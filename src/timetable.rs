@@ -0,0 +1,116 @@
+use chrono::{Datelike, NaiveDate, NaiveTime, Weekday};
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Error, Result};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct WeeklySchedule {
+    pub weekday: Vec<NaiveTime>,
+    pub saturday: Vec<NaiveTime>,
+    pub sunday: Vec<NaiveTime>,
+}
+
+/// The times buses are due somewhere on a single day, sorted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Schedule {
+    pub times: Vec<NaiveTime>,
+}
+
+impl WeeklySchedule {
+    /// The departures on `date`. Holidays aren't known, so they get their
+    /// weekday's departures.
+    pub fn on(&self, date: NaiveDate) -> Schedule {
+        let times = match date.weekday() {
+            Weekday::Sat => &self.saturday,
+            Weekday::Sun => &self.sunday,
+            _ => &self.weekday,
+        };
+        Schedule {
+            times: times.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Day {
+    Weekday,
+    Saturday,
+    Sunday,
+}
+
+fn day_of_header(text: &str) -> Option<Day> {
+    let text = text.to_lowercase();
+    if text.contains("hafta") {
+        Some(Day::Weekday)
+    } else if text.contains("cumartes") {
+        Some(Day::Saturday)
+    } else if text.contains("pazar") && !text.contains("pazartes") {
+        Some(Day::Sunday)
+    } else {
+        None
+    }
+}
+
+fn cell_text(cell: ElementRef) -> String {
+    cell.text().collect::<Vec<_>>().join(" ")
+}
+
+fn extract_table_schedule(table: ElementRef, time: &Regex) -> Option<WeeklySchedule> {
+    let row_selector = Selector::parse("tr").unwrap();
+    let cell_selector = Selector::parse("th, td").unwrap();
+
+    let mut rows = table.select(&row_selector);
+    let columns: Vec<Option<Day>> = rows
+        .next()?
+        .select(&cell_selector)
+        .map(|cell| day_of_header(&cell_text(cell)))
+        .collect();
+    if columns.iter().all(Option::is_none) {
+        return None;
+    }
+
+    let mut schedule = WeeklySchedule::default();
+    for row in rows {
+        for (cell, day) in row.select(&cell_selector).zip(&columns) {
+            let Some(day) = day else {
+                continue;
+            };
+            let times = match day {
+                Day::Weekday => &mut schedule.weekday,
+                Day::Saturday => &mut schedule.saturday,
+                Day::Sunday => &mut schedule.sunday,
+            };
+            times.extend(time.captures_iter(&cell_text(cell)).filter_map(|captures| {
+                NaiveTime::from_hms_opt(captures[1].parse().ok()?, captures[2].parse().ok()?, 0)
+            }));
+        }
+    }
+
+    for times in [
+        &mut schedule.weekday,
+        &mut schedule.saturday,
+        &mut schedule.sunday,
+    ] {
+        times.sort();
+        times.dedup();
+    }
+
+    Some(schedule)
+}
+
+pub fn extract_weekly_schedule(doc: &str) -> Option<WeeklySchedule> {
+    let time = Regex::new(r"\b(\d{1,2})[:.](\d{2})\b").unwrap();
+
+    Html::parse_document(doc)
+        .select(&Selector::parse("table").unwrap())
+        .find_map(|table| extract_table_schedule(table, &time))
+}
+
+impl Client {
+    pub async fn get_line_schedule(&self, line: &str) -> Result<WeeklySchedule> {
+        let doc = self.get_line_page(line).await?;
+        extract_weekly_schedule(&doc).ok_or(Error::NoSchedule)
+    }
+}
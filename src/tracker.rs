@@ -0,0 +1,123 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::{Coords, LineBus, NetworkSnapshot, normalize_plate};
+
+/// Where a bus was at the time of a record.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VehicleObservation {
+    pub at: DateTime<Utc>,
+    pub coords: Coords,
+}
+
+/// Keeps the recent positions of each bus across polls or snapshots, keyed
+/// by [`normalize_plate`].
+///
+/// Positions older than the retention are dropped, except the newest one
+/// before the cutoff, so a window of `retention` always has a starting point.
+pub struct VehicleTracker {
+    retention: TimeDelta,
+    latest: Option<DateTime<Utc>>,
+    positions: HashMap<String, VecDeque<VehicleObservation>>,
+}
+
+impl VehicleTracker {
+    pub fn new(retention: Duration) -> VehicleTracker {
+        VehicleTracker {
+            retention: TimeDelta::from_std(retention).unwrap_or(TimeDelta::MAX),
+            latest: None,
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Records the buses seen at `at`. Records should be made in time order.
+    pub fn record<'a>(&mut self, at: DateTime<Utc>, buses: impl IntoIterator<Item = &'a LineBus>) {
+        for bus in buses {
+            let positions = self
+                .positions
+                .entry(normalize_plate(&bus.license_plate))
+                .or_default();
+            positions.push_back(VehicleObservation {
+                at,
+                coords: bus.coords.clone(),
+            });
+            // Popping from the front keeps it contiguous, so `history` can
+            // hand out the first slice.
+            positions.make_contiguous();
+        }
+        self.latest = self.latest.max(Some(at));
+        self.prune();
+    }
+
+    pub fn record_snapshot(&mut self, snapshot: &NetworkSnapshot) {
+        self.record(snapshot.taken_at, snapshot.buses.values().flatten());
+    }
+
+    fn prune(&mut self) {
+        let Some(cutoff) = self
+            .latest
+            .and_then(|latest| latest.checked_sub_signed(self.retention))
+        else {
+            return;
+        };
+        self.positions.retain(|_, positions| {
+            while positions
+                .get(1)
+                .is_some_and(|observation| observation.at <= cutoff)
+            {
+                positions.pop_front();
+            }
+            positions
+                .back()
+                .is_some_and(|observation| observation.at >= cutoff)
+        });
+    }
+
+    /// The positions recorded for a bus, oldest first, going back as far as
+    /// the retention. Empty for a bus that hasn't been seen.
+    pub fn history(&self, plate: &str) -> &[VehicleObservation] {
+        self.positions
+            .get(&normalize_plate(plate))
+            .map_or(&[], |positions| positions.as_slices().0)
+    }
+
+    /// Normalized plates of the buses seen in the latest record that haven't moved more
+    /// than `min_move_m` from where they were `threshold` ago, likely parked
+    /// or with a stuck GPS.
+    ///
+    /// Buses tracked for less than `threshold` aren't reported, and neither
+    /// are any if `threshold` is longer than the retention.
+    pub fn stale_vehicles(&self, threshold: Duration, min_move_m: f64) -> Vec<String> {
+        let (Some(latest), Ok(threshold)) = (self.latest, TimeDelta::from_std(threshold)) else {
+            return Vec::new();
+        };
+        let Some(since) = latest.checked_sub_signed(threshold) else {
+            return Vec::new();
+        };
+
+        let mut plates: Vec<String> = self
+            .positions
+            .iter()
+            .filter(|(_, positions)| {
+                let Some(current) = positions.back() else {
+                    return false;
+                };
+                let Some(start) = positions
+                    .iter()
+                    .rposition(|observation| observation.at <= since)
+                else {
+                    return false;
+                };
+                current.at == latest
+                    && positions.range(start..).all(|observation| {
+                        observation.coords.distance_m(&current.coords) <= min_move_m
+                    })
+            })
+            .map(|(plate, _)| plate.clone())
+            .collect();
+        plates.sort();
+        plates
+    }
+}
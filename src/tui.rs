@@ -0,0 +1,387 @@
+//! A live terminal dashboard for one line.
+//!
+//! The dashboard shows a map of the line's stations with its buses on top,
+//! the arrivals at the selected station and when the data was last updated.
+//! Up and down pick the station, `r` refreshes and `q` quits.
+
+use chrono::{DateTime, Local};
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::Line as TextLine;
+use ratatui::widgets::canvas::{Canvas, Points};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::{Client, Error, LineBus, Station, StationBus, StationId};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(20);
+/// Space left around the stations on the map, in degrees.
+const MAP_MARGIN: f64 = 0.002;
+/// How often the input thread checks whether the dashboard has closed.
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+enum Event {
+    Key(KeyEvent),
+    Resize,
+    Tick,
+    Stations(Result<Vec<Station>, Error>),
+    Buses(Result<Vec<LineBus>, Error>),
+    Arrivals {
+        station: i32,
+        result: Result<Vec<StationBus>, Error>,
+    },
+}
+
+struct Dashboard {
+    line: String,
+    stations: Vec<Station>,
+    selected: usize,
+    buses: Vec<LineBus>,
+    arrivals: Option<Vec<StationBus>>,
+    updated: Option<DateTime<Local>>,
+    last_error: Option<String>,
+    /// Fetches started and not yet answered.
+    fetching: usize,
+}
+
+impl Dashboard {
+    fn station(&self) -> Option<&Station> {
+        self.stations.get(self.selected)
+    }
+
+    fn failed(&mut self, error: Error) {
+        self.last_error = Some(error.to_string());
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [body, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [map, side] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(body);
+        let [stations, arrivals] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+
+        self.draw_map(frame, map);
+        self.draw_stations(frame, stations);
+        self.draw_arrivals(frame, arrivals);
+        frame.render_widget(Paragraph::new(self.status()), status);
+    }
+
+    fn draw_map(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(format!("Line {}", self.line));
+        if self.stations.is_empty() {
+            frame.render_widget(Paragraph::new("loading...").block(block), area);
+            return;
+        }
+
+        let bounds = |coord: fn(&Station) -> f64| {
+            let (min, max) = self
+                .stations
+                .iter()
+                .map(coord)
+                .fold((f64::MAX, f64::MIN), |(min, max), x| {
+                    (min.min(x), max.max(x))
+                });
+            [min - MAP_MARGIN, max + MAP_MARGIN]
+        };
+        let stations: Vec<(f64, f64)> = self
+            .stations
+            .iter()
+            .map(|station| (station.coords.long, station.coords.lat))
+            .collect();
+        let selected = self
+            .station()
+            .map(|station| (station.coords.long, station.coords.lat));
+
+        let canvas = Canvas::default()
+            .block(block)
+            .marker(Marker::Braille)
+            .x_bounds(bounds(|station| station.coords.long))
+            .y_bounds(bounds(|station| station.coords.lat))
+            .paint(|ctx| {
+                ctx.draw(&Points {
+                    coords: &stations,
+                    color: Color::Gray,
+                });
+                if let Some(selected) = selected {
+                    ctx.draw(&Points {
+                        coords: &[selected],
+                        color: Color::Yellow,
+                    });
+                }
+                ctx.layer();
+                for bus in &self.buses {
+                    ctx.print(bus.coords.long, bus.coords.lat, "🚌");
+                }
+            });
+        frame.render_widget(canvas, area);
+    }
+
+    fn draw_stations(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .stations
+            .iter()
+            .map(|station| ListItem::new(station.human_name.clone()))
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title("Stations"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_arrivals(&self, frame: &mut Frame, area: Rect) {
+        let title = match self.station() {
+            Some(station) => format!("Arrivals at {}", station.human_name),
+            None => "Arrivals".to_string(),
+        };
+        let block = Block::bordered().title(title);
+        let Some(arrivals) = &self.arrivals else {
+            frame.render_widget(Paragraph::new("loading...").block(block), area);
+            return;
+        };
+
+        let rows = arrivals.iter().map(|bus| {
+            let arrives = match bus.arrive_time.as_secs() / 60 {
+                0 => "now".to_string(),
+                mins => format!("{mins} min"),
+            };
+            Row::new([bus.license_plate.clone(), arrives])
+        });
+        let table = Table::new(rows, [Constraint::Length(12), Constraint::Min(0)])
+            .header(
+                Row::new(["Plate", "Arrives in"]).style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .block(block);
+        frame.render_widget(table, area);
+    }
+
+    fn status(&self) -> TextLine<'static> {
+        let updated = match self.updated {
+            Some(time) => time.format("%H:%M:%S").to_string(),
+            None => "never".to_string(),
+        };
+        let mut text = format!(" updated {updated}");
+        let mut color = Color::Green;
+        if let Some(error) = &self.last_error {
+            text.push_str(&format!(" | {error}"));
+            color = Color::Red;
+        }
+        text.push_str(" | q quit, up/down station, r refresh");
+        TextLine::styled(text, Style::new().fg(color))
+    }
+}
+
+/// The thread reading the terminal's input. Dropping it stops the thread
+/// and waits for it, so input after the dashboard closes is left for
+/// whatever reads the terminal next.
+struct Input {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn spawn_input(events: mpsc::UnboundedSender<Event>) -> Input {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let thread = std::thread::spawn(move || {
+        while !stopped.load(Ordering::Relaxed) {
+            match event::poll(INPUT_POLL) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(_) => break,
+            }
+            let event = match event::read() {
+                Ok(TermEvent::Key(key)) => Event::Key(key),
+                Ok(TermEvent::Resize(..)) => Event::Resize,
+                Ok(_) => continue,
+                Err(_) => break,
+            };
+            if events.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    Input {
+        stop,
+        thread: Some(thread),
+    }
+}
+
+fn spawn_ticker(interval: Duration, events: mpsc::UnboundedSender<Event>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if events.send(Event::Tick).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Sends the event `fetch` resolves to once it's done, in the background.
+fn spawn_fetch(
+    dashboard: &mut Dashboard,
+    events: &mpsc::UnboundedSender<Event>,
+    fetch: impl Future<Output = Event> + Send + 'static,
+) {
+    dashboard.fetching += 1;
+    let events = events.clone();
+    tokio::spawn(async move {
+        events.send(fetch.await).ok();
+    });
+}
+
+/// Fetches the line's buses, its stations until they've loaded, and the
+/// arrivals at the selected station. Does nothing while an earlier fetch is
+/// still running, so slow responses don't pile up.
+fn refresh(client: &Arc<Client>, dashboard: &mut Dashboard, events: &mpsc::UnboundedSender<Event>) {
+    if dashboard.fetching > 0 {
+        return;
+    }
+    let (buses_client, line) = (client.clone(), dashboard.line.clone());
+    spawn_fetch(dashboard, events, async move {
+        Event::Buses(buses_client.get_line_buses(&line).await)
+    });
+
+    if dashboard.stations.is_empty() {
+        let (client, line) = (client.clone(), dashboard.line.clone());
+        spawn_fetch(dashboard, events, async move {
+            Event::Stations(client.get_stations(&line).await)
+        });
+    } else {
+        fetch_arrivals(client, dashboard, events);
+    }
+}
+
+fn fetch_arrivals(
+    client: &Arc<Client>,
+    dashboard: &mut Dashboard,
+    events: &mpsc::UnboundedSender<Event>,
+) {
+    let Some(station) = dashboard.station().map(|station| station.id) else {
+        return;
+    };
+    let client = client.clone();
+    spawn_fetch(dashboard, events, async move {
+        let result = client.get_station_buses(StationId(station)).await;
+        Event::Arrivals { station, result }
+    });
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    client: Arc<Client>,
+    mut dashboard: Dashboard,
+    interval: Duration,
+) -> std::io::Result<()> {
+    let (sender, mut events) = mpsc::unbounded_channel();
+    let _input = spawn_input(sender.clone());
+    spawn_ticker(interval, sender.clone());
+
+    terminal.draw(|frame| dashboard.draw(frame))?;
+    while let Some(event) = events.recv().await {
+        if matches!(
+            event,
+            Event::Stations(_) | Event::Buses(_) | Event::Arrivals { .. }
+        ) {
+            dashboard.fetching -= 1;
+        }
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Release => continue,
+            Event::Key(key) => {
+                let selected = match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('r') => {
+                        refresh(&client, &mut dashboard, &sender);
+                        continue;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => dashboard.selected.saturating_sub(1),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        (dashboard.selected + 1).min(dashboard.stations.len().saturating_sub(1))
+                    }
+                    _ => continue,
+                };
+                if selected == dashboard.selected {
+                    continue;
+                }
+                dashboard.selected = selected;
+                dashboard.arrivals = None;
+                fetch_arrivals(&client, &mut dashboard, &sender);
+            }
+            Event::Resize => {}
+            Event::Tick => {
+                refresh(&client, &mut dashboard, &sender);
+                continue;
+            }
+            Event::Stations(Ok(stations)) => {
+                dashboard.stations = stations;
+                fetch_arrivals(&client, &mut dashboard, &sender);
+            }
+            Event::Buses(Ok(buses)) => {
+                dashboard.buses = buses;
+                dashboard.updated = Some(Local::now());
+                dashboard.last_error = None;
+            }
+            Event::Arrivals { station, result } => {
+                if dashboard.station().map(|station| station.id) != Some(station) {
+                    continue;
+                }
+                match result {
+                    Ok(arrivals) => dashboard.arrivals = Some(arrivals),
+                    Err(error) => dashboard.failed(error),
+                }
+            }
+            Event::Stations(Err(error)) | Event::Buses(Err(error)) => dashboard.failed(error),
+        }
+        terminal.draw(|frame| dashboard.draw(frame))?;
+    }
+
+    Ok(())
+}
+
+/// Runs the dashboard for `line` until the user quits, refreshing every 20
+/// seconds. Failed refreshes are shown in the status bar and retried.
+pub async fn run_dashboard(client: Arc<Client>, line: &str) -> std::io::Result<()> {
+    run_dashboard_with(client, line, DEFAULT_INTERVAL).await
+}
+
+/// Like [`run_dashboard`], refreshing every `interval`.
+pub async fn run_dashboard_with(
+    client: Arc<Client>,
+    line: &str,
+    interval: Duration,
+) -> std::io::Result<()> {
+    let dashboard = Dashboard {
+        line: line.to_string(),
+        stations: Vec::new(),
+        selected: 0,
+        buses: Vec::new(),
+        arrivals: None,
+        updated: None,
+        last_error: None,
+        fetching: 0,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, client, dashboard, interval).await;
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,289 @@
+use chrono::Utc;
+use futures::stream;
+use futures::{Stream, StreamExt};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::diff::{MOVE_THRESHOLD_M, diff_positions_with};
+use crate::eta::EtaSmoother;
+use crate::session::Session;
+use crate::{Client, LineBus, Result, StationBus, StationId, normalize_plate};
+
+const MIN_REFRESH_WAIT: Duration = Duration::from_secs(1);
+/// How far past the threshold a bus's arrival time has to go back up before
+/// it can be alerted again, so that an estimate flapping between two
+/// minutes doesn't alert every other poll.
+const ALERT_HYSTERESIS: Duration = Duration::from_secs(60);
+
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn spawn_token_refresher(session: Session, path: String) -> AbortOnDrop {
+    let ttl = session.client().token_ttl;
+    let lead = session.client().token_refresh_lead;
+    let refresh_at = ttl.saturating_sub(lead);
+
+    AbortOnDrop(tokio::spawn(async move {
+        loop {
+            let age = session.page_age(&path);
+            let wait = refresh_at.saturating_sub(age.unwrap_or_default());
+            tokio::time::sleep(wait.max(MIN_REFRESH_WAIT)).await;
+
+            if session.page_age(&path).is_some_and(|age| age >= refresh_at) {
+                let _ = session.refresh_page(&path).await;
+            }
+        }
+    }))
+}
+
+/// How a watch stream polls.
+#[derive(Clone, Copy, Debug)]
+pub struct WatchOptions {
+    interval: Duration,
+    changes_only: bool,
+    min_move_m: f64,
+}
+
+impl WatchOptions {
+    pub fn new(interval: Duration) -> WatchOptions {
+        WatchOptions {
+            interval,
+            changes_only: false,
+            min_move_m: MOVE_THRESHOLD_M,
+        }
+    }
+
+    /// Only yield results that differ meaningfully from the last one
+    /// yielded. Errors are always yielded.
+    pub fn changes_only(mut self, changes_only: bool) -> WatchOptions {
+        self.changes_only = changes_only;
+        self
+    }
+
+    /// How far a bus has to move for a line's buses to count as changed,
+    /// [`MOVE_THRESHOLD_M`] by default.
+    pub fn min_move_m(mut self, min_move_m: f64) -> WatchOptions {
+        self.min_move_m = min_move_m;
+        self
+    }
+}
+
+struct Watch {
+    client: Client,
+    path: String,
+    interval: Duration,
+    ticker: Option<Interval>,
+    session: Option<(Session, AbortOnDrop)>,
+}
+
+impl Watch {
+    fn new(client: &Client, path: String, interval: Duration) -> Watch {
+        Watch {
+            client: client.clone(),
+            path,
+            interval,
+            ticker: None,
+            session: None,
+        }
+    }
+
+    async fn tick(&mut self) {
+        let interval = self.interval;
+        let ticker = self.ticker.get_or_insert_with(|| {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker
+        });
+        ticker.tick().await;
+    }
+
+    async fn session(&mut self) -> Result<&Session> {
+        if self.session.is_none() {
+            let session = self.client.open_session().await?;
+            let refresher = spawn_token_refresher(session.clone(), self.path.clone());
+            self.session = Some((session, refresher));
+        }
+        Ok(&self.session.as_ref().unwrap().0)
+    }
+}
+
+/// The arrivals as (plate, minutes away), sorted, so that a bus getting a
+/// few seconds closer doesn't count as a change.
+fn arrivals_key(buses: &[StationBus]) -> Vec<(&str, u64)> {
+    let mut key: Vec<(&str, u64)> = buses
+        .iter()
+        .map(|bus| (bus.license_plate.as_str(), bus.arrive_time.as_secs() / 60))
+        .collect();
+    key.sort();
+    key
+}
+
+impl Client {
+    /// Polls a line's buses every `interval` over a single session.
+    ///
+    /// The line page's token is refreshed in the background shortly before
+    /// it is expected to expire, see [`ClientBuilder::token_ttl`] and
+    /// [`ClientBuilder::token_refresh_lead`], so polls don't wait on a page
+    /// fetch.
+    ///
+    /// [`ClientBuilder::token_ttl`]: crate::ClientBuilder::token_ttl
+    /// [`ClientBuilder::token_refresh_lead`]: crate::ClientBuilder::token_refresh_lead
+    pub fn watch_line_buses(
+        &self,
+        line: &str,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<Vec<LineBus>>> + Send + 'static {
+        self.watch_line_buses_with(line, WatchOptions::new(interval))
+    }
+
+    /// Like [`Client::watch_line_buses`], with [`WatchOptions`].
+    ///
+    /// With [`WatchOptions::changes_only`], the buses only count as changed
+    /// when [`diff_positions_with`](crate::diff::diff_positions_with) finds
+    /// a difference, i.e. a bus comes or goes or moves at least
+    /// [`WatchOptions::min_move_m`].
+    pub fn watch_line_buses_with(
+        &self,
+        line: &str,
+        options: WatchOptions,
+    ) -> impl Stream<Item = Result<Vec<LineBus>>> + Send + 'static {
+        let watch = Watch::new(self, format!("/hat/{line}"), options.interval);
+        let line = line.to_string();
+        let last: Option<Vec<LineBus>> = None;
+
+        stream::unfold(
+            (watch, line, last),
+            move |(mut watch, line, mut last)| async move {
+                loop {
+                    watch.tick().await;
+                    let result = match watch.session().await {
+                        Ok(session) => session.get_line_buses(&line).await,
+                        Err(error) => Err(error),
+                    };
+
+                    if let Ok(buses) = &result {
+                        let unchanged = last.as_ref().is_some_and(|last| {
+                            diff_positions_with(last, buses, options.min_move_m).is_empty()
+                        });
+                        if options.changes_only && unchanged {
+                            continue;
+                        }
+                        last = Some(buses.clone());
+                    }
+                    return Some((result, (watch, line, last)));
+                }
+            },
+        )
+    }
+
+    /// Polls a station's arrivals every `interval` over a single session,
+    /// like [`Client::watch_line_buses`].
+    pub fn watch_station_buses(
+        &self,
+        station: StationId,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<Vec<StationBus>>> + Send + 'static {
+        self.watch_station_buses_with(station, WatchOptions::new(interval))
+    }
+
+    /// Like [`Client::watch_station_buses`], with [`WatchOptions`].
+    ///
+    /// With [`WatchOptions::changes_only`], a board only counts as changed
+    /// when a bus comes or goes or its arrival time changes by at least a
+    /// minute.
+    pub fn watch_station_buses_with(
+        &self,
+        station: StationId,
+        options: WatchOptions,
+    ) -> impl Stream<Item = Result<Vec<StationBus>>> + Send + 'static {
+        let watch = Watch::new(self, format!("/Akilli-Durak/{station}"), options.interval);
+        let last: Option<Vec<StationBus>> = None;
+
+        stream::unfold((watch, last), move |(mut watch, mut last)| async move {
+            loop {
+                watch.tick().await;
+                let result = match watch.session().await {
+                    Ok(session) => session.get_station_buses(station).await,
+                    Err(error) => Err(error),
+                };
+
+                if let Ok(buses) = &result {
+                    let unchanged = last
+                        .as_ref()
+                        .is_some_and(|last| arrivals_key(last) == arrivals_key(buses));
+                    if options.changes_only && unchanged {
+                        continue;
+                    }
+                    last = Some(buses.clone());
+                }
+                return Some((result, (watch, last)));
+            }
+        })
+    }
+
+    /// Like [`Client::watch_station_buses_with`], with the arrival times
+    /// smoothed by `smoother`.
+    pub fn watch_station_buses_smoothed(
+        &self,
+        station: StationId,
+        options: WatchOptions,
+        mut smoother: EtaSmoother,
+    ) -> impl Stream<Item = Result<Vec<StationBus>>> + Send + 'static {
+        self.watch_station_buses_with(station, options)
+            .map(move |result| result.map(|buses| smoother.update(Utc::now(), &buses)))
+    }
+}
+
+impl Client {
+    /// Polls a station every `interval` like [`Client::watch_station_buses`]
+    /// and yields a bus once when its arrival time gets down to `threshold`
+    /// or less. With a `plate`, only that bus is watched.
+    ///
+    /// A bus is alerted again only after it's left the board or its arrival
+    /// time has gone back up to over a minute past `threshold`. Errors are
+    /// yielded as they happen.
+    pub fn arrival_alert(
+        &self,
+        station: StationId,
+        plate: Option<String>,
+        threshold: Duration,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<StationBus>> + Send + 'static {
+        let plate = plate.map(|plate| normalize_plate(&plate));
+        let mut alerted: HashSet<String> = HashSet::new();
+
+        self.watch_station_buses(station, interval)
+            .map(move |result| {
+                let buses = match result {
+                    Ok(buses) => buses,
+                    Err(error) => return vec![Err(error)],
+                };
+                let buses: Vec<(String, StationBus)> = buses
+                    .into_iter()
+                    .map(|bus| (normalize_plate(&bus.license_plate), bus))
+                    .filter(|(key, _)| plate.as_ref().is_none_or(|plate| plate == key))
+                    .collect();
+
+                alerted.retain(|alerted| {
+                    buses.iter().any(|(key, bus)| {
+                        key == alerted && bus.arrive_time <= threshold + ALERT_HYSTERESIS
+                    })
+                });
+                buses
+                    .into_iter()
+                    .filter(|(key, bus)| {
+                        bus.arrive_time <= threshold && alerted.insert(key.clone())
+                    })
+                    .map(|(_, bus)| Ok(bus))
+                    .collect()
+            })
+            .flat_map(stream::iter)
+    }
+}
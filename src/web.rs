@@ -0,0 +1,170 @@
+//! A small REST API over [`Client`].
+//!
+//! | Route | Response |
+//! | --- | --- |
+//! | `GET /lines` | [`Client::get_lines`] |
+//! | `GET /stations` | [`Client::get_all_stations`] |
+//! | `GET /stations?line=<id>` | [`Client::get_stations`] |
+//! | `GET /buses?line=<id>` | [`Client::get_line_buses`] |
+//! | `GET /arrivals?station=<id>` | [`Client::get_station_buses`] |
+//!
+//! Each route allows one request per second per client IP, answering 429
+//! otherwise. Errors are JSON objects like `{"error": {"code", "kind", "message"}}`,
+//! using the same [`ApiError`] shape as the `envelope` feature.
+
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{MethodRouter, get};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_governor::GovernorLayer;
+use tower_governor::governor::GovernorConfigBuilder;
+
+use crate::envelope::ApiError;
+use crate::{Client, Error, ErrorKind, Line, LineBus, Station, StationBus, StationId};
+
+const LIMITER_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct WebError(Error);
+
+impl IntoResponse for WebError {
+    fn into_response(self) -> Response {
+        let status = match self.0.kind() {
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::Network if matches!(self.0, Error::Timeout) => StatusCode::GATEWAY_TIMEOUT,
+            ErrorKind::Network | ErrorKind::Parse | ErrorKind::TokenMissing => {
+                StatusCode::BAD_GATEWAY
+            }
+        };
+        let body = ErrorBody {
+            error: ApiError::from(&self.0),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<Error> for WebError {
+    fn from(error: Error) -> WebError {
+        WebError(error)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: ApiError,
+}
+
+type ApiResult<T> = Result<Json<T>, WebError>;
+
+#[derive(Deserialize)]
+struct StationsQuery {
+    line: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LineQuery {
+    line: String,
+}
+
+#[derive(Deserialize)]
+struct StationQuery {
+    station: i32,
+}
+
+async fn lines(State(client): State<Arc<Client>>) -> ApiResult<Vec<Line>> {
+    Ok(Json(client.get_lines().await?))
+}
+
+async fn stations(
+    State(client): State<Arc<Client>>,
+    Query(query): Query<StationsQuery>,
+) -> ApiResult<Vec<Station>> {
+    let stations = match query.line {
+        Some(line) => client.get_stations(&line).await?,
+        None => client.get_all_stations().await?,
+    };
+    Ok(Json(stations))
+}
+
+async fn buses(
+    State(client): State<Arc<Client>>,
+    Query(query): Query<LineQuery>,
+) -> ApiResult<Vec<LineBus>> {
+    Ok(Json(client.get_line_buses(&query.line).await?))
+}
+
+async fn arrivals(
+    State(client): State<Arc<Client>>,
+    Query(query): Query<StationQuery>,
+) -> ApiResult<Vec<StationBus>> {
+    Ok(Json(
+        client.get_station_buses(StationId(query.station)).await?,
+    ))
+}
+
+/// The per-route limiters, kept so [`serve`] can forget stale client IPs.
+#[derive(Default)]
+struct Limiters(Vec<Box<dyn Fn() + Send + Sync>>);
+
+impl Limiters {
+    /// Limits `route` to one request per second per client IP, with its own
+    /// limiter so busy routes don't starve the others.
+    fn rate_limited(&mut self, route: MethodRouter<Arc<Client>>) -> MethodRouter<Arc<Client>> {
+        let config = GovernorConfigBuilder::default()
+            .per_second(1)
+            .burst_size(1)
+            .finish()
+            .unwrap();
+
+        let limiter = config.limiter().clone();
+        self.0.push(Box::new(move || limiter.retain_recent()));
+
+        route.layer(GovernorLayer::<_, _, Body>::new(config))
+    }
+
+    fn retain_recent(&self) {
+        for retain_recent in &self.0 {
+            retain_recent();
+        }
+    }
+}
+
+/// Aborts the task when dropped, so it can't outlive the server.
+struct AbortOnDrop(tokio::task::JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Serves the API on `addr` until the server fails.
+pub async fn serve(client: Arc<Client>, addr: SocketAddr) -> std::io::Result<()> {
+    let mut limiters = Limiters::default();
+    let app = Router::new()
+        .route("/lines", limiters.rate_limited(get(lines)))
+        .route("/stations", limiters.rate_limited(get(stations)))
+        .route("/buses", limiters.rate_limited(get(buses)))
+        .route("/arrivals", limiters.rate_limited(get(arrivals)))
+        .with_state(client);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let _cleanup = AbortOnDrop(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(LIMITER_CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+            limiters.retain_recent();
+        }
+    }));
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+}
@@ -0,0 +1,1415 @@
+mod common;
+
+use assert_cmd::Command;
+use serde_json::Value;
+use sivasbus::{Line, LineBus, Station, StationBus};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::TempDir;
+
+use common::{Response, StubSite};
+
+/// The CLI in `lang`, with its config and cache in `home` so the tests
+/// don't see the user's own.
+fn cli_in(lang: &str, home: &TempDir) -> Command {
+    let mut command = Command::cargo_bin("sivasbus").unwrap();
+    command
+        .env_clear()
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join("config"))
+        .env("XDG_CACHE_HOME", home.path().join("cache"))
+        .args(["--lang", lang, "--throttle-ms", "0"]);
+    command
+}
+
+/// [`cli_in`] English.
+fn cli(home: &TempDir) -> Command {
+    cli_in("en", home)
+}
+
+/// [`cli`] pointed at `site`.
+fn sivasbus(site: &StubSite, home: &TempDir) -> Command {
+    let mut command = cli(home);
+    command.args(["--base-url", site.url()]);
+    command
+}
+
+fn stdout_json(command: &mut Command) -> Value {
+    let output = command.assert().success().get_output().stdout.clone();
+    serde_json::from_slice(&output).unwrap()
+}
+
+fn stderr(command: &mut Command, code: i32) -> String {
+    let output = command.assert().code(code).get_output().stderr.clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn lines() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let lines = stdout_json(sivasbus(&site, &home).args(["lines", "--format", "json"]));
+    assert_eq!(
+        lines,
+        serde_json::json!([
+            {"id": "1", "human_name": "1 - Otogar Merkez"},
+            {"id": "7A", "human_name": "7A - Kampüs"},
+        ])
+    );
+}
+
+#[test]
+fn stations_of_a_line() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let stations =
+        stdout_json(sivasbus(&site, &home).args(["stations", "--line", "7A", "--format", "json"]));
+    let ids: Vec<&Value> = stations
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| &s["id"])
+        .collect();
+    assert_eq!(ids, [12, 13]);
+    assert_eq!(site.count("GET", "/hat/7A"), 1);
+}
+
+#[test]
+fn every_station_without_a_line() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let stations = stdout_json(sivasbus(&site, &home).args(["stations", "--format", "json"]));
+    assert_eq!(stations.as_array().unwrap().len(), 4);
+    assert_eq!(site.count("GET", "/Akilli-Duraklar-Harita"), 1);
+}
+
+#[test]
+fn arrivals_at_a_station() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let arrivals = stdout_json(sivasbus(&site, &home).args(["arrivals", "10", "--format", "json"]));
+    assert_eq!(arrivals[0]["license_plate"], "58 AC 123");
+    assert_eq!(arrivals[0]["arrive_time"]["secs"], 240);
+    assert_eq!(arrivals[1]["license_plate"], "58 AB 999");
+
+    let posts: Vec<_> = site
+        .requests()
+        .into_iter()
+        .filter(|request| request.path == "/durakTekrar")
+        .collect();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].param("drkID").as_deref(), Some("10"));
+    assert_eq!(
+        posts[0].param("__RequestVerificationToken").as_deref(),
+        Some(common::TOKEN)
+    );
+}
+
+#[test]
+fn buses_on_a_line() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let buses = stdout_json(sivasbus(&site, &home).args(["buses", "1", "--format", "json"]));
+    let plates: Vec<&Value> = buses
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| &b["license_plate"])
+        .collect();
+    assert_eq!(plates, ["58 AC 123", "58 AB 999"]);
+}
+
+#[test]
+fn unknown_line_exits_with_not_found() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let stderr = stderr(sivasbus(&site, &home).args(["buses", "999"]), 3);
+    assert!(stderr.starts_with("error: line id not found"), "{stderr}");
+}
+
+#[test]
+fn unreachable_site_exits_with_network_error() {
+    let home = TempDir::new().unwrap();
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", closed.local_addr().unwrap());
+    drop(closed);
+
+    let stderr = stderr(cli(&home).args(["--base-url", &url, "lines"]), 4);
+    assert!(stderr.starts_with("error: request error"), "{stderr}");
+}
+
+fn stdout(command: &mut Command) -> String {
+    let output = command.assert().success().get_output().stdout.clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn lines_table() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let table = stdout(sivasbus(&site, &home).args(["lines", "--format", "table"]));
+    assert_eq!(
+        table,
+        "\
+┌────────────────────────┐
+│ ID   Name              │
+╞════════════════════════╡
+│ 1    1 - Otogar Merkez │
+│ 7A   7A - Kampüs       │
+└────────────────────────┘
+"
+    );
+}
+
+#[test]
+fn stations_table() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let table =
+        stdout(sivasbus(&site, &home).args(["stations", "--line", "1", "--format", "table"]));
+    assert_eq!(
+        table,
+        "\
+┌────────────────────────────────────────────┐
+│ ID   Name                 Lat       Long   │
+╞════════════════════════════════════════════╡
+│ 10   Cumhuriyet Meydanı   39.7505   37.015 │
+│ 11   Hükümet Konağı       39.752    37.018 │
+│ 12   Otogar               39.77     37.04  │
+└────────────────────────────────────────────┘
+"
+    );
+}
+
+#[test]
+fn arrivals_table_humanizes_durations() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let table = stdout(sivasbus(&site, &home).args(["arrivals", "10", "--format", "table"]));
+    assert_eq!(
+        table,
+        "\
+┌────────────────────────┐
+│ Plate       Arrives in │
+╞════════════════════════╡
+│ 58 AC 123   4 min      │
+│ 58 AB 999   11 min     │
+└────────────────────────┘
+"
+    );
+}
+
+#[test]
+fn buses_csv_has_a_header_row() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let csv = stdout(sivasbus(&site, &home).args(["buses", "1", "--format", "csv"]));
+    assert_eq!(
+        csv,
+        "license_plate,lat,long\n58 AC 123,39.751,37.016\n58 AB 999,39.76,37.03\n"
+    );
+}
+
+#[test]
+fn json_matches_the_library_types() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let json = |args: &[&str]| {
+        sivasbus(&site, &home)
+            .args(args)
+            .args(["--format", "json"])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone()
+    };
+
+    let lines: Vec<Line> = serde_json::from_slice(&json(&["lines"])).unwrap();
+    assert_eq!(lines[1].id, "7A");
+
+    let stations: Vec<Station> =
+        serde_json::from_slice(&json(&["stations", "--line", "1"])).unwrap();
+    assert_eq!(stations[0].human_name, "Cumhuriyet Meydanı");
+    assert_eq!(stations[0].coords.lat, 39.7505);
+
+    let buses: Vec<LineBus> = serde_json::from_slice(&json(&["buses", "7A"])).unwrap();
+    assert_eq!(buses[0].license_plate, "58 KP 007");
+
+    let arrivals: Vec<StationBus> = serde_json::from_slice(&json(&["arrivals", "10"])).unwrap();
+    assert_eq!(arrivals[1].arrive_time, Duration::from_secs(11 * 60));
+}
+
+/// Runs a `--watch` command for `secs`, long enough for a couple of
+/// refreshes at the one second interval, and returns what it printed.
+fn watch_for(command: &mut Command, secs: f64) -> String {
+    let output = command
+        .args(["--interval", "1s"])
+        .timeout(Duration::from_secs_f64(secs))
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn watch_reuses_the_session_between_refreshes() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let output = watch_for(
+        sivasbus(&site, &home).args(["arrivals", "10", "--watch", "--format", "ndjson"]),
+        2.5,
+    );
+
+    let records: Vec<Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(records.len() >= 2, "{output}");
+    for record in &records {
+        assert_eq!(record["target"], serde_json::json!({"station": 10}));
+        assert_eq!(record["data"][0]["license_plate"], "58 AC 123");
+    }
+
+    // The station page and its token are fetched once, each refresh is a
+    // single POST.
+    assert_eq!(site.count("GET", "/Akilli-Durak/10"), 1);
+    assert_eq!(site.count("POST", "/durakTekrar"), records.len());
+}
+
+#[test]
+fn watch_marks_a_failed_refresh_stale() {
+    // Only the first board comes through.
+    let posts = AtomicUsize::new(0);
+    let site = StubSite::with(move |request| {
+        (request.path == "/durakTekrar" && posts.fetch_add(1, Ordering::SeqCst) > 0)
+            .then(|| Response::new(500, "text/plain", "down"))
+    });
+    let home = TempDir::new().unwrap();
+    let output = watch_for(
+        sivasbus(&site, &home).args(["arrivals", "10", "--watch", "--format", "table"]),
+        1.5,
+    );
+    let (first, refresh) = output.split_once("Last updated").unwrap();
+    assert!(first.contains("58 AC 123"), "{output}");
+    assert!(refresh.contains("STALE, last updated"), "{output}");
+    // The last good board stays up under the stale marker.
+    assert!(refresh.contains("58 AC 123"), "{output}");
+}
+
+fn nearest(site: &StubSite, args: &[&str]) -> Vec<Value> {
+    let home = TempDir::new().unwrap();
+    let found = stdout_json(
+        sivasbus(site, &home)
+            .arg("nearest")
+            .args(args)
+            .args(["--format", "json"]),
+    );
+    found.as_array().unwrap().clone()
+}
+
+#[test]
+fn nearest_orders_by_distance() {
+    let site = StubSite::start();
+    // At Hükümet Konağı, with Otogar 2.7 km away and the university 5.9 km.
+    let found = nearest(&site, &["39.7520,37.0180", "--radius", "4000"]);
+
+    let ids: Vec<&Value> = found.iter().map(|station| &station["id"]).collect();
+    assert_eq!(ids, [11, 10, 12]);
+    let distances: Vec<f64> = found
+        .iter()
+        .map(|station| station["distance_m"].as_f64().unwrap())
+        .collect();
+    for (distance, expected) in distances.iter().zip([0.0, 305.9, 2746.3]) {
+        assert!((distance - expected).abs() < 1.0, "{distances:?}");
+    }
+    assert!(
+        found
+            .iter()
+            .all(|station| station.get("next_arrival").is_none())
+    );
+}
+
+#[test]
+fn nearest_accepts_decimal_commas_and_caps_the_count() {
+    let site = StubSite::start();
+    let found = nearest(
+        &site,
+        &["39,7520;37,0180", "--radius", "10000", "--count", "2"],
+    );
+    let ids: Vec<&Value> = found.iter().map(|station| &station["id"]).collect();
+    assert_eq!(ids, [11, 10]);
+}
+
+#[test]
+fn nearest_with_arrivals() {
+    let site = StubSite::start();
+    let found = nearest(
+        &site,
+        &["39.7700,37.0400", "--radius", "400", "--with-arrivals"],
+    );
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["id"], 12);
+    assert_eq!(found[0]["next_arrival"]["license_plate"], "58 KP 007");
+    assert_eq!(site.count("POST", "/durakTekrar"), 1);
+}
+
+fn write_config(home: &TempDir, config: &str) -> std::path::PathBuf {
+    let dir = home.path().join("config").join("sivasbus");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    std::fs::write(&path, config).unwrap();
+    path
+}
+
+#[test]
+fn config_path_follows_flag_then_env_then_xdg() {
+    let home = TempDir::new().unwrap();
+    let default = home.path().join("config/sivasbus/config.toml");
+    let path = stdout(cli(&home).args(["config", "path"]));
+    assert_eq!(path.trim_end(), default.to_str().unwrap());
+
+    let path = stdout(
+        cli(&home)
+            .env("SIVASBUS_CONFIG", "/etc/sivasbus.toml")
+            .args(["config", "path"]),
+    );
+    assert_eq!(path.trim_end(), "/etc/sivasbus.toml");
+
+    let path = stdout(
+        cli(&home)
+            .env("SIVASBUS_CONFIG", "/etc/sivasbus.toml")
+            .args(["--config", "/tmp/mine.toml", "config", "path"]),
+    );
+    assert_eq!(path.trim_end(), "/tmp/mine.toml");
+}
+
+#[test]
+fn config_init_writes_a_template_once() {
+    let home = TempDir::new().unwrap();
+    let path = home.path().join("config/sivasbus/config.toml");
+
+    cli(&home).args(["config", "init"]).assert().success();
+    let template = std::fs::read_to_string(&path).unwrap();
+    assert!(template.contains("[stations]\n# home = 10"), "{template}");
+    toml::from_str::<toml::Table>(&template).unwrap();
+
+    std::fs::write(&path, "format = \"csv\"\n").unwrap();
+    let stderr = stderr(cli(&home).args(["config", "init"]), 1);
+    assert!(stderr.contains("pass --force to overwrite it"), "{stderr}");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "format = \"csv\"\n"
+    );
+
+    cli(&home)
+        .args(["config", "init", "--force"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), template);
+}
+
+#[test]
+fn aliases_resolve_without_the_catalog() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    write_config(&home, "[stations]\nhome = 10\n\n[lines]\nwork = \"7A\"\n");
+
+    let arrivals =
+        stdout_json(sivasbus(&site, &home).args(["arrivals", "home", "--format", "json"]));
+    assert_eq!(arrivals[0]["license_plate"], "58 AC 123");
+    let buses = stdout_json(sivasbus(&site, &home).args(["buses", "work", "--format", "json"]));
+    assert_eq!(buses[0]["license_plate"], "58 KP 007");
+
+    assert_eq!(site.count("GET", "/Akilli-Durak/10"), 1);
+    assert_eq!(site.count("GET", "/hat/7A"), 1);
+    assert_eq!(site.count("GET", "/Akilli-Duraklar-Harita"), 0);
+}
+
+#[test]
+fn flags_take_precedence_over_the_config() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    write_config(
+        &home,
+        &format!("base_url = \"{}\"\nformat = \"csv\"\n", site.url()),
+    );
+
+    // The config's base URL and format over the built-in defaults.
+    let csv = stdout(cli(&home).arg("lines"));
+    assert_eq!(csv, "id,human_name\n1,1 - Otogar Merkez\n7A,7A - Kampüs\n");
+
+    // And flags over the config.
+    let json = stdout_json(cli(&home).args(["lines", "--format", "json"]));
+    assert_eq!(json[0]["id"], "1");
+
+    let other = StubSite::start();
+    stdout(cli(&home).args(["--base-url", other.url(), "lines"]));
+    assert_eq!(other.count("GET", "/"), 1);
+    assert_eq!(site.count("GET", "/"), 2);
+}
+
+#[test]
+fn explicit_missing_config_is_an_error() {
+    let home = TempDir::new().unwrap();
+    let stderr = stderr(
+        cli(&home).args(["--config", "/nonexistent/sivasbus.toml", "lines"]),
+        1,
+    );
+    assert!(
+        stderr.contains("can't access /nonexistent/sivasbus.toml"),
+        "{stderr}"
+    );
+}
+
+fn statusbar(site: &StubSite, home: &TempDir, args: &[&str]) -> String {
+    let output = stdout(sivasbus(site, home).arg("statusbar").args(args));
+    output.strip_suffix('\n').unwrap().to_string()
+}
+
+#[test]
+fn statusbar_waybar_shows_the_soonest_bus() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    assert_eq!(
+        statusbar(&site, &home, &["10"]),
+        r#"{"class":"soon","text":"58 AC 123 4m","tooltip":"58 AC 123 4m\n58 AB 999 11m"}"#
+    );
+    assert_eq!(
+        statusbar(&site, &home, &["10", "--soon", "3m"]),
+        r#"{"class":"later","text":"58 AC 123 4m","tooltip":"58 AC 123 4m\n58 AB 999 11m"}"#
+    );
+}
+
+#[test]
+fn statusbar_filters_to_a_line() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    assert_eq!(
+        statusbar(&site, &home, &["12", "--line", "7A"]),
+        r#"{"class":"soon","text":"7A 2m","tooltip":"58 KP 007 2m"}"#
+    );
+    assert_eq!(
+        statusbar(&site, &home, &["12", "--line", "1"]),
+        r#"{"class":"none","text":"-","tooltip":"no buses"}"#
+    );
+}
+
+#[test]
+fn statusbar_empty_board() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    assert_eq!(
+        statusbar(&site, &home, &["11"]),
+        r#"{"class":"none","text":"-","tooltip":"no buses"}"#
+    );
+    assert_eq!(
+        statusbar(&site, &home, &["11", "--style", "i3"]),
+        r##"{"color":"#6272a4","full_text":"-","name":"sivasbus"}"##
+    );
+}
+
+#[test]
+fn statusbar_i3() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    assert_eq!(
+        statusbar(&site, &home, &["10", "--style", "i3"]),
+        r##"{"color":"#50fa7b","full_text":"58 AC 123 4m","name":"sivasbus"}"##
+    );
+}
+
+#[test]
+fn statusbar_prints_no_data_when_the_site_is_down() {
+    let site = StubSite::with(|request| {
+        (request.method == "POST").then(|| Response::new(500, "text/plain", "down"))
+    });
+    let home = TempDir::new().unwrap();
+    assert_eq!(
+        statusbar(&site, &home, &["10"]),
+        r#"{"class":"none","text":"no data","tooltip":"no data"}"#
+    );
+}
+
+#[test]
+fn statusbar_reuses_the_cached_session() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    statusbar(&site, &home, &["10"]);
+    statusbar(&site, &home, &["10"]);
+    assert_eq!(site.count("GET", "/Akilli-Durak/10"), 1);
+    assert_eq!(site.count("POST", "/durakTekrar"), 2);
+    assert!(home.path().join("cache/sivasbus/session.json").exists());
+}
+
+/// The fixtures plus lines 2 to 9, which have a station but no buses.
+fn many_lines() -> StubSite {
+    StubSite::with(
+        |request| match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => {
+                let extra: String = (2..=9)
+                    .map(|n| format!(r#"<a href="/hat/{n}">{n} - Hat {n}</a>"#))
+                    .collect();
+                Some(Response::html(
+                    common::home_page().replace("</nav>", &format!("{extra}</nav>")),
+                ))
+            }
+            ("GET", path) => {
+                let n: u32 = path.strip_prefix("/hat/")?.parse().ok()?;
+                (2..=9)
+                    .contains(&n)
+                    .then(|| Response::html(common::line_page(200 + n, &[10])))
+            }
+            _ => None,
+        },
+    )
+}
+
+#[test]
+fn plate_found_on_the_second_line() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let found = stdout_json(sivasbus(&site, &home).args([
+        "plate",
+        "58kp007",
+        "--lines",
+        "1,7A",
+        "--from",
+        "39.7200,37.0300",
+        "--format",
+        "json",
+    ]));
+    assert_eq!(found[0]["line"], "7A");
+    assert_eq!(found[0]["license_plate"], "58 KP 007");
+    assert_eq!(found[0]["lat"], 39.72);
+    assert_eq!(found[0]["long"], 37.035);
+    let distance = found[0]["distance_m"].as_f64().unwrap();
+    assert!((distance - 427.9).abs() < 1.0, "{distance}");
+    let bearing = found[0]["bearing_deg"].as_f64().unwrap();
+    assert!((bearing - 90.0).abs() < 0.1, "{bearing}");
+    assert_eq!(
+        found[0]["map_url"],
+        "https://www.openstreetmap.org/?mlat=39.72&mlon=37.035#map=17/39.72/37.035"
+    );
+    assert_eq!(site.count("POST", "/aractekrar"), 2);
+}
+
+#[test]
+fn plate_search_stops_at_the_first_match() {
+    let (site, home) = (many_lines(), TempDir::new().unwrap());
+    let found = stdout_json(sivasbus(&site, &home).args([
+        "plate",
+        "58 KP 007",
+        "--lines",
+        "2,7A,3,4,5,6,7,8,9",
+        "--format",
+        "json",
+    ]));
+    assert_eq!(found[0]["line"], "7A");
+
+    // Four lines are in flight at once and one more starts when line 2
+    // finishes, so lines 6 to 9 are never requested.
+    let fetched: Vec<String> = site
+        .requests()
+        .into_iter()
+        .filter(|request| request.path.starts_with("/hat/"))
+        .map(|request| request.path)
+        .collect();
+    assert!(fetched.len() <= 5, "{fetched:?}");
+    for line in 6..=9 {
+        assert_eq!(site.count("GET", &format!("/hat/{line}")), 0, "{fetched:?}");
+    }
+}
+
+#[test]
+fn plate_search_warns_when_searching_every_line() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let output = sivasbus(&site, &home)
+        .args(["plate", "58 ab 999", "--format", "csv"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("searching all 2 lines"), "{stderr}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\n1,58 AB 999,39.76,37.03,,,"), "{stdout}");
+}
+
+#[test]
+fn missing_plate_exits_with_plate_not_found() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let stderr = stderr(
+        sivasbus(&site, &home).args(["plate", "06 XYZ 1", "--lines", "1,7A"]),
+        8,
+    );
+    assert!(
+        stderr.contains("no bus with plate 06XYZ1 found"),
+        "{stderr}"
+    );
+    assert_eq!(site.count("POST", "/aractekrar"), 2);
+}
+
+/// The fixtures, except that station 11 isn't a smart station.
+fn route_site() -> StubSite {
+    StubSite::with(|request| {
+        (request.path == "/Akilli-Duraklar-Harita").then(|| {
+            Response::html(format!(
+                "<script>var duraks = {};</script>",
+                common::stations_json(&[10, 12, 13])
+            ))
+        })
+    })
+}
+
+/// Line 1's path, with a detour between stations 10 and 11.
+const SHAPE: &str = r#"{"type": "LineString", "coordinates": [
+    [37.0150, 39.7505], [37.0180, 39.7505], [37.0180, 39.7520], [37.0400, 39.7700]
+]}"#;
+
+fn route(site: &StubSite, home: &TempDir, args: &[&str]) -> String {
+    let shape = home.path().join("shape.json");
+    std::fs::write(&shape, SHAPE).unwrap();
+    let mut command = sivasbus(site, home);
+    command.args(["route", "1"]).args(args);
+    if args.contains(&"--shape") {
+        command.arg(shape);
+    }
+    stdout(&mut command)
+}
+
+#[test]
+fn route_without_a_shape_is_approximate() {
+    let (site, home) = (route_site(), TempDir::new().unwrap());
+    assert_eq!(
+        route(&site, &home, &["--format", "table"]),
+        "\
+┌────────────────────────────────────────────────┐
+│ #   ID   Name                 Smart   Distance │
+╞════════════════════════════════════════════════╡
+│ 1   10   Cumhuriyet Meydanı   *       ~0.00 km │
+│ 2   11   Hükümet Konağı               ~0.31 km │
+│ 3   12   Otogar               *       ~3.05 km │
+└────────────────────────────────────────────────┘
+"
+    );
+    assert_eq!(
+        route(&site, &home, &["--format", "csv"]),
+        "\
+sequence,id,human_name,smart,distance_m,approximate
+1,10,Cumhuriyet Meydanı,true,0,true
+2,11,Hükümet Konağı,false,306,true
+3,12,Otogar,true,3052,true
+"
+    );
+}
+
+#[test]
+fn route_follows_the_shape() {
+    let (site, home) = (route_site(), TempDir::new().unwrap());
+    assert_eq!(
+        route(&site, &home, &["--format", "table", "--shape"]),
+        "\
+┌────────────────────────────────────────────────┐
+│ #   ID   Name                 Smart   Distance │
+╞════════════════════════════════════════════════╡
+│ 1   10   Cumhuriyet Meydanı   *       0.00 km  │
+│ 2   11   Hükümet Konağı               0.42 km  │
+│ 3   12   Otogar               *       3.17 km  │
+└────────────────────────────────────────────────┘
+"
+    );
+    assert_eq!(
+        route(&site, &home, &["--format", "csv", "--shape"]),
+        "\
+sequence,id,human_name,smart,distance_m,approximate
+1,10,Cumhuriyet Meydanı,true,0,false
+2,11,Hükümet Konağı,false,423,false
+3,12,Otogar,true,3170,false
+"
+    );
+    assert_eq!(
+        route(
+            &site,
+            &home,
+            &["--format", "table", "--direction", "inbound", "--shape"]
+        ),
+        "\
+┌────────────────────────────────────────────────┐
+│ #   ID   Name                 Smart   Distance │
+╞════════════════════════════════════════════════╡
+│ 1   12   Otogar               *       0.00 km  │
+│ 2   11   Hükümet Konağı               2.75 km  │
+│ 3   10   Cumhuriyet Meydanı   *       3.17 km  │
+└────────────────────────────────────────────────┘
+"
+    );
+}
+
+#[test]
+fn route_live_shows_buses_by_leg() {
+    let (site, home) = (route_site(), TempDir::new().unwrap());
+    assert_eq!(
+        route(&site, &home, &["--format", "table", "--live"]),
+        "\
+┌────────────────────────────────────────────────────────────┐
+│ #   ID   Name                 Smart   Distance   Buses     │
+╞════════════════════════════════════════════════════════════╡
+│ 1   10   Cumhuriyet Meydanı   *       ~0.00 km   58 AC 123 │
+│ 2   11   Hükümet Konağı               ~0.31 km   58 AB 999 │
+│ 3   12   Otogar               *       ~3.05 km             │
+└────────────────────────────────────────────────────────────┘
+"
+    );
+}
+
+#[test]
+fn route_falls_back_when_stops_are_off_the_shape() {
+    let (site, home) = (route_site(), TempDir::new().unwrap());
+    let shape = home.path().join("far.json");
+    std::fs::write(
+        &shape,
+        r#"{"type": "LineString", "coordinates": [[32.85, 39.93], [32.86, 39.94]]}"#,
+    )
+    .unwrap();
+    let output = sivasbus(&site, &home)
+        .args(["route", "1", "--format", "csv", "--shape"])
+        .arg(&shape)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("off the route shape"), "{stderr}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("2,11,Hükümet Konağı,false,306,true"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn route_rejects_a_shape_that_isnt_a_line_string() {
+    let (site, home) = (route_site(), TempDir::new().unwrap());
+    let shape = home.path().join("point.json");
+    std::fs::write(&shape, r#"{"type": "Point", "coordinates": [37.0, 39.7]}"#).unwrap();
+    let stderr = stderr(
+        sivasbus(&site, &home)
+            .args(["route", "1", "--shape"])
+            .arg(&shape),
+        1,
+    );
+    assert!(stderr.contains("invalid route shape"), "{stderr}");
+    assert!(site.requests().is_empty());
+}
+
+fn export(site: &StubSite, home: &TempDir, args: &[&str], to: &str) -> std::path::PathBuf {
+    let path = home.path().join(to);
+    sivasbus(site, home)
+        .arg("export")
+        .args(args)
+        .arg("--to")
+        .arg(&path)
+        .assert()
+        .success();
+    path
+}
+
+#[cfg(feature = "geojson")]
+fn features(path: &std::path::Path) -> Vec<geojson::Feature> {
+    let text = std::fs::read_to_string(path).unwrap();
+    match text.parse::<geojson::GeoJson>().unwrap() {
+        geojson::GeoJson::FeatureCollection(collection) => collection.features,
+        other => panic!("expected a FeatureCollection, got {other:?}"),
+    }
+}
+
+#[cfg(feature = "geojson")]
+#[test]
+fn export_stations_geojson() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let path = export(&site, &home, &["stations"], "stations.geojson");
+    let features = features(&path);
+    assert_eq!(features.len(), 4);
+    let geometry = features[0].geometry.as_ref().unwrap();
+    assert_eq!(geometry.value, geojson::Value::Point(vec![37.015, 39.7505]));
+    assert_eq!(features[0].property("name").unwrap(), "Cumhuriyet Meydanı");
+}
+
+#[cfg(feature = "geojson")]
+#[test]
+fn export_clustered_stations_geojson() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let path = export(
+        &site,
+        &home,
+        &["stations", "--cluster-zoom", "11"],
+        "clusters.geojson",
+    );
+    let features = features(&path);
+    // Stations 10 and 11 are 300 m apart and share a cell at zoom 11.
+    let counts: Vec<_> = features
+        .iter()
+        .map(|feature| feature.property("count").and_then(|count| count.as_u64()))
+        .collect();
+    assert_eq!(counts, [None, Some(2), None]);
+    let cluster = &features[1];
+    assert_eq!(
+        cluster.property("ids").unwrap(),
+        &serde_json::json!([10, 11])
+    );
+    let geometry = cluster.geometry.as_ref().unwrap();
+    assert_eq!(
+        geometry.value,
+        geojson::Value::Point(vec![37.0165, 39.75125])
+    );
+    assert_eq!(features[2].property("name").unwrap(), "Otogar");
+}
+
+#[cfg(feature = "geojson")]
+#[test]
+fn export_route_geojson() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let path = export(&site, &home, &["route", "7A"], "route.json");
+    let features = features(&path);
+    let line = features
+        .iter()
+        .find_map(|feature| match &feature.geometry.as_ref()?.value {
+            geojson::Value::LineString(points) => Some(points.clone()),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(line, [vec![37.04, 39.77], vec![37.03, 39.7]]);
+}
+
+#[cfg(feature = "kml")]
+#[test]
+fn export_route_kml() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let path = export(&site, &home, &["route", "1"], "route.kml");
+    let text = std::fs::read_to_string(path).unwrap();
+    let document = roxmltree::Document::parse(&text).unwrap();
+    let names: Vec<&str> = document
+        .descendants()
+        .filter(|node| node.has_tag_name("Placemark"))
+        .filter_map(|placemark| {
+            placemark
+                .children()
+                .find(|node| node.has_tag_name("name"))?
+                .text()
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "1 - Otogar Merkez",
+            "Cumhuriyet Meydanı",
+            "Hükümet Konağı",
+            "Otogar"
+        ]
+    );
+    let route = document
+        .descendants()
+        .find(|node| node.has_tag_name("coordinates"))
+        .and_then(|node| node.text())
+        .unwrap();
+    assert_eq!(route, "37.015,39.7505 37.018,39.752 37.04,39.77");
+}
+
+#[cfg(feature = "gtfs-static")]
+#[test]
+fn export_network_gtfs() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let dir = export(&site, &home, &["network", "--as", "gtfs"], "gtfs");
+    let rows = |name: &str| -> Vec<csv::StringRecord> {
+        csv::Reader::from_path(dir.join(name))
+            .unwrap()
+            .records()
+            .map(Result::unwrap)
+            .collect()
+    };
+    let stops: Vec<String> = rows("stops.txt")
+        .iter()
+        .map(|row| row[0].to_string())
+        .collect();
+    assert_eq!(stops, ["10", "11", "12", "13"]);
+    let routes: Vec<String> = rows("routes.txt")
+        .iter()
+        .map(|row| row[0].to_string())
+        .collect();
+    assert_eq!(routes, ["1", "7A"]);
+    assert_eq!(rows("trips.txt").len(), 2);
+    let times: Vec<(String, String, String)> = rows("stop_times.txt")
+        .iter()
+        .map(|row| (row[0].to_string(), row[1].to_string(), row[5].to_string()))
+        .collect();
+    // Placeholder times at each trip's ends, about 3 km at 20 km/h for 1
+    // and about 8 km for 7A.
+    let row = |trip: &str, time: &str| (trip.to_string(), time.to_string(), "0".to_string());
+    assert_eq!(
+        times,
+        [
+            row("1", "06:00:00"),
+            row("1", ""),
+            row("1", "06:10:00"),
+            row("7A", "06:00:00"),
+            row("7A", "06:24:00"),
+        ]
+    );
+    assert_eq!(rows("agency.txt").len(), 1);
+    assert_eq!(rows("calendar.txt").len(), 1);
+}
+
+#[test]
+fn export_lines_csv() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let path = export(&site, &home, &["lines"], "lines.csv");
+    let mut reader = csv::Reader::from_path(path).unwrap();
+    assert_eq!(reader.headers().unwrap(), vec!["id", "human_name"]);
+    let lines: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(
+        lines,
+        [vec!["1", "1 - Otogar Merkez"], vec!["7A", "7A - Kampüs"]]
+    );
+}
+
+#[test]
+fn export_refuses_to_overwrite_without_force() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let path = home.path().join("lines.csv");
+    std::fs::write(&path, "keep me").unwrap();
+
+    let stderr = stderr(
+        sivasbus(&site, &home)
+            .args(["export", "lines", "--to"])
+            .arg(&path),
+        1,
+    );
+    assert!(stderr.contains("already exists, pass --force"), "{stderr}");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+    assert!(site.requests().is_empty());
+
+    sivasbus(&site, &home)
+        .args(["export", "lines", "--force", "--to"])
+        .arg(&path)
+        .assert()
+        .success();
+    assert!(
+        std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("id,human_name\n")
+    );
+}
+
+#[test]
+fn export_rejects_unsupported_combinations() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let unsupported = stderr(
+        sivasbus(&site, &home)
+            .args(["export", "network", "--to"])
+            .arg(home.path().join("network.csv")),
+        1,
+    );
+    assert!(
+        unsupported.contains("network can't be exported as csv"),
+        "{unsupported}"
+    );
+
+    let unknown = stderr(
+        sivasbus(&site, &home)
+            .args(["export", "lines", "--to"])
+            .arg(home.path().join("lines.txt")),
+        1,
+    );
+    assert!(
+        unknown.contains("can't tell the export format"),
+        "{unknown}"
+    );
+}
+
+#[cfg(not(feature = "kml"))]
+#[test]
+fn export_suggests_the_missing_feature() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let stderr = stderr(
+        sivasbus(&site, &home)
+            .args(["export", "stations", "--to"])
+            .arg(home.path().join("stations.kml")),
+        1,
+    );
+    assert!(stderr.contains("rebuild with `--features kml`"), "{stderr}");
+}
+
+/// The fixture network a while later: station 10 moved, 11 renamed, 13
+/// dropped from 7A and gone, 7A renamed and a line 9 added with a new
+/// station 14.
+fn changed_site() -> StubSite {
+    fn station(id: i32, name: &str, lat: &str, long: &str) -> String {
+        format!(
+            r#"{{"linko": "/Akilli-Durak/{id}", "durakAd": "{name}", "durakLat": "{lat}", "durakLng": "{long}"}}"#
+        )
+    }
+    fn page(hg_id: u32, stations: &[String]) -> Response {
+        Response::html(format!(
+            "{}<script>var duraks = [{}];\n$.post('/aractekrar', {{ hgID: {hg_id} }});</script>",
+            common::token_input(),
+            stations.join(", ")
+        ))
+    }
+
+    StubSite::with(|request| {
+        let moved = station(10, "Cumhuriyet Meydanı", "39.7515", "37.0150");
+        let renamed = station(11, "Hükümet Meydanı", "39.7520", "37.0180");
+        let new = station(14, "Yenişehir", "39.7300", "37.0000");
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => Some(Response::html(
+                r#"<a href="/hat/1">1 - Otogar Merkez</a>
+<a href="/hat/7A">7A - Kampüs Hattı</a>
+<a href="/hat/9">9 - Yenişehir</a>"#,
+            )),
+            ("GET", "/hat/1") => Some(page(101, &[moved, renamed, common::station_json(12)])),
+            ("GET", "/hat/7A") => Some(page(107, &[common::station_json(12)])),
+            ("GET", "/hat/9") => Some(page(109, &[new])),
+            ("GET", "/Akilli-Duraklar-Harita") => Some(Response::html(format!(
+                "<script>var duraks = [{moved}, {renamed}, {}, {new}];</script>",
+                common::station_json(12)
+            ))),
+            _ => None,
+        }
+    })
+}
+
+/// Snapshots of the fixture network and of [`changed_site`].
+fn snapshots(home: &TempDir) -> (std::path::PathBuf, std::path::PathBuf) {
+    let (old, new) = (home.path().join("old.json"), home.path().join("new.json"));
+    for (site, path) in [(StubSite::start(), &old), (changed_site(), &new)] {
+        sivasbus(&site, home)
+            .args(["snapshot", "--out"])
+            .arg(path)
+            .assert()
+            .success();
+    }
+    (old, new)
+}
+
+#[test]
+fn diff_lists_every_change_and_exits_with_one() {
+    let home = TempDir::new().unwrap();
+    let (old, new) = snapshots(&home);
+    let output = cli(&home)
+        .args(["diff", "--format", "table"])
+        .args([&old, &new])
+        .assert()
+        .code(1)
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "\
++ station 14 Yenişehir
+- station 13 Cumhuriyet Üniversitesi
+~ station 11 renamed \"Hükümet Konağı\" -> \"Hükümet Meydanı\"
+~ station 10 Cumhuriyet Meydanı moved 111 m
++ line 9 9 - Yenişehir
+~ line 7A renamed \"7A - Kampüs\" -> \"7A - Kampüs Hattı\"
+~ line 7A stops changed
+"
+    );
+}
+
+#[test]
+fn diff_as_json() {
+    let home = TempDir::new().unwrap();
+    let (old, new) = snapshots(&home);
+    let output = cli(&home)
+        .args(["diff", "--format", "json"])
+        .args([&old, &new])
+        .assert()
+        .code(1)
+        .get_output()
+        .stdout
+        .clone();
+    let diff: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(diff["added_stations"][0]["id"], 14);
+    assert_eq!(diff["removed_stations"][0]["id"], 13);
+    assert_eq!(diff["renamed_stations"][0]["new_name"], "Hükümet Meydanı");
+    assert_eq!(diff["moved_stations"][0]["id"], 10);
+    assert_eq!(diff["added_lines"][0]["id"], "9");
+    assert_eq!(diff["renamed_lines"][0]["id"], "7A");
+    assert_eq!(diff["rerouted_lines"], serde_json::json!(["7A"]));
+}
+
+#[test]
+fn diff_of_identical_snapshots_exits_with_zero() {
+    let home = TempDir::new().unwrap();
+    let (old, _) = snapshots(&home);
+    let output = cli(&home)
+        .args(["diff", "--format", "table"])
+        .args([&old, &old])
+        .assert()
+        .code(0)
+        .get_output()
+        .stdout
+        .clone();
+    assert!(output.is_empty());
+}
+
+#[test]
+fn diff_rejects_corrupt_and_future_snapshots() {
+    let home = TempDir::new().unwrap();
+    let (old, _) = snapshots(&home);
+
+    let corrupt = home.path().join("corrupt.json");
+    std::fs::write(&corrupt, "{\"version\": 1, \"lines\": [").unwrap();
+    let message = stderr(cli(&home).arg("diff").args([&old, &corrupt]), 2);
+    assert!(message.contains("can't load snapshot"), "{message}");
+    assert!(message.contains("corrupt.json"), "{message}");
+
+    let future = home.path().join("future.json");
+    let mut snapshot: Value = serde_json::from_slice(&std::fs::read(&old).unwrap()).unwrap();
+    snapshot["version"] = 99.into();
+    std::fs::write(&future, snapshot.to_string()).unwrap();
+    let message = stderr(cli(&home).arg("diff").args([&future, &old]), 2);
+    assert!(
+        message.contains("unsupported snapshot version 99"),
+        "{message}"
+    );
+}
+
+#[test]
+fn snapshot_refuses_to_overwrite_without_force() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let (old, _) = snapshots(&home);
+    stderr(
+        sivasbus(&site, &home).args(["snapshot", "--out"]).arg(&old),
+        1,
+    );
+    sivasbus(&site, &home)
+        .args(["snapshot", "--with-buses", "--force", "--out"])
+        .arg(&old)
+        .assert()
+        .success();
+    let snapshot: Value = serde_json::from_slice(&std::fs::read(&old).unwrap()).unwrap();
+    assert!(snapshot.to_string().contains("58 KP 007"));
+}
+
+#[test]
+fn station_ids_skip_name_resolution() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    stdout(sivasbus(&site, &home).args(["arrivals", "12"]));
+    assert_eq!(site.count("GET", "/Akilli-Duraklar-Harita"), 0);
+    assert!(!home.path().join("cache/sivasbus/catalog.json").exists());
+}
+
+#[test]
+fn unique_station_name_resolves_from_the_cached_catalog() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    for query in ["cumhuriyet meydani", "CUMHURİYET MEYDANI", "meydan"] {
+        let arrivals =
+            stdout_json(sivasbus(&site, &home).args(["arrivals", query, "--format", "json"]));
+        assert_eq!(arrivals[0]["license_plate"], "58 AC 123", "{query}");
+    }
+    assert_eq!(site.count("GET", "/Akilli-Duraklar-Harita"), 1);
+    assert_eq!(site.count("GET", "/Akilli-Durak/10"), 3);
+}
+
+#[test]
+fn unique_line_name_resolves() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let buses = stdout_json(sivasbus(&site, &home).args(["buses", "kampus", "--format", "json"]));
+    assert_eq!(buses[0]["license_plate"], "58 KP 007");
+    assert_eq!(site.count("GET", "/hat/7A"), 1);
+}
+
+#[test]
+fn ambiguous_name_lists_candidates_without_a_terminal() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let message = stderr(sivasbus(&site, &home).args(["arrivals", "cumhuriyet"]), 3);
+    assert!(
+        message.contains(
+            "several stations match \"cumhuriyet\": \
+             Cumhuriyet Meydanı (10), Cumhuriyet Üniversitesi (13)"
+        ),
+        "{message}"
+    );
+    assert_eq!(site.count("POST", "/durakTekrar"), 0);
+}
+
+#[test]
+fn unmatched_name_is_not_found() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let message = stderr(sivasbus(&site, &home).args(["arrivals", "havaalani"]), 3);
+    assert!(
+        message.contains("no station matches \"havaalani\""),
+        "{message}"
+    );
+    let message = stderr(sivasbus(&site, &home).args(["buses", "havaalani"]), 3);
+    assert!(
+        message.contains("no line matches \"havaalani\""),
+        "{message}"
+    );
+}
+
+/// Parses each line of NDJSON output on its own, checking that it has
+/// exactly `keys`.
+fn ndjson_records(output: &str, keys: &[&[&str]]) -> Vec<Value> {
+    assert!(output.ends_with('\n'), "{output:?}");
+    output
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let record: Value = serde_json::from_str(line).unwrap();
+            let mut found: Vec<&str> = record
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .collect();
+            found.sort();
+            assert_eq!(found, keys[i.min(keys.len() - 1)], "{line}");
+            let timestamp = record["timestamp"].as_str().unwrap();
+            chrono::DateTime::parse_from_rfc3339(timestamp).unwrap();
+            record
+        })
+        .collect()
+}
+
+#[test]
+fn ndjson_watch_of_a_line() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let output = watch_for(
+        sivasbus(&site, &home).args(["buses", "1", "--watch", "--format", "ndjson"]),
+        2.5,
+    );
+    let records = ndjson_records(&output, &[&["data", "target", "timestamp"]]);
+    assert!(records.len() >= 2, "{output}");
+    for record in &records {
+        assert_eq!(record["target"], serde_json::json!({"line": "1"}));
+        assert_eq!(record["data"].as_array().unwrap().len(), 2);
+    }
+}
+
+#[test]
+fn ndjson_watch_reports_errors_as_records() {
+    let posts = AtomicUsize::new(0);
+    let site = StubSite::with(move |request| {
+        (request.path == "/durakTekrar" && posts.fetch_add(1, Ordering::SeqCst) > 0)
+            .then(|| Response::new(500, "text/plain", "down"))
+    });
+    let home = TempDir::new().unwrap();
+    let output = sivasbus(&site, &home)
+        .args(["arrivals", "10", "--watch", "--format", "ndjson"])
+        .args(["--interval", "1s"])
+        .timeout(Duration::from_secs_f64(2.5))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let records = ndjson_records(
+        &stdout,
+        &[
+            &["data", "target", "timestamp"],
+            &["error", "target", "timestamp"],
+        ],
+    );
+    assert!(records.len() >= 2, "{stdout}");
+    assert_eq!(records[1]["target"], serde_json::json!({"station": 10}));
+    assert!(records[1]["error"]["code"].is_string(), "{stdout}");
+    assert!(records[1]["error"]["message"].is_string(), "{stdout}");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+#[cfg(unix)]
+#[test]
+fn ndjson_watch_ends_cleanly_on_ctrl_c() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("sivasbus"))
+        .env_clear()
+        .env("HOME", home.path())
+        .env("XDG_CACHE_HOME", home.path().join("cache"))
+        .args(["--lang", "en", "--base-url", site.url()])
+        .args(["arrivals", "10", "--watch", "--interval", "1s"])
+        .args(["--format", "ndjson"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(1500));
+    let killed = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output.status);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let records = ndjson_records(&stdout, &[&["data", "target", "timestamp"]]);
+    assert!(!records.is_empty());
+}
+
+/// Station 10's board in `lang`, as a table and in the status bar.
+fn localized_board(lang: &str) -> (String, String) {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let mut command = cli_in(lang, &home);
+    command.args([
+        "--base-url",
+        site.url(),
+        "arrivals",
+        "10",
+        "--format",
+        "table",
+    ]);
+    let table = stdout(&mut command);
+    let mut command = cli_in(lang, &home);
+    command.args(["--base-url", site.url(), "statusbar", "10"]);
+    (table, stdout(&mut command).trim_end().to_string())
+}
+
+#[test]
+fn board_in_english() {
+    let (table, statusbar) = localized_board("en");
+    assert_eq!(
+        table,
+        "\
+┌────────────────────────┐
+│ Plate       Arrives in │
+╞════════════════════════╡
+│ 58 AC 123   4 min      │
+│ 58 AB 999   11 min     │
+└────────────────────────┘
+"
+    );
+    assert_eq!(
+        statusbar,
+        r#"{"class":"soon","text":"58 AC 123 4m","tooltip":"58 AC 123 4m\n58 AB 999 11m"}"#
+    );
+}
+
+#[test]
+fn board_in_turkish() {
+    let (table, statusbar) = localized_board("tr");
+    assert_eq!(
+        table,
+        "\
+┌───────────────────┐
+│ Plaka       Varış │
+╞═══════════════════╡
+│ 58 AC 123   4 dk  │
+│ 58 AB 999   11 dk │
+└───────────────────┘
+"
+    );
+    assert_eq!(
+        statusbar,
+        r#"{"class":"soon","text":"58 AC 123 4dk","tooltip":"58 AC 123 4dk\n58 AB 999 11dk"}"#
+    );
+}
+
+#[test]
+fn errors_in_turkish() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let mut command = cli_in("tr", &home);
+    command.args(["--base-url", site.url(), "buses", "999"]);
+    assert_eq!(stderr(&mut command, 3), "hata: hat bulunamadı\n");
+}
+
+#[test]
+fn lang_defaults_from_the_environment() {
+    let (site, home) = (StubSite::start(), TempDir::new().unwrap());
+    let mut command = Command::cargo_bin("sivasbus").unwrap();
+    command
+        .env_clear()
+        .env("HOME", home.path())
+        .env("XDG_CACHE_HOME", home.path().join("cache"))
+        .env("LANG", "tr_TR.UTF-8")
+        .args([
+            "--base-url",
+            site.url(),
+            "arrivals",
+            "10",
+            "--format",
+            "table",
+        ]);
+    assert_eq!(stdout(&mut command), localized_board("tr").0);
+}
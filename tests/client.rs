@@ -0,0 +1,619 @@
+mod common;
+
+use sivasbus::media::{LineMedia, MAX_MEDIA_BYTES, MediaKind, MediaRef};
+use sivasbus::recommend::{BoardState, RecommendOptions, Recommendation};
+use sivasbus::{Client, Coords, Error, LineFilterStrategy, StationId};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use common::{Response, StubSite};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+fn client(site: &StubSite) -> Client {
+    Client::builder()
+        .base_url(site.url())
+        .request_delay(Duration::ZERO)
+        .build()
+        .unwrap()
+}
+
+/// A progress event with the borrowed parts copied out.
+#[derive(Debug, PartialEq)]
+struct Event {
+    done: usize,
+    total: usize,
+    line: String,
+    result: Result<Option<usize>, String>,
+}
+
+/// Records every progress event into `events`.
+fn recorder(events: &mut Vec<Event>) -> impl FnMut(sivasbus::LineProgress) + '_ {
+    |progress| {
+        events.push(Event {
+            done: progress.done,
+            total: progress.total,
+            line: progress.line.to_string(),
+            result: progress.result.map_err(Error::code).map_err(str::to_string),
+        })
+    }
+}
+
+fn event(done: usize, line: &str, result: Result<Option<usize>, &str>) -> Event {
+    Event {
+        done,
+        total: 2,
+        line: line.to_string(),
+        result: result.map_err(str::to_string),
+    }
+}
+
+#[test]
+fn all_data_reports_each_line_with_its_buses() {
+    let site = StubSite::start();
+    let mut events = Vec::new();
+    let snapshot =
+        block_on(client(&site).get_all_data_with_progress(recorder(&mut events))).unwrap();
+
+    assert_eq!(
+        events,
+        [event(1, "1", Ok(Some(2))), event(2, "7A", Ok(Some(1)))]
+    );
+    assert_eq!(snapshot.buses.len(), 2);
+}
+
+#[test]
+fn network_reports_lines_without_bus_counts() {
+    let site = StubSite::start();
+    let mut events = Vec::new();
+    block_on(client(&site).get_network_with_progress(recorder(&mut events))).unwrap();
+
+    assert_eq!(events, [event(1, "1", Ok(None)), event(2, "7A", Ok(None))]);
+    assert_eq!(site.count("POST", "/aractekrar"), 0);
+}
+
+#[test]
+fn failed_lines_are_reported_without_stopping() {
+    let site = StubSite::with(|request| {
+        (request.path == "/hat/1").then(|| Response::new(500, "text/plain", "down"))
+    });
+    let mut events = Vec::new();
+    let snapshot =
+        block_on(client(&site).get_all_data_with_progress(recorder(&mut events))).unwrap();
+
+    assert_eq!(events.len(), 2);
+    assert!(events[0].result.is_err(), "{events:?}");
+    assert_eq!(events[1], event(2, "7A", Ok(Some(1))));
+    assert_eq!(snapshot.failed_lines, ["1"]);
+}
+
+#[test]
+fn plate_search_reports_lines_until_the_match() {
+    let site = StubSite::start();
+    let lines = ["1".to_string(), "7A".to_string()];
+    let mut events = Vec::new();
+    let found = block_on(client(&site).find_bus_with_progress(
+        "58 AC 123",
+        &lines,
+        1,
+        recorder(&mut events),
+    ));
+
+    assert_eq!(found.unwrap().line, "1");
+    assert_eq!(events, [event(1, "1", Ok(Some(2)))]);
+}
+
+fn media(site: &StubSite, kind: MediaKind, path: &str, title: &str) -> MediaRef {
+    MediaRef {
+        kind,
+        url: format!("{}{path}", site.url()),
+        title: Some(title.to_string()),
+    }
+}
+
+#[test]
+fn line_media_is_found_and_resolved() {
+    let site = StubSite::start();
+    let client = client(&site);
+
+    assert_eq!(
+        block_on(client.get_line_media("1")).unwrap(),
+        LineMedia {
+            images: vec![media(
+                &site,
+                MediaKind::Image,
+                "/uploads/hat-1-guzergah.png",
+                "1 Otogar Merkez güzergah haritası",
+            )],
+            documents: vec![media(
+                &site,
+                MediaKind::Document,
+                "/uploads/hat-1-tarife.pdf",
+                "Hafta içi tarifesi",
+            )],
+        }
+    );
+    assert_eq!(
+        block_on(client.get_line_media("7A")).unwrap(),
+        LineMedia::default()
+    );
+}
+
+#[test]
+fn media_downloads_check_the_content_type() {
+    let site = StubSite::start();
+    let client = client(&site);
+    let line_media = block_on(client.get_line_media("1")).unwrap();
+
+    let image = block_on(client.download_media(&line_media.images[0])).unwrap();
+    assert_eq!(image, common::PNG);
+    let document = block_on(client.download_media(&line_media.documents[0])).unwrap();
+    assert_eq!(document, common::PDF);
+
+    let mut page = line_media.images[0].clone();
+    page.url = format!("{}/hat/1", site.url());
+    assert!(matches!(
+        block_on(client.download_media(&page)),
+        Err(Error::UnexpectedContentType { content_type: Some(content_type) })
+            if content_type.starts_with("text/html")
+    ));
+}
+
+#[test]
+fn unknown_lines_have_no_media() {
+    let site = StubSite::with(|request| match request.path.as_str() {
+        "/Hata/404" => Some(Response::html(common::line_page_with_media())),
+        _ => None,
+    });
+    let client = client(&site);
+
+    let result = block_on(client.get_line_media("999"));
+    assert!(
+        matches!(&result, Err(Error::NoLineId { redirect: Some(url) }) if url.ends_with("/Hata/404")),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn media_downloads_stop_at_the_size_limit() {
+    let site = StubSite::with(|request| match request.path.as_str() {
+        "/uploads/large.png" => Some(Response::new(
+            200,
+            "image/png",
+            vec![0; MAX_MEDIA_BYTES + 1],
+        )),
+        "/uploads/streamed.png" => {
+            Some(Response::new(200, "image/png", vec![0; MAX_MEDIA_BYTES + 1]).without_length())
+        }
+        "/uploads/limit.png" => Some(Response::new(200, "image/png", vec![0; MAX_MEDIA_BYTES])),
+        _ => None,
+    });
+    let client = client(&site);
+
+    for path in ["/uploads/large.png", "/uploads/streamed.png"] {
+        let result = block_on(client.download_media(&media(&site, MediaKind::Image, path, "")));
+        assert!(
+            matches!(
+                result,
+                Err(Error::MediaTooLarge {
+                    limit: MAX_MEDIA_BYTES
+                })
+            ),
+            "{path}: {result:?}"
+        );
+    }
+    let at_limit = media(&site, MediaKind::Image, "/uploads/limit.png", "");
+    assert_eq!(
+        block_on(client.download_media(&at_limit)).unwrap().len(),
+        MAX_MEDIA_BYTES
+    );
+}
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+fn compress(encoding: &str, body: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    match encoding {
+        #[cfg(feature = "gzip")]
+        "gzip" => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body).unwrap();
+            encoder.finish().unwrap()
+        }
+        #[cfg(feature = "brotli")]
+        "br" => {
+            let mut compressed = Vec::new();
+            let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            encoder.write_all(body).unwrap();
+            drop(encoder);
+            compressed
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Serves the stations page compressed with `encoding` to clients that ask
+/// for it.
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+fn compressing_site(encoding: &'static str) -> StubSite {
+    StubSite::with(move |request| {
+        let accepted = request
+            .header("Accept-Encoding")
+            .is_some_and(|accepted| accepted.contains(encoding));
+        (request.path == "/Akilli-Duraklar-Harita" && accepted).then(|| {
+            let mut response = common::fixture(request);
+            response.body = compress(encoding, &response.body);
+            response.with_header("Content-Encoding", encoding)
+        })
+    })
+}
+
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+fn assert_decompressed(encoding: &'static str) {
+    let plain = block_on(client(&StubSite::start()).get_all_stations()).unwrap();
+
+    let site = compressing_site(encoding);
+    assert_eq!(block_on(client(&site).get_all_stations()).unwrap(), plain);
+    let accepted = site.requests()[0]
+        .header("Accept-Encoding")
+        .map(str::to_string);
+    assert!(accepted.is_some_and(|accepted| accepted.contains(encoding)));
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_responses_parse_like_plain_ones() {
+    assert_decompressed("gzip");
+
+    // Turned off, the client doesn't ask and gets the page uncompressed.
+    let site = compressing_site("gzip");
+    let client = Client::builder()
+        .base_url(site.url())
+        .gzip(false)
+        .build()
+        .unwrap();
+    assert_eq!(block_on(client.get_all_stations()).unwrap().len(), 4);
+    let accepted = site.requests()[0]
+        .header("Accept-Encoding")
+        .map(str::to_string);
+    assert!(!accepted.is_some_and(|accepted| accepted.contains("gzip")));
+}
+
+#[cfg(feature = "brotli")]
+#[test]
+fn brotli_responses_parse_like_plain_ones() {
+    assert_decompressed("br");
+}
+
+const PRODUCTION_HOST: &str = "ulasim.sivas.bel.tr";
+
+fn stub_addr(site: &StubSite) -> SocketAddr {
+    site.url().trim_start_matches("http://").parse().unwrap()
+}
+
+/// Asserts every request to `site` was for the production host.
+fn assert_production_host(site: &StubSite) {
+    let requests = site.requests();
+    assert!(!requests.is_empty());
+    for request in requests {
+        assert_eq!(request.header("Host"), Some(PRODUCTION_HOST), "{request:?}");
+    }
+}
+
+#[test]
+fn connect_to_sends_production_requests_to_the_stub() {
+    let site = StubSite::start();
+    let client = Client::builder()
+        .base_url(format!("http://{PRODUCTION_HOST}"))
+        .request_delay(Duration::ZERO)
+        .danger_connect_to(stub_addr(&site))
+        .build()
+        .unwrap();
+
+    let buses = block_on(client.get_line_buses("1")).unwrap();
+    assert_eq!(buses.len(), 2);
+    assert_production_host(&site);
+}
+
+#[test]
+fn resolve_sends_the_production_host_to_the_stub() {
+    let site = StubSite::start();
+    let addr = stub_addr(&site);
+    let client = Client::builder()
+        .base_url(format!("http://{PRODUCTION_HOST}:{}", addr.port()))
+        .request_delay(Duration::ZERO)
+        .resolve(PRODUCTION_HOST, SocketAddr::new(addr.ip(), 0))
+        .build()
+        .unwrap();
+
+    assert_eq!(block_on(client.get_all_stations()).unwrap().len(), 4);
+    let host = format!("{PRODUCTION_HOST}:{}", addr.port());
+    assert!(
+        site.requests()
+            .iter()
+            .all(|request| request.header("Host") == Some(&host))
+    );
+}
+
+#[test]
+fn overrides_must_be_for_the_base_url_and_a_real_address() {
+    let stub: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+    let unspecified: SocketAddr = "0.0.0.0:8080".parse().unwrap();
+    let builder = || Client::builder().base_url(format!("http://{PRODUCTION_HOST}"));
+
+    for builder in [
+        builder().resolve("example.com", stub),
+        builder().resolve(PRODUCTION_HOST, unspecified),
+        builder().danger_connect_to(unspecified),
+    ] {
+        assert!(matches!(builder.build(), Err(Error::InvalidResolve { .. })));
+    }
+    assert!(
+        builder()
+            .resolve("ULASIM.sivas.bel.tr", stub)
+            .build()
+            .is_ok()
+    );
+}
+
+fn plates(arrivals: &sivasbus::LineArrivals) -> Vec<&str> {
+    arrivals
+        .buses
+        .iter()
+        .map(|bus| bus.license_plate.as_str())
+        .collect()
+}
+
+#[test]
+fn arrivals_are_filtered_by_the_lines_on_the_board() {
+    let site = StubSite::with(|request| {
+        (request.path == "/durakTekrar").then(|| {
+            Response::json(
+                r#"[{"plaka": "58 KP 007", "sure": 2, "hatNo": "7A"},
+{"plaka": "58 AC 123", "sure": 5, "hatNo": 1},
+{"plaka": "58 TT 100", "sure": 9, "hat": " 7a "}]"#,
+            )
+        })
+    });
+    let arrivals =
+        block_on(client(&site).get_station_buses_for_lines(StationId(12), &["7a"])).unwrap();
+
+    assert_eq!(arrivals.strategy, LineFilterStrategy::BoardLines);
+    assert_eq!(plates(&arrivals), ["58 KP 007", "58 TT 100"]);
+    assert_eq!(site.count("POST", "/aractekrar"), 0);
+}
+
+#[test]
+fn arrivals_are_filtered_by_looking_up_plates() {
+    let site = StubSite::start();
+    let client = client(&site);
+
+    let on_1 = block_on(client.get_station_buses_for_lines(StationId(10), &["1"])).unwrap();
+    assert_eq!(on_1.strategy, LineFilterStrategy::PlateLookup);
+    assert_eq!(plates(&on_1), ["58 AC 123", "58 AB 999"]);
+
+    let on_7a = block_on(client.get_station_buses_for_lines(StationId(10), &["7A"])).unwrap();
+    assert_eq!(on_7a.strategy, LineFilterStrategy::PlateLookup);
+    assert!(on_7a.buses.is_empty());
+    // One bus lookup per line asked for, nothing more.
+    assert_eq!(site.count("POST", "/aractekrar"), 2);
+}
+
+#[test]
+fn plate_lookup_reports_lines_it_could_not_fetch() {
+    let site = StubSite::start();
+    let client = client(&site);
+
+    let arrivals =
+        block_on(client.get_station_buses_for_lines(StationId(10), &["1", "999"])).unwrap();
+    assert_eq!(plates(&arrivals), ["58 AC 123", "58 AB 999"]);
+    assert_eq!(arrivals.failed_lines, ["999"]);
+
+    assert!(block_on(client.get_station_buses_for_lines(StationId(10), &["999"])).is_err());
+}
+
+/// Serves the stations page with the array assigned by `assignment`.
+fn stations_page_site(assignment: &'static str) -> StubSite {
+    StubSite::with(move |request| {
+        (request.path == "/Akilli-Duraklar-Harita").then(|| {
+            Response::html(format!(
+                "<script>\n{assignment} {};\n</script>",
+                common::stations_json(&[10, 11, 12, 13])
+            ))
+        })
+    })
+}
+
+#[test]
+fn stations_are_found_however_they_are_assigned() {
+    let expected = block_on(client(&StubSite::start()).get_all_stations()).unwrap();
+    for assignment in [
+        "var duraks =",
+        "let duraks=",
+        "const  duraks =",
+        "window.duraks =",
+        "var map = init(); window.duraks =",
+    ] {
+        let stations = block_on(client(&stations_page_site(assignment)).get_all_stations());
+        assert_eq!(stations.unwrap(), expected, "{assignment}");
+    }
+}
+
+#[test]
+fn other_arrays_are_not_taken_for_stations() {
+    for assignment in [
+        "var myduraks =",
+        "let duraksCount = 4; var hatlar =",
+        "duraks =",
+    ] {
+        let stations = block_on(client(&stations_page_site(assignment)).get_all_stations());
+        assert!(
+            matches!(stations, Err(Error::NoStations)),
+            "{assignment}: {stations:?}"
+        );
+    }
+}
+
+/// Standing at station 11, whose board is empty, with station 10 about
+/// 300 metres away.
+const AT_STATION_11: Coords = Coords {
+    lat: 39.7520,
+    long: 37.0180,
+};
+
+fn station_ids(recommendations: &[Recommendation]) -> Vec<i32> {
+    recommendations
+        .iter()
+        .map(|recommendation| recommendation.station.id)
+        .collect()
+}
+
+#[test]
+fn farther_stop_wins_with_an_earlier_bus() {
+    let site = StubSite::start();
+    let options = RecommendOptions::new().candidates(2);
+    let recommendations = block_on(client(&site).recommend_stop(AT_STATION_11, options)).unwrap();
+
+    assert_eq!(station_ids(&recommendations), [10, 11]);
+    let best = &recommendations[0];
+    assert_eq!(best.board, BoardState::Departure);
+    // About 3 and a half minutes' walk, in time for the bus in 4.
+    assert!((290.0..310.0).contains(&best.distance_m), "{best:?}");
+    assert!((210..230).contains(&best.walk_time.as_secs()), "{best:?}");
+    let departure = best.departure.as_ref().unwrap();
+    assert_eq!(departure.license_plate, "58 AC 123");
+    assert_eq!(best.walk_time + best.wait_time, Duration::from_secs(240));
+    assert_eq!(best.total, Duration::from_secs(240));
+
+    let nearest = &recommendations[1];
+    assert_eq!(nearest.distance_m, 0.0);
+    assert_eq!(nearest.board, BoardState::NoBus);
+    assert_eq!(nearest.wait_time, Duration::from_secs(30 * 60));
+}
+
+#[test]
+fn buses_that_leave_before_the_walk_ends_are_skipped() {
+    let site = StubSite::start();
+    // At 1 km/h the walk takes 18 minutes, past both buses at station 10.
+    let options = RecommendOptions::new()
+        .candidates(2)
+        .walking_speed_kmh(1.0)
+        .no_bus_penalty(Duration::from_secs(60));
+    let recommendations = block_on(client(&site).recommend_stop(AT_STATION_11, options)).unwrap();
+
+    assert_eq!(station_ids(&recommendations), [11, 10]);
+    assert!(recommendations.iter().all(|r| r.board == BoardState::NoBus));
+}
+
+#[test]
+fn failed_boards_are_ranked_with_the_penalty() {
+    let site = StubSite::with(|request| {
+        (request.path == "/durakTekrar" && request.param("drkID").as_deref() == Some("10"))
+            .then(|| Response::new(500, "text/plain", "down"))
+    });
+    let options = RecommendOptions::new()
+        .candidates(2)
+        .no_bus_penalty(Duration::from_secs(10 * 60));
+    let recommendations = block_on(client(&site).recommend_stop(AT_STATION_11, options)).unwrap();
+
+    // Both stops get the penalty as their wait, and the nearer one wins.
+    assert_eq!(station_ids(&recommendations), [11, 10]);
+    assert_eq!(recommendations[0].board, BoardState::NoBus);
+    assert!(matches!(recommendations[1].board, BoardState::Failed(_)));
+    assert_eq!(recommendations[1].departure, None);
+    assert_eq!(recommendations[1].wait_time, Duration::from_secs(10 * 60));
+}
+
+#[test]
+fn recommendations_can_be_limited_to_lines() {
+    let site = StubSite::start();
+    let options = RecommendOptions::new()
+        .candidates(2)
+        .lines(vec!["7A".to_string()]);
+    let recommendations = block_on(client(&site).recommend_stop(AT_STATION_11, options)).unwrap();
+
+    // Station 10's buses are on line 1, so neither stop has one to catch.
+    assert!(recommendations.iter().all(|r| r.board == BoardState::NoBus));
+    assert_eq!(station_ids(&recommendations), [11, 10]);
+}
+
+#[test]
+fn bus_timeout_does_not_count_the_request_delay() {
+    let site = StubSite::start();
+    let client = Client::builder()
+        .base_url(site.url())
+        .request_delay(Duration::from_millis(300))
+        .build()
+        .unwrap();
+
+    let buses = block_on(
+        client.get_station_buses_with_bus_timeout(StationId(10), Duration::from_millis(100)),
+    )
+    .unwrap();
+    assert_eq!(buses.len(), 2);
+}
+
+#[test]
+fn bus_timeout_stops_a_slow_board() {
+    let site = StubSite::with(|request| {
+        (request.path == "/durakTekrar").then(|| {
+            std::thread::sleep(Duration::from_millis(500));
+            common::fixture(request)
+        })
+    });
+    let result = block_on(
+        client(&site).get_station_buses_with_bus_timeout(StationId(10), Duration::from_millis(100)),
+    );
+    assert!(matches!(result, Err(Error::Timeout)), "{result:?}");
+}
+
+/// A timetable like the ones on line pages, here on the error page unknown
+/// lines redirect to.
+const TIMETABLE: &str = "<table><tr><th>Hafta İçi</th><th>Cumartesi</th><th>Pazar</th></tr>
+<tr><td>07:00</td><td>08:00</td><td>09:00</td></tr></table>";
+
+fn timetable_on_error_page(request: &common::Request) -> Option<Response> {
+    match request.path.as_str() {
+        "/hat/1" => Some(Response::html(common::line_page_with_media() + TIMETABLE)),
+        "/Hata/404" => Some(Response::html(TIMETABLE)),
+        _ => None,
+    }
+}
+
+#[test]
+fn unknown_lines_have_no_schedule() {
+    let site = StubSite::with(timetable_on_error_page);
+    let client = client(&site);
+
+    let schedule = block_on(client.get_line_schedule("1")).unwrap();
+    assert_eq!(schedule.weekday.len(), 1);
+
+    let result = block_on(client.get_line_schedule("999"));
+    assert!(
+        matches!(&result, Err(Error::NoLineId { redirect: Some(url) }) if url.ends_with("/Hata/404")),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn zero_capacity_rate_limit_still_lets_requests_through() {
+    let site = StubSite::start();
+    let client = Client::builder()
+        .base_url(site.url())
+        .request_delay(Duration::ZERO)
+        .rate_limiter(sivasbus::rate_limit::TokenBucket::new(0, 50.0))
+        .build()
+        .unwrap();
+
+    let lines =
+        block_on(async { tokio::time::timeout(Duration::from_secs(5), client.get_lines()).await });
+    assert_eq!(lines.expect("timed out").unwrap().len(), 2);
+}
@@ -0,0 +1,300 @@
+//! An in-process stand-in for the municipality's site, serving small pages
+//! shaped like the real ones and logging every request it gets.
+//!
+//! The network has two lines:
+//!
+//! | Line | `hgID` | Stations | Buses |
+//! | --- | --- | --- | --- |
+//! | `1` | 101 | 10, 11, 12 | `58 AC 123`, `58 AB 999` |
+//! | `7A` | 107 | 12, 13 | `58 KP 007` |
+//!
+//! Station 10's board has `58 AC 123` in 4 minutes and `58 AB 999` in 11,
+//! station 12's has `58 KP 007` in 2. Other boards are empty. `/hat/999`
+//! redirects to an error page, like an unknown line on the real site.
+//!
+//! Line 1's page also links a route map image and a PDF timetable, both by
+//! relative URL, next to a logo that isn't route media. Line 7A's has none.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+pub const TOKEN: &str = "tok123";
+/// The bytes of line 1's route map and timetable.
+pub const PNG: &[u8] = b"\x89PNG\r\n\x1a\n route map";
+pub const PDF: &[u8] = b"%PDF-1.4 timetable";
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Request {
+    /// A form field of the body.
+    pub fn param(&self, name: &str) -> Option<String> {
+        self.body.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            (key == name).then(|| value.replace("%20", " "))
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Whether to send `Content-Length`. Without it the body ends when the
+    /// connection closes.
+    pub length: bool,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Response {
+        Response {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.into(),
+            length: true,
+        }
+    }
+
+    pub fn html(body: impl Into<Vec<u8>>) -> Response {
+        Response::new(200, "text/html; charset=utf-8", body)
+    }
+
+    pub fn json(body: impl Into<Vec<u8>>) -> Response {
+        Response::new(200, "application/json", body)
+    }
+
+    pub fn not_found() -> Response {
+        Response::new(404, "text/plain", "not found")
+    }
+
+    pub fn redirect(location: &str) -> Response {
+        Response::new(302, "text/plain", "").with_header("Location", location)
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Response {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn without_length(mut self) -> Response {
+        self.length = false;
+        self
+    }
+}
+
+type Handler = dyn Fn(&Request) -> Option<Response> + Send + Sync;
+
+pub struct StubSite {
+    url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl StubSite {
+    /// Serves the fixtures described in the module docs.
+    pub fn start() -> StubSite {
+        StubSite::with(|_| None)
+    }
+
+    /// Serves what `handler` returns, or the fixtures where it returns
+    /// `None`.
+    pub fn with(
+        handler: impl Fn(&Request) -> Option<Response> + Send + Sync + 'static,
+    ) -> StubSite {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let log = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let log = log.clone();
+                let handler = handler.clone();
+                std::thread::spawn(move || serve(stream, &log, &*handler));
+            }
+        });
+
+        StubSite { url, requests }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// How many `method` requests were made for `path`.
+    pub fn count(&self, method: &str, path: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|request| request.method == method && request.path == path)
+            .count()
+    }
+}
+
+fn serve(stream: TcpStream, log: &Mutex<Vec<Request>>, handler: &Handler) {
+    let mut reader = BufReader::new(&stream);
+    let Some(request) = read_request(&mut reader) else {
+        return;
+    };
+    log.lock().unwrap().push(request.clone());
+
+    let response = handler(&request).unwrap_or_else(|| fixture(&request));
+    let mut head = format!("HTTP/1.1 {} Stub\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if response.length {
+        head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+
+    let mut stream = &stream;
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&response.body);
+}
+
+fn read_request(reader: &mut impl BufRead) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+    }
+
+    let length = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+pub fn token_input() -> String {
+    format!(r#"<input name="__RequestVerificationToken" type="hidden" value="{TOKEN}">"#)
+}
+
+pub fn station_json(id: i32) -> String {
+    let (name, lat, long) = match id {
+        10 => ("Cumhuriyet Meydanı", "39.7505", "37.0150"),
+        11 => ("Hükümet Konağı", "39.7520", "37.0180"),
+        12 => ("Otogar", "39.7700", "37.0400"),
+        13 => ("Cumhuriyet Üniversitesi", "39.7000", "37.0300"),
+        _ => panic!("no fixture for station {id}"),
+    };
+    format!(
+        r#"{{"linko": "/Akilli-Durak/{id}", "durakAd": "{name}", "durakLat": "{lat}", "durakLng": "{long}"}}"#
+    )
+}
+
+pub fn stations_json(ids: &[i32]) -> String {
+    let stations: Vec<String> = ids.iter().map(|id| station_json(*id)).collect();
+    format!("[{}]", stations.join(", "))
+}
+
+pub fn home_page() -> String {
+    r#"<html><body><nav>
+<a href="/hat/1">1 - Otogar Merkez</a>
+<a href="/hat/7A">7A - Kampüs</a>
+</nav></body></html>"#
+        .to_string()
+}
+
+pub fn line_page(hg_id: u32, stations: &[i32]) -> String {
+    format!(
+        "<html><body>{}<script>var duraks = {};\n$.post('/aractekrar', {{ hgID: {hg_id} }});</script></body></html>",
+        token_input(),
+        stations_json(stations),
+    )
+}
+
+/// Line 1's page, with its route media.
+pub fn line_page_with_media() -> String {
+    line_page(101, &[10, 11, 12]).replace(
+        "<body>",
+        r#"<body><img src="/images/logo.png" alt="Sivas Belediyesi">
+<img src="uploads/hat-1-guzergah.png" alt="1 Otogar Merkez güzergah haritası">
+<a href="/uploads/hat-1-tarife.pdf">Hafta içi
+  tarifesi</a>"#,
+    )
+}
+
+pub fn line_buses_json(hg_id: &str) -> String {
+    match hg_id {
+        "101" => r#"[{"aracPlaka": "58 AC 123", "mevcutlat": "39.7510", "mevcutlng": "37.0160"},
+{"aracPlaka": "58 AB 999", "mevcutlat": "39.7600", "mevcutlng": "37.0300"}]"#
+            .to_string(),
+        "107" => r#"[{"aracPlaka": "58 KP 007", "mevcutlat": "39.7200", "mevcutlng": "37.0350"}]"#
+            .to_string(),
+        _ => "[]".to_string(),
+    }
+}
+
+pub fn board_json(station: &str) -> String {
+    match station {
+        "10" => {
+            r#"[{"plaka": "58 AC 123", "sure": 4}, {"plaka": "58 AB 999", "sure": 11}]"#.to_string()
+        }
+        "12" => r#"[{"plaka": "58 KP 007", "sure": 2}]"#.to_string(),
+        _ => "[]".to_string(),
+    }
+}
+
+/// The default response for `request`.
+pub fn fixture(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => Response::html(home_page()),
+        ("GET", "/hat/1") => Response::html(line_page_with_media()),
+        ("GET", "/hat/7A") => Response::html(line_page(107, &[12, 13])),
+        ("GET", "/hat/999") => Response::redirect("/Hata/404"),
+        ("GET", "/Akilli-Duraklar-Harita") => Response::html(format!(
+            "<script>var duraks = {};</script>",
+            stations_json(&[10, 11, 12, 13])
+        )),
+        ("GET", "/uploads/hat-1-guzergah.png") => Response::new(200, "image/png", PNG),
+        ("GET", "/uploads/hat-1-tarife.pdf") => Response::new(200, "application/pdf", PDF),
+        ("GET", path) if path.starts_with("/Akilli-Durak/") => Response::html(token_input()),
+        ("POST", "/aractekrar") => {
+            Response::json(line_buses_json(&request.param("hgID").unwrap_or_default()))
+        }
+        ("POST", "/durakTekrar") => {
+            Response::json(board_json(&request.param("drkID").unwrap_or_default()))
+        }
+        _ => Response::not_found(),
+    }
+}
@@ -0,0 +1,93 @@
+//! Checks that the library doesn't pull in the binary's dependencies and
+//! that each feature builds on its own. The compile matrix is slow, run it
+//! with `cargo test --test features -- --ignored`.
+
+use std::process::Command;
+
+fn cargo(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "cargo {args:?} failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The package's normal dependencies with `features` on, as
+/// "name features" lines.
+fn tree(features: &[&str]) -> Vec<String> {
+    let mut args = vec!["tree", "-q", "-e", "normal", "--prefix", "none"];
+    args.extend(["-f", "{p} {f}", "-p", "sivasbus"]);
+    let features = features.join(",");
+    if !features.is_empty() {
+        args.extend(["--features", &features]);
+    }
+    cargo(&args).lines().map(str::to_string).collect()
+}
+
+fn has(tree: &[String], name: &str) -> bool {
+    tree.iter()
+        .any(|line| line.starts_with(&format!("{name} ")))
+}
+
+const CLI_ONLY: &[&str] = &[
+    "clap",
+    "comfy-table",
+    "humantime",
+    "indicatif",
+    "toml",
+    "tracing-subscriber",
+];
+
+#[test]
+fn library_has_no_cli_dependencies() {
+    let tree = tree(&[]);
+    for name in CLI_ONLY {
+        assert!(!has(&tree, name), "the library depends on {name}");
+    }
+    let tokio = tree.iter().find(|line| line.starts_with("tokio ")).unwrap();
+    for feature in ["rt-multi-thread", "macros", "signal", "process"] {
+        assert!(!tokio.contains(feature), "{tokio}");
+    }
+}
+
+#[test]
+fn cli_feature_brings_them_in() {
+    let tree = tree(&["cli"]);
+    for name in CLI_ONLY {
+        assert!(has(&tree, name), "the cli feature doesn't bring in {name}");
+    }
+}
+
+#[test]
+#[ignore = "compiles the crate once per feature"]
+fn each_feature_builds_on_its_own() {
+    let library = [
+        "envelope",
+        "uniffi",
+        "prometheus",
+        "csv",
+        "gtfs-static",
+        "geo-types",
+        "geojson",
+        "kml",
+        "server",
+        "sqlite",
+        "qr",
+        "gzip",
+        "brotli",
+        "socks",
+    ];
+    cargo(&["check", "-q", "--lib"]);
+    for feature in library {
+        cargo(&["check", "-q", "--lib", "--features", feature]);
+    }
+    for features in ["cli", "cli,tui", "cli,notify"] {
+        cargo(&["check", "-q", "--bin", "sivasbus", "--features", features]);
+    }
+}